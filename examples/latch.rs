@@ -0,0 +1,15 @@
+use digicir::circuit::Circuit;
+use digicir::node::NodeType;
+use digicir::propagation;
+
+//用两个与非门搭成的 RS 锁存器，按 1、2 切换 sn、rn，观察反馈如何稳定
+fn main() {
+    let mut circuit = Circuit::new(2);
+    let input = circuit.get_input();
+    //q、nq 互为对方与非门的输入，先给出初始值再由与非门驱动
+    let q = circuit.advance_output_with_value(&[0, 1]);
+    circuit.add_node(NodeType::NAnd, vec![input[0], q[1]], vec![q[0]]);
+    circuit.add_node(NodeType::NAnd, vec![input[1], q[0]], vec![q[1]]);
+    circuit.init_input(&[1, 1]);
+    propagation::run(circuit, &["sn", "rn"], &[("q", q[0]), ("nq", q[1])]);
+}
@@ -0,0 +1,692 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::logic::Logic;
+use crate::node::{Node, NodeType};
+
+//与 AIGER 相同：变量 v 的文字为 2v，取反为 2v + 1，0 和 1 是常量
+pub type AigLit = u32;
+
+pub const FALSE: AigLit = 0;
+pub const TRUE: AigLit = 1;
+
+#[derive(Debug, Error)]
+pub enum AigError {
+    #[error("line {0}: {1}")]
+    Format(usize, String),
+    #[error("signal {0} depends on an uninitialized signal")]
+    Undetermined(usize),
+    #[error("{0} has no AIGER representation")]
+    Unsupported(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+//变量依次为输入、latch、与门，与门的两个输入总是比输出小，可以直接写成二进制 AIGER
+#[derive(Debug, Clone, Default)]
+pub struct Aig {
+    input_size: usize,
+    //(下一状态, 初始值)，None 表示未初始化
+    latches: Vec<(AigLit, Option<bool>)>,
+    ands: Vec<(AigLit, AigLit)>,
+    outputs: Vec<AigLit>,
+    input_name: Vec<String>,
+    output_name: Vec<String>,
+    strash: HashMap<(AigLit, AigLit), AigLit>,
+}
+
+pub fn not(a: AigLit) -> AigLit { a ^ 1 }
+
+impl Aig {
+    pub fn new(input_size: usize, latch_size: usize) -> Aig {
+        Self {
+            input_size,
+            latches: vec![(FALSE, Some(false)); latch_size],
+            ..Default::default()
+        }
+    }
+
+    pub fn input(&self, index: usize) -> AigLit { 2 * (1 + index as AigLit) }
+
+    pub fn latch(&self, index: usize) -> AigLit { 2 * (1 + (self.input_size + index) as AigLit) }
+
+    pub fn input_size(&self) -> usize { self.input_size }
+    pub fn latch_size(&self) -> usize { self.latches.len() }
+    pub fn and_size(&self) -> usize { self.ands.len() }
+    pub fn max_var(&self) -> usize { self.input_size + self.latches.len() + self.ands.len() }
+
+    pub fn get_latches(&self) -> &Vec<(AigLit, Option<bool>)> { &self.latches }
+    pub fn get_ands(&self) -> &Vec<(AigLit, AigLit)> { &self.ands }
+    pub fn get_outputs(&self) -> &Vec<AigLit> { &self.outputs }
+
+    pub fn set_latch(&mut self, index: usize, next: AigLit, init: Option<bool>) {
+        self.latches[index] = (next, init);
+    }
+
+    pub fn add_output(&mut self, lit: AigLit) {
+        self.outputs.push(lit);
+    }
+
+    pub fn set_name(&mut self, input_name: &[&str], output_name: &[&str]) -> &mut Aig {
+        self.input_name = input_name.iter().map(|s| s.to_string()).collect();
+        self.output_name = output_name.iter().map(|s| s.to_string()).collect();
+        self
+    }
+    pub fn get_input_name(&self) -> &Vec<String> { &self.input_name }
+    pub fn get_output_name(&self) -> &Vec<String> { &self.output_name }
+
+    //结构哈希并做常量、相同输入、互补输入的化简
+    pub fn and(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        let (a, b) = if a > b { (a, b) } else { (b, a) };
+        if b == FALSE || a == not(b) { return FALSE; }
+        if b == TRUE || a == b { return a; }
+        if let Some(lit) = self.strash.get(&(a, b)) { return *lit; }
+        self.ands.push((a, b));
+        let lit = 2 * self.max_var() as AigLit;
+        self.strash.insert((a, b), lit);
+        lit
+    }
+
+    pub fn or(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        not(self.and(not(a), not(b)))
+    }
+
+    pub fn xor(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        let x = self.and(a, not(b));
+        let y = self.and(not(a), b);
+        self.or(x, y)
+    }
+
+    //s 为 1 时取 a，否则取 b
+    pub fn mux(&mut self, s: AigLit, a: AigLit, b: AigLit) -> AigLit {
+        let x = self.and(s, a);
+        let y = self.and(not(s), b);
+        self.or(x, y)
+    }
+
+    //同时按输入和当前状态求出各输出以及下一状态，未初始化的 latch 视为 0
+    pub fn simulate(&self, inputs: &[bool], state: &[bool]) -> (Vec<bool>, Vec<bool>) {
+        let mut values = vec![false; self.max_var() + 1];
+        values[1..=self.input_size].copy_from_slice(&inputs[..self.input_size]);
+        values[self.input_size + 1..=self.input_size + self.latches.len()].copy_from_slice(state);
+        let value = |values: &[bool], lit: AigLit| values[lit as usize >> 1] ^ (lit & 1 == 1);
+        for (i, (a, b)) in self.ands.iter().enumerate() {
+            values[self.input_size + self.latches.len() + 1 + i] = value(&values, *a) && value(&values, *b);
+        }
+        let outputs = self.outputs.iter().map(|o| value(&values, *o)).collect();
+        let next = self.latches.iter().map(|(n, _)| value(&values, *n)).collect();
+        (outputs, next)
+    }
+
+    fn write_header(&self, v: &mut String, magic: &str) {
+        writeln!(v, "{} {} {} {} {} {}", magic, self.max_var(), self.input_size,
+                 self.latches.len(), self.outputs.len(), self.ands.len()).unwrap();
+    }
+
+    fn write_latch(&self, v: &mut String, index: usize, ascii: bool) {
+        let (next, init) = self.latches[index];
+        if ascii { write!(v, "{} ", self.latch(index)).unwrap(); }
+        match init {
+            Some(false) => writeln!(v, "{}", next).unwrap(),
+            Some(true) => writeln!(v, "{} 1", next).unwrap(),
+            None => writeln!(v, "{} {}", next, self.latch(index)).unwrap(),
+        }
+    }
+
+    fn write_symbols(&self, v: &mut String) {
+        for (i, name) in self.input_name.iter().enumerate().take(self.input_size) {
+            writeln!(v, "i{} {}", i, name).unwrap();
+        }
+        for (i, name) in self.output_name.iter().enumerate().take(self.outputs.len()) {
+            writeln!(v, "o{} {}", i, name).unwrap();
+        }
+    }
+
+    pub fn to_ascii(&self) -> String {
+        let mut v = String::new();
+        self.write_header(&mut v, "aag");
+        for i in 0..self.input_size {
+            writeln!(v, "{}", self.input(i)).unwrap();
+        }
+        for i in 0..self.latches.len() {
+            self.write_latch(&mut v, i, true);
+        }
+        for o in &self.outputs {
+            writeln!(v, "{}", o).unwrap();
+        }
+        for (i, (a, b)) in self.ands.iter().enumerate() {
+            let lhs = 2 * (self.input_size + self.latches.len() + 1 + i);
+            writeln!(v, "{} {} {}", lhs, a, b).unwrap();
+        }
+        self.write_symbols(&mut v);
+        v
+    }
+
+    //与门按输出与两个输入的差值写成 7 位一组的变长整数
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut v = String::new();
+        self.write_header(&mut v, "aig");
+        for i in 0..self.latches.len() {
+            self.write_latch(&mut v, i, false);
+        }
+        for o in &self.outputs {
+            writeln!(v, "{}", o).unwrap();
+        }
+        let mut bytes = v.into_bytes();
+        for (i, (a, b)) in self.ands.iter().enumerate() {
+            let lhs = 2 * (self.input_size + self.latches.len() + 1 + i) as AigLit;
+            for mut delta in [lhs - a, a - b] {
+                while delta >= 0x80 {
+                    bytes.push((delta & 0x7f) as u8 | 0x80);
+                    delta >>= 7;
+                }
+                bytes.push(delta as u8);
+            }
+        }
+        let mut symbols = String::new();
+        self.write_symbols(&mut symbols);
+        bytes.extend(symbols.into_bytes());
+        bytes
+    }
+
+    //根据开头的 aag / aig 区分 ASCII 与二进制格式
+    pub fn parse(bytes: &[u8]) -> Result<Aig, AigError> {
+        Reader { bytes, pos: 0, line: 0 }.read()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Aig, AigError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    //扩展名为 .aag 时写成 ASCII，否则写成二进制
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AigError> {
+        let ascii = path.as_ref().extension().is_some_and(|e| e == "aag");
+        let bytes = if ascii { self.to_ascii().into_bytes() } else { self.to_binary() };
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    //每个 latch 是一个带初值的信号，在 pipeline 的最后统一更新，所以执行一次 execute_sequential 等于一个时钟周期
+    pub fn to_circuit(&self) -> (Circuit, Vec<usize>) {
+        let mut circuit = Circuit::new(self.input_size);
+        let mut signal = vec![0; self.max_var() + 1];
+        let constant = circuit.advance_output_with_value(&[0])[0];
+        signal[0] = constant;
+        for i in 0..self.input_size {
+            signal[i + 1] = i;
+        }
+        for (i, (_, init)) in self.latches.iter().enumerate() {
+            signal[self.input_size + 1 + i] = match init {
+                Some(value) => circuit.advance_output_with_value(&[*value as usize])[0],
+                None => circuit.advance_output(1)[0],
+            };
+        }
+
+        let mut inverted = HashMap::new();
+        let mut lit_signal = |circuit: &mut Circuit, signal: &[usize], lit: AigLit| {
+            let s = signal[lit as usize >> 1];
+            match lit & 1 {
+                0 => s,
+                _ => *inverted.entry(s).or_insert_with(|| circuit.add_gate_t(NodeType::Not, &[s])),
+            }
+        };
+        for (i, (a, b)) in self.ands.iter().enumerate() {
+            let inputs = [lit_signal(&mut circuit, &signal, *a), lit_signal(&mut circuit, &signal, *b)];
+            signal[self.input_size + self.latches.len() + 1 + i] = circuit.add_gate_t(NodeType::And, &inputs);
+        }
+        let outputs = self.outputs.iter()
+            .map(|o| lit_signal(&mut circuit, &signal, *o))
+            .collect::<Vec<usize>>();
+
+        //先取出所有下一状态，避免某个 latch 读到另一个已经更新的 latch
+        let latch_signals = (0..self.latches.len()).map(|i| signal[self.input_size + 1 + i]).collect::<Vec<usize>>();
+        let next = self.latches.iter()
+            .map(|(n, _)| {
+                let s = lit_signal(&mut circuit, &signal, *n);
+                if latch_signals.contains(&s) { circuit.add_gate_t(NodeType::And, &[s]) } else { s }
+            })
+            .collect::<Vec<usize>>();
+        for (latch, next) in latch_signals.into_iter().zip(next) {
+            circuit.add_node(NodeType::And, vec![next], vec![latch]);
+        }
+        (circuit, outputs)
+    }
+}
+
+impl Logic for Aig {
+    type Value = AigLit;
+
+    fn constant(&mut self, value: bool) -> AigLit { value as AigLit }
+
+    fn gate(&mut self, gate: &NodeType, inputs: &[AigLit]) -> AigLit {
+        match gate {
+            NodeType::And => inputs.iter().fold(TRUE, |acc, i| self.and(acc, *i)),
+            NodeType::Or => inputs.iter().fold(FALSE, |acc, i| self.or(acc, *i)),
+            NodeType::Not => not(inputs[0]),
+            NodeType::Xor => self.xor(inputs[0], inputs[1]),
+            NodeType::NAnd => not(inputs.iter().fold(TRUE, |acc, i| self.and(acc, *i))),
+            NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+        }
+    }
+}
+
+impl Circuit {
+    //在 pipeline 中先被读取后被驱动的信号，以及带反馈的 pattern 的输出，都保存了上一次执行的状态，成为 latch
+    //其余信号在一次执行中完全由输入和状态决定
+    //寄存器的每一位和它上一次看到的时钟各是一个 latch，与 execute_sequential_mut 一样，
+    //上升沿改变了寄存器时取用新值再执行一轮的结果；rom 展开成组合逻辑，ram 无法表示
+    pub fn to_aig(&self, outputs: &[usize]) -> Result<Aig, AigError> {
+        if let Some(node) = self.get_pipeline().iter().find(|node| matches!(node.get_type(), NodeType::Ram(..))) {
+            return Err(AigError::Unsupported(format!("{:?}", node.get_type())));
+        }
+        let registers = self.get_pipeline().iter()
+            .filter(|node| matches!(node.get_type(), NodeType::Register(_)))
+            .collect::<Vec<&Node>>();
+        let mut driver = vec![None; self.signals_size()];
+        for (k, node) in self.get_pipeline().iter().enumerate() {
+            for o in node.get_output() {
+                driver[*o] = Some(k);
+            }
+        }
+        let is_register = |k: usize| matches!(self.get_pipeline()[k].get_type(), NodeType::Register(_));
+        let mut state = Vec::new();
+        let mut seen = HashSet::new();
+        for (j, node) in self.get_pipeline().iter().enumerate() {
+            let sequential = match node.get_type() {
+                NodeType::Pattern(name) => self.get_pattern(name).is_sequential(),
+                //寄存器在一轮结束后才读取输入，输出由它自己的 latch 给出
+                NodeType::Register(_) => continue,
+                _ => false,
+            };
+            let held = node.get_input().iter().filter(|i| driver[**i].is_some_and(|k| k >= j && !is_register(k)));
+            let held = held.chain(node.get_output().iter().chain(node.get_state()).filter(|_| sequential));
+            for s in held.copied().collect::<Vec<usize>>() {
+                if seen.insert(s) { state.push(s); }
+            }
+        }
+
+        let input_size = self.get_input().len();
+        let register_size = registers.iter().map(|node| node.get_output().len() + 1).sum::<usize>();
+        let mut aig = Aig::new(input_size, state.len() + register_size);
+        let mut values = self.get_signals().iter()
+            .enumerate()
+            .map(|(i, s)| match i < input_size {
+                true => Some(aig.input(i)),
+                false => s.is_valid().then(|| aig.constant(s.get_unchecked())),
+            })
+            .collect::<Vec<Option<AigLit>>>();
+        for (i, s) in state.iter().enumerate() {
+            values[*s] = Some(aig.latch(i));
+        }
+        let mut base = state.len();
+        for node in &registers {
+            for (k, q) in node.get_output().iter().enumerate() {
+                values[*q] = Some(aig.latch(base + k));
+            }
+            base += node.get_output().len() + 1;
+        }
+
+        let first = self.evaluate_from(&mut aig, values);
+        let values = match registers.is_empty() {
+            true => first,
+            false => self.register_latches(&mut aig, &registers, state.len(), first)?,
+        };
+        for (i, s) in state.iter().enumerate() {
+            let next = values[*s].ok_or(AigError::Undetermined(*s))?;
+            let init = self.get_signal(*s);
+            aig.set_latch(i, next, init.is_valid().then(|| init.get_unchecked()));
+        }
+        for o in outputs {
+            aig.add_output(values[*o].ok_or(AigError::Undetermined(*o))?);
+        }
+        Ok(aig)
+    }
+
+    //从 base 开始设置寄存器的 latch，返回一轮结束时各信号的值
+    fn register_latches(
+        &self,
+        aig: &mut Aig,
+        registers: &[&Node],
+        mut base: usize,
+        first: Vec<Option<AigLit>>,
+    ) -> Result<Vec<Option<AigLit>>, AigError> {
+        let value = |s: usize| first[s].ok_or(AigError::Undetermined(s));
+        let mut second = first.clone();
+        let mut changed = FALSE;
+        for node in registers {
+            let (inputs, q) = (node.get_input(), node.get_output());
+            let clocked = self.get_states().get(&q[0]).is_some_and(|s| s.get_clock());
+            let stored = self.register_value(q[0]);
+            let clock = aig.latch(base + q.len());
+            let edge = aig.and(value(inputs[0])?, not(clock));
+            let (enable, reset) = (value(inputs[1])?, value(inputs[2])?);
+            for (k, (q, d)) in q.iter().zip(&inputs[3..]).enumerate() {
+                let old = aig.latch(base + k);
+                let load = aig.mux(enable, value(*d)?, old);
+                let write = aig.and(not(reset), load);
+                let new = aig.mux(edge, write, old);
+                let differ = aig.xor(new, old);
+                changed = aig.or(changed, differ);
+                aig.set_latch(base + k, new, Some(stored >> k & 1 == 1));
+                second[*q] = Some(new);
+            }
+            aig.set_latch(base + q.len(), value(inputs[0])?, Some(clocked));
+            base += q.len() + 1;
+        }
+        let second = self.evaluate_from(aig, second);
+        Ok(first.into_iter().zip(second)
+            .map(|(x, y)| match (x, y) {
+                (Some(x), Some(y)) => Some(aig.mux(changed, y, x)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl Reader<'_> {
+    fn error<T>(&self, message: &str) -> Result<T, AigError> {
+        Err(AigError::Format(self.line, message.to_string()))
+    }
+
+    fn next_line(&mut self) -> Result<&str, AigError> {
+        if self.pos >= self.bytes.len() { return self.error("unexpected end of file"); }
+        let rest = &self.bytes[self.pos..];
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        self.pos += end + 1;
+        self.line += 1;
+        match std::str::from_utf8(&rest[..end]) {
+            Ok(line) => Ok(line.trim_end_matches('\r')),
+            Err(_) => self.error("invalid text"),
+        }
+    }
+
+    fn numbers(&mut self, count: std::ops::RangeInclusive<usize>) -> Result<Vec<AigLit>, AigError> {
+        let line = self.next_line()?.to_string();
+        let numbers = line.split_whitespace().map(|w| w.parse::<AigLit>()).collect::<Result<Vec<AigLit>, _>>();
+        match numbers {
+            Ok(numbers) if count.contains(&numbers.len()) => Ok(numbers),
+            _ => self.error("malformed line"),
+        }
+    }
+
+    fn varint(&mut self) -> Result<AigLit, AigError> {
+        let mut value: AigLit = 0;
+        for shift in (0..35).step_by(7) {
+            let Some(byte) = self.bytes.get(self.pos) else { return self.error("unexpected end of file") };
+            self.pos += 1;
+            value |= ((byte & 0x7f) as AigLit) << shift;
+            if byte & 0x80 == 0 { return Ok(value); }
+        }
+        self.error("integer too large")
+    }
+
+    fn read(mut self) -> Result<Aig, AigError> {
+        let header = self.next_line()?.split_whitespace().map(|w| w.to_string()).collect::<Vec<String>>();
+        let binary = match header.first().map(|s| s.as_str()) {
+            Some("aag") => false,
+            Some("aig") => true,
+            _ => return self.error("expected aag or aig header"),
+        };
+        let sizes = header[1..].iter().map(|w| w.parse::<usize>()).collect::<Result<Vec<usize>, _>>();
+        let sizes = match sizes {
+            Ok(sizes) if sizes.len() >= 5 && sizes[5..].iter().all(|n| *n == 0) => sizes,
+            Ok(sizes) if sizes.len() >= 5 => return self.error("bad, constraint, justice and fairness sections are not supported"),
+            _ => return self.error("malformed header"),
+        };
+        let (m, i, l, o, a) = (sizes[0], sizes[1], sizes[2], sizes[3], sizes[4]);
+        if (binary && m < i + l + a) || m < i + l {
+            return self.error("maximum variable index too small");
+        }
+
+        //文件中的变量到新建图中文字的映射
+        let mut aig = Aig::new(i, l);
+        let mut map = HashMap::new();
+        for k in 0..i {
+            let var = if binary { k + 1 } else {
+                let lit = self.numbers(1..=1)?[0];
+                if lit & 1 == 1 || lit < 2 { return self.error("input must be a positive variable"); }
+                lit as usize >> 1
+            };
+            map.insert(var, aig.input(k));
+        }
+        let mut latches = Vec::new();
+        for k in 0..l {
+            let numbers = self.numbers(if binary { 1..=2 } else { 2..=3 })?;
+            let (var, rest) = if binary { (i + k + 1, &numbers[..]) } else { (numbers[0] as usize >> 1, &numbers[1..]) };
+            let init = match rest.get(1) {
+                None | Some(0) => Some(false),
+                Some(1) => Some(true),
+                Some(lit) if *lit as usize == 2 * var => None,
+                _ => return self.error("invalid latch initial value"),
+            };
+            map.insert(var, aig.latch(k));
+            latches.push((rest[0], init));
+        }
+        let outputs = (0..o).map(|_| self.numbers(1..=1).map(|n| n[0])).collect::<Result<Vec<AigLit>, AigError>>()?;
+
+        let mut defs = HashMap::new();
+        for k in 0..a {
+            if binary {
+                let lhs = 2 * (i + l + 1 + k) as AigLit;
+                let a = lhs.checked_sub(self.varint()?);
+                let b = a.and_then(|a| self.varint().ok().and_then(|d| a.checked_sub(d)));
+                let (Some(a), Some(b)) = (a, b) else { return self.error("invalid and gate delta") };
+                defs.insert(lhs as usize >> 1, (a, b));
+            } else {
+                let n = self.numbers(3..=3)?;
+                if n[0] & 1 == 1 { return self.error("and gate output must be a positive variable"); }
+                defs.insert(n[0] as usize >> 1, (n[1], n[2]));
+            }
+        }
+
+        let mut input_name = vec![String::new(); i];
+        let mut output_name = vec![String::new(); o];
+        while self.pos < self.bytes.len() {
+            let line = self.next_line()?.to_string();
+            if line == "c" { break; }
+            let Some((symbol, name)) = line.split_once(' ') else { continue };
+            let Some(k) = symbol.get(1..).and_then(|s| s.parse::<usize>().ok()) else {
+                return self.error("malformed symbol");
+            };
+            match symbol.as_bytes()[0] {
+                b'i' if k < i => input_name[k] = name.to_string(),
+                b'o' if k < o => output_name[k] = name.to_string(),
+                _ => {}
+            }
+        }
+
+        let line = self.line;
+        let mut resolve = |aig: &mut Aig, lit: AigLit| resolve(aig, &mut map, &defs, lit)
+            .ok_or_else(|| AigError::Format(line, format!("literal {} is undefined or cyclic", lit)));
+        for (k, (next, init)) in latches.into_iter().enumerate() {
+            let next = resolve(&mut aig, next)?;
+            aig.set_latch(k, next, init);
+        }
+        for output in outputs {
+            let output = resolve(&mut aig, output)?;
+            aig.add_output(output);
+        }
+        if input_name.iter().chain(&output_name).any(|n| !n.is_empty()) {
+            aig.input_name = input_name;
+            aig.output_name = output_name;
+        }
+        Ok(aig)
+    }
+}
+
+//按依赖顺序把文件中的与门加入图中，遇到未定义的变量或回路时返回 None
+fn resolve(
+    aig: &mut Aig,
+    map: &mut HashMap<usize, AigLit>,
+    defs: &HashMap<usize, (AigLit, AigLit)>,
+    lit: AigLit,
+) -> Option<AigLit> {
+    let lookup = |map: &HashMap<usize, AigLit>, lit: AigLit| match lit >> 1 {
+        0 => Some(lit & 1),
+        var => map.get(&(var as usize)).map(|l| l ^ (lit & 1)),
+    };
+    let mut stack = vec![lit as usize >> 1];
+    while let Some(&var) = stack.last() {
+        if var == 0 || map.contains_key(&var) {
+            stack.pop();
+            continue;
+        }
+        let (a, b) = *defs.get(&var)?;
+        let pending = [a as usize >> 1, b as usize >> 1].into_iter()
+            .filter(|v| *v != 0 && !map.contains_key(v))
+            .collect::<Vec<usize>>();
+        if pending.is_empty() {
+            let lit = aig.and(lookup(map, a)?, lookup(map, b)?);
+            map.insert(var, lit);
+            stack.pop();
+        } else if stack.len() > defs.len() {
+            return None;
+        } else {
+            stack.extend(pending);
+        }
+    }
+    lookup(map, lit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::Equivalence;
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn test_combinational() {
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input).get_output().to_vec();
+
+        let mut aig = circuit.to_aig(&sum).unwrap();
+        aig.set_name(&["c0", "a", "b"], &["s", "c1"]);
+        assert_eq!(aig.latch_size(), 0);
+        let ascii = aig.to_ascii();
+        assert!(ascii.starts_with(&format!("aag {} 3 0 2 {}\n2\n4\n6\n", aig.max_var(), aig.and_size())));
+        assert!(ascii.contains("i0 c0\n"));
+
+        for parsed in [Aig::parse(ascii.as_bytes()).unwrap(), Aig::parse(&aig.to_binary()).unwrap()] {
+            assert_eq!(parsed.get_ands(), aig.get_ands());
+            assert_eq!(parsed.get_outputs(), aig.get_outputs());
+            assert_eq!(parsed.get_output_name(), &["s", "c1"]);
+            let (other, outputs) = parsed.to_circuit();
+            assert_eq!(circuit.equivalent(&sum, &other, &outputs).unwrap(), Equivalence::Equal);
+        }
+
+        //ASCII 格式中的与门可以乱序、变量可以不连续
+        let shuffled = "aag 9 2 0 1 2\n2\n4\n19\n18 6 2\n6 4 3\n";
+        let parsed = Aig::parse(shuffled.as_bytes()).unwrap();
+        assert_eq!(parsed.to_ascii(), "aag 4 2 0 1 2\n2\n4\n9\n6 4 3\n8 6 2\n");
+        assert!(matches!(Aig::parse(b"aag 3 1 0 1 1\n2\n6\n6 6 2\n"), Err(AigError::Format(_, _))));
+        for symbol in [" a", "\u{e9}0 a", "ix a"] {
+            let src = format!("aag 1 1 0 1 0\n2\n2\n{}\n", symbol);
+            assert!(matches!(Aig::parse(src.as_bytes()), Err(AigError::Format(4, _))));
+        }
+    }
+
+    #[test]
+    fn test_sequential() {
+        let mut circuit = Circuit::new_with_basic_pattern(3);
+        let input = circuit.get_input();
+        let q = circuit.apply_flipflop("rsff", &input).get_output().to_vec();
+        let x = circuit.add_gate("xor", &[q[1], input[1]]);
+
+        let aig = circuit.to_aig(&[q[0], q[1], x]).unwrap();
+        assert_eq!(aig.latch_size(), 2);
+        let parsed = Aig::parse(&aig.to_binary()).unwrap();
+        //读入时按依赖顺序重新编号，与门的顺序可能不同
+        let init = |aig: &Aig| aig.get_latches().iter().map(|(_, init)| *init).collect::<Vec<Option<bool>>>();
+        assert_eq!(init(&parsed), init(&aig));
+        assert_eq!(parsed.and_size(), aig.and_size());
+        let (mut other, outputs) = parsed.to_circuit();
+
+        let mut state = aig.get_latches().iter().map(|(_, init)| init.unwrap()).collect::<Vec<bool>>();
+        for row in [[0, 1, 1], [0, 0, 0], [1, 1, 0], [0, 1, 0], [0, 1, 1], [1, 0, 1]] {
+            circuit.execute_sequential_mut(&row);
+            other.execute_sequential_mut(&row);
+            let expected = [q[0], q[1], x].map(|s| circuit.get_signal(s));
+            assert_eq!(outputs.iter().map(|o| other.get_signal(*o)).collect::<Vec<_>>(), expected);
+
+            let inputs = row.map(|v| v == 1);
+            let (values, next) = aig.simulate(&inputs, &state);
+            assert_eq!(values, expected.map(|s| s.get_unchecked()));
+            state = next;
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        //两位计数器，寄存器的输出由它自己的 latch 给出
+        let mut circuit = Circuit::new(3);
+        let input = circuit.get_input();
+        let d = circuit.advance_output_with_value(&[0, 0]);
+        let q = circuit.add_register(input[0], input[1], input[2], &d).unwrap();
+        circuit.add_node(NodeType::Not, vec![q[0]], vec![d[0]]);
+        circuit.add_node(NodeType::Xor, vec![q[0], q[1]], vec![d[1]]);
+        let carry = circuit.add_gate("and", &[q[0], q[1]]);
+
+        let aig = circuit.to_aig(&[q[0], q[1], carry]).unwrap();
+        assert_eq!(aig.latch_size(), 3);
+        let mut state = aig.get_latches().iter().map(|(_, init)| init.unwrap()).collect::<Vec<bool>>();
+        let rows = [[0, 1, 0], [1, 1, 0], [1, 1, 0], [0, 1, 0], [1, 1, 0], [0, 0, 0], [1, 0, 0],
+                    [0, 1, 0], [1, 1, 0], [0, 1, 0], [1, 1, 0], [0, 1, 1], [1, 1, 1], [0, 1, 0], [1, 1, 0]];
+        for row in rows {
+            circuit.execute_sequential_mut(&row);
+            let (values, next) = aig.simulate(&row.map(|v| v == 1), &state);
+            let expected = [q[0], q[1], carry].map(|s| circuit.get_signal(s).get_unchecked());
+            assert_eq!(values, expected, "{:?}", row);
+            state = next;
+        }
+        assert_eq!(circuit.register_value(q[0]), 1);
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut circuit = Circuit::new(2);
+        let input = circuit.get_input();
+        let q = circuit.add_rom(&input, 2, vec![1, 2, 3]).unwrap();
+        let aig = circuit.to_aig(&q).unwrap();
+        assert_eq!(aig.latch_size(), 0);
+        for (a, word) in [1, 2, 3, 0].into_iter().enumerate() {
+            let (values, _) = aig.simulate(&[a & 1 == 1, a >> 1 == 1], &[]);
+            assert_eq!(values, [word & 1 == 1, word >> 1 == 1], "address {}", a);
+        }
+
+        let mut circuit = Circuit::new(4);
+        let q = circuit.add_ram(0, 1, &[2], &[3]).unwrap();
+        assert!(matches!(circuit.to_aig(&q), Err(AigError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_hidden_state() {
+        //edff 的主锁存器是实例的内部状态，也要成为 latch
+        let mut circuit = Circuit::new(4);
+        Pattern::build_edge_dff(&mut circuit);
+        let input = circuit.get_input();
+        let outputs = circuit.advance_output_with_value(&[1, 0]);
+        let node = circuit.add_node(NodeType::Pattern("edff".to_string()), input, outputs.clone());
+        assert_eq!(node.get_state().len(), 1);
+
+        let aig = circuit.to_aig(&outputs).unwrap();
+        assert_eq!(aig.latch_size(), 3);
+        let mut state = aig.get_latches().iter().map(|(_, init)| init.unwrap()).collect::<Vec<bool>>();
+        for row in [[0, 0, 0, 1], [0, 0, 1, 1], [0, 0, 1, 0], [0, 0, 0, 0], [0, 0, 1, 0], [1, 0, 1, 0], [0, 1, 0, 1]] {
+            circuit.execute_sequential_mut(&row);
+            let (values, next) = aig.simulate(&row.map(|v| v == 1), &state);
+            assert_eq!(values, outputs.iter().map(|s| circuit.get_signal(*s).get_unchecked()).collect::<Vec<bool>>(), "{:?}", row);
+            state = next;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::arithmetic::{add_bits, named};
+use crate::circuit::Circuit;
+use crate::generator::{build, select_width};
+use crate::pattern::Pattern;
+
+//op 的取值依次对应的运算
+pub const ALU_OPS: [&str; 8] = ["add", "sub", "and", "or", "xor", "shl", "shr", "cmp"];
+
+//桶形移位器：第 j 级在 amount[j] 为 1 时移动 2^j 位，移出的位补 0
+fn shift(circuit: &mut Circuit, a: &[usize], amount: &[usize], left: bool) -> Vec<usize> {
+    let n = a.len();
+    let mut current = a.to_vec();
+    for (j, bit) in amount.iter().enumerate() {
+        let nbit = circuit.add_gate("not", &[*bit]);
+        let distance = 1 << j;
+        current = (0..n)
+            .map(|i| {
+                let keep = circuit.add_gate("and", &[current[i], nbit]);
+                let source = if left { i.checked_sub(distance) } else { Some(i + distance).filter(|s| *s < n) };
+                match source {
+                    Some(s) => {
+                        let moved = circuit.add_gate("and", &[current[s], *bit]);
+                        circuit.add_gate("or", &[keep, moved])
+                    }
+                    None => keep,
+                }
+            })
+            .collect();
+    }
+    current
+}
+
+impl Pattern {
+    //n 位 ALU alu{n}：输入 a[], b[], op[0..3]，输出 y[] 和 zero, carry, overflow, negative。
+    //移位的位数取 b 的低位；cmp 的 y 为 a < b（无符号），标志位与 sub 相同；n 至少为 1
+    pub fn build_alu(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "alu needs at least 1 bit");
+        let input_name = [Self::port_names("a", n), Self::port_names("b", n), Self::port_names("op", 3)].concat();
+        let name = build(circuit, &format!("alu{}", n), &input_name, |circuit, inputs| {
+            let (a, b, op) = (&inputs[..n], &inputs[n..2 * n], &inputs[2 * n..]);
+            let nop = op.iter().map(|x| circuit.add_gate("not", &[*x])).collect::<Vec<usize>>();
+            let decode = (0..ALU_OPS.len())
+                .map(|k| {
+                    let select = (0..3).map(|j| if k >> j & 1 == 1 { op[j] } else { nop[j] }).collect::<Vec<usize>>();
+                    circuit.add_gate("and", &select)
+                })
+                .collect::<Vec<usize>>();
+            let (add, sub, and, or, xor, shl, shr, cmp) =
+                (decode[0], decode[1], decode[2], decode[3], decode[4], decode[5], decode[6], decode[7]);
+
+            //加减法共用一个加法器，减法把 b 取反并从最低位加 1
+            let subtract = circuit.add_gate("or", &[sub, cmp]);
+            let mut carry = vec![subtract];
+            let mut sum = Vec::new();
+            for i in 0..n {
+                let y = circuit.add_gate("xor", &[b[i], subtract]);
+                let (s, c) = add_bits(circuit, &[a[i], y, carry[i]]);
+                sum.push(s);
+                carry.push(c.unwrap());
+            }
+            let overflow = circuit.add_gate("xor", &[carry[n], carry[n - 1]]);
+            let left = shift(circuit, a, &b[..select_width(n).min(n)], true);
+            let right = shift(circuit, a, &b[..select_width(n).min(n)], false);
+            let borrow = circuit.add_gate("not", &[carry[n]]);
+
+            let arithmetic = circuit.add_gate("or", &[add, sub]);
+            let y = (0..n)
+                .map(|i| {
+                    let mut terms = vec![
+                        circuit.add_gate("and", &[arithmetic, sum[i]]),
+                        circuit.add_gate("and", &[and, a[i], b[i]]),
+                        circuit.add_gate("and", &[or, a[i]]),
+                        circuit.add_gate("and", &[or, b[i]]),
+                    ];
+                    let x = circuit.add_gate("xor", &[a[i], b[i]]);
+                    terms.push(circuit.add_gate("and", &[xor, x]));
+                    terms.push(circuit.add_gate("and", &[shl, left[i]]));
+                    terms.push(circuit.add_gate("and", &[shr, right[i]]));
+                    if i == 0 {
+                        terms.push(circuit.add_gate("and", &[cmp, borrow]));
+                    }
+                    circuit.add_gate("or", &terms)
+                })
+                .collect::<Vec<usize>>();
+
+            //cmp 的 zero 和 negative 看差值而不是 y
+            let ncmp = circuit.add_gate("not", &[cmp]);
+            let flag_source = (0..n)
+                .map(|i| {
+                    let result = circuit.add_gate("and", &[ncmp, y[i]]);
+                    let difference = circuit.add_gate("and", &[cmp, sum[i]]);
+                    circuit.add_gate("or", &[result, difference])
+                })
+                .collect::<Vec<usize>>();
+            let any = circuit.add_gate("or", &flag_source);
+            let zero = circuit.add_gate("not", &[any]);
+            let uses_adder = circuit.add_gate("or", &[add, sub, cmp]);
+            let carry = circuit.add_gate("and", &[uses_adder, carry[n]]);
+            let overflow = circuit.add_gate("and", &[uses_adder, overflow]);
+            let negative = flag_source[n - 1];
+            let flags = [("zero", zero), ("carry", carry), ("overflow", overflow), ("negative", negative)];
+            [named("y", &y), flags.map(|(f, s)| (f.to_string(), s)).to_vec()].concat()
+        });
+        let description = ALU_OPS.iter().enumerate().map(|(k, op)| format!("{} {}", k, op)).collect::<Vec<String>>();
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description(&format!("op: {}", description.join(", ")));
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+
+    //返回 (y, zero, carry, overflow, negative)
+    fn model(op: u64, a: u64, b: u64, n: usize) -> (u64, bool, bool, bool, bool) {
+        let mask = (1 << n) - 1;
+        let sign = |x: u64| if x >> (n - 1) & 1 == 1 { x as i64 - (1 << n) } else { x as i64 };
+        let fits = |x: i64| x >= -(1 << (n - 1)) && x < 1 << (n - 1);
+        let amount = b & ((1 << select_width(n)) - 1);
+        let (sum, difference) = (a + b, a + (!b & mask) + 1);
+        let flags = |y: u64| (y, y == 0, false, false, y >> (n - 1) & 1 == 1);
+        match ALU_OPS[op as usize] {
+            "add" => (sum & mask, sum & mask == 0, sum >> n == 1, !fits(sign(a) + sign(b)), sum >> (n - 1) & 1 == 1),
+            "sub" => (difference & mask, difference & mask == 0, difference >> n == 1, !fits(sign(a) - sign(b)), difference >> (n - 1) & 1 == 1),
+            "and" => flags(a & b),
+            "or" => flags(a | b),
+            "xor" => flags(a ^ b),
+            "shl" => flags((a << amount) & mask),
+            "shr" => flags(a >> amount),
+            _ => ((a < b) as u64, a == b, difference >> n == 1, !fits(sign(a) - sign(b)), difference >> (n - 1) & 1 == 1),
+        }
+    }
+
+    #[test]
+    fn test_alu() {
+        for n in [1, 3, 4] {
+            let mut circuit = Circuit::new(2 * n + 3);
+            let name = Pattern::build_alu(&mut circuit, n);
+            assert_eq!(name, format!("alu{}", n));
+            let a = circuit.input_bus("a", 0..n);
+            let b = circuit.input_bus("b", n..2 * n);
+            circuit.input_bus("op", 2 * n..2 * n + 3);
+            let inputs = circuit.get_input();
+            let outputs = circuit.apply_pattern(&name, &inputs).get_output().to_vec();
+            circuit.add_bus(Bus::new("y", &outputs[..n]));
+            let flag = |circuit: &Circuit, k: usize| circuit.get_signal(outputs[n + k]).get_unchecked();
+
+            for op in 0..8u64 {
+                for x in 0..1u64 << n {
+                    for y in 0..1u64 << n {
+                        circuit.set("op", op).unwrap();
+                        circuit.set_bus(&a, x).unwrap();
+                        circuit.set_bus(&b, y).unwrap();
+                        circuit.execute_mut();
+                        let result = (circuit.get_u64("y").unwrap(), flag(&circuit, 0), flag(&circuit, 1), flag(&circuit, 2), flag(&circuit, 3));
+                        assert_eq!(result, model(op, x, y, n), "{} {} {} on {} bits", ALU_OPS[op as usize], x, y, n);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1 bit")]
+    fn test_zero_width() {
+        Pattern::build_alu(&mut Circuit::new(3), 0);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::circuit::Circuit;
+use crate::generator::build;
+use crate::pattern::Pattern;
+
+//把 1 到 3 位相加：一位原样返回，两位为半加器，三位为全加器；返回 (和, 进位)
+pub(crate) fn add_bits(circuit: &mut Circuit, bits: &[usize]) -> (usize, Option<usize>) {
+    match bits {
+        [x] => (*x, None),
+        [x, y] => (circuit.add_gate("xor", &[*x, *y]), Some(circuit.add_gate("and", &[*x, *y]))),
+        [x, y, c] => {
+            let p = circuit.add_gate("xor", &[*x, *y]);
+            let s = circuit.add_gate("xor", &[p, *c]);
+            let g = circuit.add_gate("and", &[*x, *y]);
+            let t = circuit.add_gate("and", &[p, *c]);
+            (s, Some(circuit.add_gate("or", &[g, t])))
+        }
+        _ => unreachable!(),
+    }
+}
+
+//pattern 中没有常量，用 x and not(x) 得到 0
+pub(crate) fn zero(circuit: &mut Circuit, x: usize) -> usize {
+    let nx = circuit.add_gate("not", &[x]);
+    circuit.add_gate("and", &[x, nx])
+}
+
+pub(crate) fn named(name: &str, bits: &[usize]) -> Vec<(String, usize)> {
+    Pattern::port_names(name, bits.len()).into_iter().zip(bits.iter().copied()).collect()
+}
+
+impl Pattern {
+    //n 位超前进位加法器 cla{n}，端口与 adder{n} 相同；每一位的进位直接由 g、p 两级展开得到
+    pub fn build_cla_adder(circuit: &mut Circuit, n: usize) -> String {
+        let input_name = [vec!["ci".to_string()], Self::port_names("a", n), Self::port_names("b", n)].concat();
+        let name = build(circuit, &format!("cla{}", n), &input_name, |circuit, inputs| {
+            let (a, b) = (&inputs[1..n + 1], &inputs[n + 1..]);
+            let p = (0..n).map(|i| circuit.add_gate("xor", &[a[i], b[i]])).collect::<Vec<usize>>();
+            let g = (0..n).map(|i| circuit.add_gate("and", &[a[i], b[i]])).collect::<Vec<usize>>();
+            //c[i+1] = g[i] + p[i]g[i-1] + ... + p[i]..p[0]ci
+            let mut carry = vec![inputs[0]];
+            for i in 0..n {
+                let terms = (0..=i + 1)
+                    .map(|j| {
+                        let generate = if j == 0 { inputs[0] } else { g[j - 1] };
+                        match j {
+                            j if j == i + 1 => generate,
+                            j => circuit.add_gate("and", &[&[generate], &p[j..=i]].concat()),
+                        }
+                    })
+                    .collect::<Vec<usize>>();
+                carry.push(circuit.add_gate("or", &terms));
+            }
+            let s = (0..n).map(|i| circuit.add_gate("xor", &[p[i], carry[i]])).collect::<Vec<usize>>();
+            [named("s", &s), vec![("co".to_string(), carry[n])]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description(&format!("ci + a + b = co s, {} bits, carry lookahead", n));
+        name
+    }
+
+    //n 位加减法器 addsub{n}：sub 为 0 时 s = a + b，为 1 时 s = a - b；ov 为有符号溢出，n 至少为 1
+    pub fn build_adder_subtractor(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "addsub needs at least 1 bit");
+        let input_name = [vec!["sub".to_string()], Self::port_names("a", n), Self::port_names("b", n)].concat();
+        let name = build(circuit, &format!("addsub{}", n), &input_name, |circuit, inputs| {
+            let (sub, a, b) = (inputs[0], &inputs[1..n + 1], &inputs[n + 1..]);
+            let mut carry = vec![sub];
+            let mut s = Vec::new();
+            for i in 0..n {
+                let y = circuit.add_gate("xor", &[b[i], sub]);
+                let (sum, c) = add_bits(circuit, &[a[i], y, carry[i]]);
+                s.push(sum);
+                carry.push(c.unwrap());
+            }
+            let ov = circuit.add_gate("xor", &[carry[n], carry[n - 1]]);
+            [named("s", &s), vec![("co".to_string(), carry[n]), ("ov".to_string(), ov)]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("s = a + b when not sub else a - b");
+        name
+    }
+
+    //n 位阵列乘法器 multiplier{n}：p[0..2n] = a * b，逐行把部分积加到累加结果上，n 至少为 1
+    pub fn build_multiplier(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "multiplier needs at least 1 bit");
+        let input_name = [Self::port_names("a", n), Self::port_names("b", n)].concat();
+        let name = build(circuit, &format!("multiplier{}", n), &input_name, |circuit, inputs| {
+            let (a, b) = (&inputs[..n], &inputs[n..]);
+            let row = |circuit: &mut Circuit, i: usize| (0..n).map(|j| circuit.add_gate("and", &[a[j], b[i]])).collect::<Vec<usize>>();
+            //acc 为当前行对应的 n 位，最高位之上可能还有一位进位
+            let mut acc = row(circuit, 0);
+            let mut top = None;
+            let mut p = Vec::new();
+            for i in 1..n {
+                p.push(acc[0]);
+                let pp = row(circuit, i);
+                let mut carry = None;
+                let mut next = Vec::new();
+                for j in 0..n {
+                    let x = if j + 1 < n { Some(acc[j + 1]) } else { top };
+                    let bits = [x, Some(pp[j]), carry].into_iter().flatten().collect::<Vec<usize>>();
+                    let (s, c) = add_bits(circuit, &bits);
+                    next.push(s);
+                    carry = c;
+                }
+                acc = next;
+                top = carry;
+            }
+            p.extend(acc);
+            let top = top.unwrap_or_else(|| zero(circuit, a[0]));
+            p.push(top);
+            named("p", &p)
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("p = a * b");
+        name
+    }
+
+    //n 位加一器 incrementer{n}：s = a + 1，co 为进位，n 至少为 1
+    pub fn build_incrementer(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "incrementer needs at least 1 bit");
+        let input_name = Self::port_names("a", n);
+        let name = build(circuit, &format!("incrementer{}", n), &input_name, |circuit, a| {
+            let mut s = vec![circuit.add_gate("not", &[a[0]])];
+            let mut carry = a[0];
+            for x in &a[1..] {
+                let (sum, c) = add_bits(circuit, &[*x, carry]);
+                s.push(sum);
+                carry = c.unwrap();
+            }
+            [named("s", &s), vec![("co".to_string(), carry)]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("s = a + 1");
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bits, instance, read};
+
+    //穷举 pattern 的所有输入，输入和输出都按端口顺序从低位到高位组成整数
+    fn exhaust(circuit: &mut Circuit, name: &str, expected: impl Fn(usize) -> usize) {
+        let pattern = circuit.get_pattern(name);
+        let input_size = pattern.input_size();
+        let mut test = Circuit::new(input_size);
+        test.get_patterns_mut().insert(name.to_string(), pattern.clone());
+        let outputs = instance(&mut test, name);
+        for v in 0..1 << input_size {
+            test.execute_sequential_mut(&bits(v, input_size));
+            assert_eq!(read(&test, &outputs), expected(v), "{} with input {:b}", name, v);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut circuit = Circuit::new(0);
+        for n in 1..=4 {
+            let mask = (1 << n) - 1;
+            let split = |v: usize| (v & 1, v >> 1 & mask, v >> (n + 1));
+
+            for name in [Pattern::build_adder(&mut circuit, n), Pattern::build_cla_adder(&mut circuit, n)] {
+                exhaust(&mut circuit, &name, |v| {
+                    let (ci, a, b) = split(v);
+                    ci + a + b
+                });
+            }
+
+            let name = Pattern::build_adder_subtractor(&mut circuit, n);
+            exhaust(&mut circuit, &name, |v| {
+                let (sub, a, b) = split(v);
+                let sign = |x: usize| if x >> (n - 1) & 1 == 1 { x as isize - (1 << n) } else { x as isize };
+                let (sum, exact) = match sub {
+                    0 => (a + b, sign(a) + sign(b)),
+                    _ => (a + (!b & mask) + 1, sign(a) - sign(b)),
+                };
+                let ov = exact < -(1 << (n - 1)) || exact >= 1 << (n - 1);
+                (sum & ((mask << 1) | 1)) | (ov as usize) << (n + 1)
+            });
+
+            let name = Pattern::build_comparator(&mut circuit, n);
+            exhaust(&mut circuit, &name, |v| {
+                let (a, b) = (v & mask, v >> n);
+                (a < b) as usize | ((a == b) as usize) << 1 | ((a > b) as usize) << 2
+            });
+
+            let name = Pattern::build_incrementer(&mut circuit, n);
+            exhaust(&mut circuit, &name, |a| a + 1);
+
+            let name = Pattern::build_multiplier(&mut circuit, n);
+            assert_eq!(circuit.get_pattern(&name).output_size(), 2 * n);
+            exhaust(&mut circuit, &name, |v| (v & mask) * (v >> n));
+        }
+        assert_eq!(circuit.get_pattern("addsub4").get_output_name()[4..], ["co", "ov"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1 bit")]
+    fn test_zero_width() {
+        Pattern::build_incrementer(&mut Circuit::new(0), 0);
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::logic::Logic;
+use crate::pattern::Pattern;
+
+#[derive(Debug, Error)]
+pub enum BddError {
+    #[error("order is not a permutation of {0} variables")]
+    Order(usize),
+    #[error("output {0} depends on an uninitialized signal")]
+    Undetermined(usize),
+    #[error("{0} inputs do not fit in {1} variables")]
+    TooManyInputs(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BddNode {
+    var: usize,
+    low: usize,
+    high: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BddOp { And, Or, Xor }
+
+//约简有序二元决策图，函数以节点下标表示，相同的函数一定得到相同的下标
+#[derive(Debug)]
+pub struct Bdd {
+    var_count: usize,
+    //order[level] 为该层的变量，level[var] 为变量所在的层
+    order: Vec<usize>,
+    level: Vec<usize>,
+    var_name: Vec<String>,
+    nodes: Vec<BddNode>,
+    unique: HashMap<BddNode, usize>,
+    cache: HashMap<(BddOp, usize, usize), usize>,
+}
+
+impl Bdd {
+    pub const FALSE: usize = 0;
+    pub const TRUE: usize = 1;
+
+    pub fn new(var_count: usize) -> Bdd {
+        let terminal = BddNode { var: usize::MAX, low: 0, high: 0 };
+        Self {
+            var_count,
+            order: (0..var_count).collect(),
+            level: (0..var_count).collect(),
+            var_name: (0..var_count).map(|i| format!("x{}", i)).collect(),
+            nodes: vec![terminal, BddNode { high: 1, low: 1, ..terminal }],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    //order 从根到叶给出变量的顺序
+    pub fn with_order(order: &[usize]) -> Result<Bdd, BddError> {
+        let mut bdd = Self::new(order.len());
+        let mut level = vec![usize::MAX; order.len()];
+        for (l, var) in order.iter().enumerate() {
+            match level.get_mut(*var) {
+                Some(x) if *x == usize::MAX => *x = l,
+                _ => return Err(BddError::Order(order.len())),
+            }
+        }
+        bdd.order = order.to_vec();
+        bdd.level = level;
+        Ok(bdd)
+    }
+
+    //按从输出深度优先回溯时遇到输入的先后排序，相关的输入会排得比较近
+    pub fn dfs_order(circuit: &Circuit, outputs: &[usize]) -> Vec<usize> {
+        fn visit(circuit: &Circuit, signal: usize, seen: &mut HashSet<usize>, order: &mut Vec<usize>) {
+            if !seen.insert(signal) { return; }
+            if signal < circuit.get_input().len() {
+                order.push(signal);
+            } else if let Some(node) = circuit.get_parent_node(signal) {
+                for input in node.get_input() {
+                    visit(circuit, *input, seen, order);
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        let mut order = Vec::new();
+        for output in outputs {
+            visit(circuit, *output, &mut seen, &mut order);
+        }
+        let rest = circuit.get_input().into_iter()
+            .filter(|i| !order.contains(i))
+            .collect::<Vec<usize>>();
+        order.extend(rest);
+        order
+    }
+
+    pub fn var_count(&self) -> usize { self.var_count }
+    pub fn node_count(&self) -> usize { self.nodes.len() }
+    pub fn get_order(&self) -> &Vec<usize> { &self.order }
+
+    pub fn set_var_name(&mut self, names: &[&str]) -> &mut Bdd {
+        for (var, name) in names.iter().enumerate().take(self.var_count) {
+            self.var_name[var] = name.to_string();
+        }
+        self
+    }
+
+    //以 f 为根的子图大小，包括两个终结点
+    pub fn size(&self, f: usize) -> usize {
+        self.reachable(&[f]).len()
+    }
+
+    fn level_of(&self, f: usize) -> usize {
+        if f <= Self::TRUE { self.var_count } else { self.level[self.nodes[f].var] }
+    }
+
+    //f 按第 level 层的变量展开后的两个余因子
+    fn cofactor(&self, f: usize, level: usize) -> (usize, usize) {
+        if self.level_of(f) == level {
+            (self.nodes[f].low, self.nodes[f].high)
+        } else {
+            (f, f)
+        }
+    }
+
+    fn mk(&mut self, var: usize, low: usize, high: usize) -> usize {
+        if low == high { return low; }
+        let node = BddNode { var, low, high };
+        if let Some(i) = self.unique.get(&node) { return *i; }
+        self.nodes.push(node);
+        self.unique.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn var(&mut self, var: usize) -> usize {
+        assert!(var < self.var_count, "variable {} out of range", var);
+        self.mk(var, Self::FALSE, Self::TRUE)
+    }
+
+    pub fn constant(&self, value: bool) -> usize {
+        if value { Self::TRUE } else { Self::FALSE }
+    }
+
+    pub fn not(&mut self, f: usize) -> usize { self.xor(f, Self::TRUE) }
+    pub fn and(&mut self, f: usize, g: usize) -> usize { self.apply(BddOp::And, f, g) }
+    pub fn or(&mut self, f: usize, g: usize) -> usize { self.apply(BddOp::Or, f, g) }
+    pub fn xor(&mut self, f: usize, g: usize) -> usize { self.apply(BddOp::Xor, f, g) }
+
+    pub fn ite(&mut self, f: usize, g: usize, h: usize) -> usize {
+        let t = self.and(f, g);
+        let nf = self.not(f);
+        let e = self.and(nf, h);
+        self.or(t, e)
+    }
+
+    pub fn apply(&mut self, op: BddOp, f: usize, g: usize) -> usize {
+        let terminal = match op {
+            BddOp::And if f == Self::FALSE || g == Self::FALSE => Some(Self::FALSE),
+            BddOp::And if f == Self::TRUE => Some(g),
+            BddOp::And if g == Self::TRUE || f == g => Some(f),
+            BddOp::Or if f == Self::TRUE || g == Self::TRUE => Some(Self::TRUE),
+            BddOp::Or if f == Self::FALSE => Some(g),
+            BddOp::Or if g == Self::FALSE || f == g => Some(f),
+            BddOp::Xor if f == g => Some(Self::FALSE),
+            BddOp::Xor if f == Self::FALSE => Some(g),
+            BddOp::Xor if g == Self::FALSE => Some(f),
+            _ => None,
+        };
+        if let Some(res) = terminal { return res; }
+
+        //三种运算都满足交换律
+        let key = (op, f.min(g), f.max(g));
+        if let Some(res) = self.cache.get(&key) { return *res; }
+
+        let level = self.level_of(f).min(self.level_of(g));
+        let (f0, f1) = self.cofactor(f, level);
+        let (g0, g1) = self.cofactor(g, level);
+        let low = self.apply(op, f0, g0);
+        let high = self.apply(op, f1, g1);
+        let res = self.mk(self.order[level], low, high);
+        self.cache.insert(key, res);
+        res
+    }
+
+    //令变量 var 取 value
+    pub fn restrict(&mut self, f: usize, var: usize, value: bool) -> usize {
+        self.restrict_inner(f, var, value, &mut HashMap::new())
+    }
+
+    fn restrict_inner(&mut self, f: usize, var: usize, value: bool, memo: &mut HashMap<usize, usize>) -> usize {
+        if self.level_of(f) > self.level[var] { return f; }
+        if let Some(res) = memo.get(&f) { return *res; }
+        let node = self.nodes[f];
+        let res = if node.var == var {
+            if value { node.high } else { node.low }
+        } else {
+            let low = self.restrict_inner(node.low, var, value, memo);
+            let high = self.restrict_inner(node.high, var, value, memo);
+            self.mk(node.var, low, high)
+        };
+        memo.insert(f, res);
+        res
+    }
+
+    //用函数 g 代替 f 中的变量 var
+    pub fn compose(&mut self, f: usize, var: usize, g: usize) -> usize {
+        let high = self.restrict(f, var, true);
+        let low = self.restrict(f, var, false);
+        self.ite(g, high, low)
+    }
+
+    pub fn is_tautology(&self, f: usize) -> bool { f == Self::TRUE }
+
+    //使 f 为真的赋值个数（即最小项个数），变量超过 127 个时会溢出
+    pub fn sat_count(&self, f: usize) -> u128 {
+        fn count(bdd: &Bdd, f: usize, memo: &mut HashMap<usize, u128>) -> u128 {
+            if f <= Bdd::TRUE { return f as u128; }
+            if let Some(c) = memo.get(&f) { return *c; }
+            let node = bdd.nodes[f];
+            let level = bdd.level_of(f);
+            let c = (count(bdd, node.low, memo) << (bdd.level_of(node.low) - level - 1))
+                + (count(bdd, node.high, memo) << (bdd.level_of(node.high) - level - 1));
+            memo.insert(f, c);
+            c
+        }
+        count(self, f, &mut HashMap::new()) << self.level_of(f)
+    }
+
+    //f 的所有路径，每条路径上未出现的变量为 None
+    pub fn all_sat(&self, f: usize) -> Vec<Vec<Option<bool>>> {
+        fn walk(bdd: &Bdd, f: usize, path: &mut Vec<Option<bool>>, res: &mut Vec<Vec<Option<bool>>>) {
+            if f == Bdd::FALSE { return; }
+            if f == Bdd::TRUE {
+                res.push(path.clone());
+                return;
+            }
+            let node = bdd.nodes[f];
+            for (value, child) in [(false, node.low), (true, node.high)] {
+                path[node.var] = Some(value);
+                walk(bdd, child, path, res);
+            }
+            path[node.var] = None;
+        }
+        let mut res = Vec::new();
+        walk(self, f, &mut vec![None; self.var_count], &mut res);
+        res
+    }
+
+    //逐个列出使 f 为真的完整赋值
+    pub fn enumerate(&self, f: usize) -> impl Iterator<Item = Vec<bool>> {
+        self.all_sat(f).into_iter().flat_map(|cube| {
+            let free = cube.iter()
+                .enumerate()
+                .filter(|(_, v)| v.is_none())
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>();
+            (0..1usize << free.len()).map(move |bits| {
+                let mut assignment = cube.iter().map(|v| v.unwrap_or(false)).collect::<Vec<bool>>();
+                for (k, var) in free.iter().enumerate() {
+                    assignment[*var] = bits >> (free.len() - k - 1) & 1 == 1;
+                }
+                assignment
+            })
+        })
+    }
+
+    fn reachable(&self, roots: &[usize]) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut stack = roots.to_vec();
+        let mut res = Vec::new();
+        while let Some(f) = stack.pop() {
+            if !seen.insert(f) { continue; }
+            res.push(f);
+            if f > Self::TRUE {
+                stack.push(self.nodes[f].low);
+                stack.push(self.nodes[f].high);
+            }
+        }
+        res.sort();
+        res
+    }
+
+    //虚线为取 0 的分支，实线为取 1 的分支
+    pub fn to_dot(&self, roots: &[(&str, usize)]) -> String {
+        let nodes = self.reachable(&roots.iter().map(|(_, f)| *f).collect::<Vec<usize>>());
+        let mut dot = String::from("digraph bdd {\n");
+        for (name, f) in roots {
+            writeln!(dot, "    \"{}\" [shape=plaintext];", name).unwrap();
+            writeln!(dot, "    \"{}\" -> n{};", name, f).unwrap();
+        }
+        for f in &nodes {
+            if *f <= Self::TRUE {
+                writeln!(dot, "    n{} [shape=box, label=\"{}\"];", f, f).unwrap();
+                continue;
+            }
+            let node = self.nodes[*f];
+            writeln!(dot, "    n{} [shape=circle, label=\"{}\"];", f, self.var_name[node.var]).unwrap();
+            writeln!(dot, "    n{} -> n{} [style=dashed];", f, node.low).unwrap();
+            writeln!(dot, "    n{} -> n{};", f, node.high).unwrap();
+        }
+        for var in &self.order {
+            let same = nodes.iter()
+                .filter(|f| **f > Self::TRUE && self.nodes[**f].var == *var)
+                .map(|f| format!("n{}", f))
+                .collect::<Vec<String>>();
+            if !same.is_empty() {
+                writeln!(dot, "    {{ rank=same; {}; }}", same.join("; ")).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    //输入 i 对应变量 i，输入不能比变量多
+    fn input_vars(&mut self, input_size: usize) -> Result<Vec<usize>, BddError> {
+        if input_size > self.var_count {
+            return Err(BddError::TooManyInputs(input_size, self.var_count));
+        }
+        Ok((0..input_size).map(|i| self.var(i)).collect())
+    }
+
+    //电路的第 i 个输入对应变量 i
+    pub fn from_circuit(&mut self, circuit: &Circuit, outputs: &[usize]) -> Result<Vec<usize>, BddError> {
+        let inputs = self.input_vars(circuit.get_input().len())?;
+        let values = circuit.evaluate(self, &inputs);
+        outputs.iter()
+            .enumerate()
+            .map(|(i, o)| values[*o].ok_or(BddError::Undetermined(i)))
+            .collect()
+    }
+
+    //嵌套的 pattern 在 patterns 中查找，一般为 circuit.get_patterns()
+    pub fn from_pattern(&mut self, pattern: &Pattern, patterns: &HashMap<String, Pattern>) -> Result<Vec<usize>, BddError> {
+        let inputs = self.input_vars(pattern.input_size())?;
+        pattern.evaluate(self, patterns, &inputs, &[])
+            .into_iter()
+            .enumerate()
+            .map(|(i, f)| f.ok_or(BddError::Undetermined(i)))
+            .collect()
+    }
+
+    //任取一组使 f 为真的赋值，未出现的变量取 false
+    pub fn any_sat(&self, f: usize) -> Option<Vec<bool>> {
+        if f == Self::FALSE { return None; }
+        let mut assignment = vec![false; self.var_count];
+        let mut node = f;
+        while node != Self::TRUE {
+            let n = self.nodes[node];
+            if n.low != Self::FALSE {
+                node = n.low;
+            } else {
+                assignment[n.var] = true;
+                node = n.high;
+            }
+        }
+        Some(assignment)
+    }
+}
+
+impl Logic for Bdd {
+    type Value = usize;
+
+    fn constant(&mut self, value: bool) -> usize { Bdd::constant(self, value) }
+
+    fn gate(&mut self, gate: &NodeType, inputs: &[usize]) -> usize {
+        let mut fold = |f: fn(&mut Bdd, usize, usize) -> usize, init: usize| {
+            inputs.iter().fold(init, |acc, x| f(self, acc, *x))
+        };
+        match gate {
+            NodeType::Not => self.not(inputs[0]),
+            NodeType::And => fold(Bdd::and, Self::TRUE),
+            NodeType::Or => fold(Bdd::or, Self::FALSE),
+            //与 Node::execute_gate 一致，只取前两个输入
+            NodeType::Xor => self.xor(inputs[0], inputs[1]),
+            NodeType::NAnd => {
+                let f = fold(Bdd::and, Self::TRUE);
+                self.not(f)
+            }
+            NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+
+    #[test]
+    fn test_operations() {
+        let mut bdd = Bdd::new(3);
+        let (a, b, c) = (bdd.var(0), bdd.var(1), bdd.var(2));
+        let ab = bdd.and(a, b);
+        let f = bdd.or(ab, c);
+        assert_eq!(bdd.sat_count(f), 5);
+        assert_eq!(bdd.sat_count(a), 4);
+        assert_eq!(bdd.enumerate(f).count(), 5);
+
+        let f1 = bdd.restrict(f, 0, true);
+        let bc = bdd.or(b, c);
+        assert_eq!(f1, bc);
+        let g = bdd.compose(f, 2, ab);
+        assert_eq!(g, ab);
+
+        let na = bdd.not(a);
+        let t = bdd.or(a, na);
+        assert!(bdd.is_tautology(t));
+        assert_eq!(bdd.all_sat(ab), vec![vec![Some(true), Some(true), None]]);
+
+        bdd.set_var_name(&["a", "b", "c"]);
+        let dot = bdd.to_dot(&[("f", f)]);
+        assert!(dot.contains("label=\"a\""));
+        assert!(dot.contains("\"f\" -> n"));
+    }
+
+    #[test]
+    fn test_order() {
+        //a0 b0 a1 b1 ... 相邻时 BDD 是线性的，分开排列时是指数的
+        let src = "y = a0 & b0 | a1 & b1 | a2 & b2 | a3 & b3";
+        let mut expression = Expression::parse(src).unwrap();
+        expression.set_input_name(&["a0", "a1", "a2", "a3", "b0", "b1", "b2", "b3"]);
+        let (circuit, outputs) = expression.build_circuit();
+
+        let mut separated = Bdd::new(8);
+        let f = separated.from_circuit(&circuit, &outputs).unwrap()[0];
+        let order = Bdd::dfs_order(&circuit, &outputs);
+        assert_eq!(order, vec![0, 4, 1, 5, 2, 6, 3, 7]);
+        let mut interleaved = Bdd::with_order(&order).unwrap();
+        let g = interleaved.from_circuit(&circuit, &outputs).unwrap()[0];
+
+        assert_eq!(separated.sat_count(f), interleaved.sat_count(g));
+        assert_eq!(interleaved.size(g), 10);
+        assert!(separated.size(f) > interleaved.size(g));
+        assert!(Bdd::with_order(&[0, 0, 1]).is_err());
+        assert!(matches!(Bdd::new(7).from_circuit(&circuit, &outputs), Err(BddError::TooManyInputs(8, 7))));
+        let pattern = expression.to_pattern();
+        assert!(matches!(Bdd::new(2).from_pattern(&pattern, &HashMap::new()), Err(BddError::TooManyInputs(8, 2))));
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::netlist::{Cell, CellKind, Connection, Module, Netlist, NetlistError};
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PniType};
+
+#[derive(Debug, Error)]
+pub enum BlifError {
+    #[error("line {0}: {1}")]
+    Syntax(usize, String),
+    #[error(transparent)]
+    Netlist(#[from] NetlistError),
+    #[error("{0} has no BLIF representation")]
+    Unsupported(String),
+}
+
+//逻辑门写成 .names 的覆盖表
+fn write_names(v: &mut String, gate: &NodeType, operands: &[String], output: &str) {
+    let n = operands.len();
+    writeln!(v, ".names {} {}", operands.join(" "), output).unwrap();
+    let row = |c: char, i: usize, rest: char| (0..n).map(|j| if j == i { c } else { rest }).collect::<String>();
+    match gate {
+        NodeType::And => writeln!(v, "{} 1", "1".repeat(n)).unwrap(),
+        NodeType::Or => (0..n).for_each(|i| writeln!(v, "{} 1", row('1', i, '-')).unwrap()),
+        NodeType::NAnd => (0..n).for_each(|i| writeln!(v, "{} 1", row('0', i, '-')).unwrap()),
+        NodeType::Not => writeln!(v, "0 1").unwrap(),
+        //与 execute_gate 一致，只取前两个输入，缺少的输入按 0 处理
+        NodeType::Xor => (0..n.min(2)).for_each(|i| {
+            let row = (0..n).map(|j| if j == i { '1' } else if j < 2 { '0' } else { '-' }).collect::<String>();
+            writeln!(v, "{} 1", row).unwrap()
+        }),
+        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+    }
+}
+
+fn write_subckt(v: &mut String, pattern_name: &str, pattern: &Pattern, inputs: &[String], outputs: &[String]) {
+    let ports = inputs.iter().enumerate()
+        .map(|(i, s)| format!("{}={}", pattern.input_port(i), s))
+        .chain(outputs.iter().enumerate()
+            .map(|(i, s)| format!("{}={}", pattern.output_port(i), s)))
+        .collect::<Vec<String>>();
+    writeln!(v, ".subckt {} {}", pattern_name, ports.join(" ")).unwrap();
+}
+
+impl Pattern {
+    pub fn to_blif(&self, model_name: &str, circuit: &Circuit) -> String {
+        let inputs = (0..self.input_size()).map(|i| self.input_port(i)).collect::<Vec<String>>();
+        let outputs = (0..self.output_size()).map(|i| self.output_port(i)).collect::<Vec<String>>();
+        let wire = |node: usize, k: usize| match self.get_pattern()[node].get_type() {
+            NodeType::Pattern(_) => format!("n{}_{}", node, k),
+            _ => format!("n{}", node),
+        };
+
+        let mut v = String::new();
+        if !self.get_description().is_empty() {
+            writeln!(v, "# {}", self.get_description()).unwrap();
+        }
+        writeln!(v, ".model {}", model_name).unwrap();
+        writeln!(v, ".inputs {}", inputs.join(" ")).unwrap();
+        writeln!(v, ".outputs {}", outputs.join(" ")).unwrap();
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            let operands = node.get_input().iter()
+                .map(|input| match input {
+                    PniType::Input(i) => inputs[*i].clone(),
+                    PniType::NodeOutput((n, k)) => wire(*n, *k),
+                })
+                .collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let pattern = circuit.get_pattern(name);
+                    let node_outputs = (0..pattern.output_size()).map(|k| wire(i, k)).collect::<Vec<String>>();
+                    write_subckt(&mut v, name, pattern, &operands, &node_outputs);
+                }
+                gate => write_names(&mut v, gate, &operands, &wire(i, 0)),
+            }
+        }
+        for (output, (node, k)) in outputs.iter().zip(self.get_output()) {
+            writeln!(v, ".names {} {}\n1 1", wire(*node, *k), output).unwrap();
+        }
+        v.push_str(".end\n");
+        v
+    }
+}
+
+impl Circuit {
+    //BLIF 以第一个 model 为顶层，之后是用到的 pattern；参数与 to_verilog 相同
+    //顶层的 dlatch 写成 .latch，没有驱动但有值的信号写成常量；ram 无法用 BLIF 表示
+    pub fn to_blif(&self, model_name: &str, input_name: &[&str], outputs: &[(&str, usize)]) -> Result<String, BlifError> {
+        let input_size = self.get_input().len();
+        let signal = |i: usize| {
+            if i < input_size {
+                input_name.get(i).map(|s| s.to_string()).unwrap_or_else(|| format!("i{}", i))
+            } else {
+                format!("s{}", i)
+            }
+        };
+
+        let mut v = String::new();
+        writeln!(v, ".model {}", model_name).unwrap();
+        writeln!(v, ".inputs {}", (0..input_size).map(signal).collect::<Vec<String>>().join(" ")).unwrap();
+        writeln!(v, ".outputs {}", outputs.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(" ")).unwrap();
+        for (i, s) in self.get_signals().iter().enumerate().skip(input_size) {
+            if s.get_parent().is_none() && s.is_valid() {
+                writeln!(v, ".names {}", signal(i)).unwrap();
+                if s.get_unchecked() { writeln!(v, "1").unwrap(); }
+            }
+        }
+        for node in self.get_pipeline() {
+            let operands = node.get_input().iter().map(|i| signal(*i)).collect::<Vec<String>>();
+            let node_outputs = node.get_output().iter().map(|o| signal(*o)).collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) if name == "dlatch" => {
+                    let q = self.get_signal(node.get_output()[0]);
+                    let init = if q.is_valid() { q.get_unchecked() as usize } else { 3 };
+                    writeln!(v, ".latch {} {} ah {} {}", operands[1], node_outputs[0], operands[0], init).unwrap();
+                }
+                NodeType::Pattern(name) => {
+                    write_subckt(&mut v, name, self.get_pattern(name), &operands, &node_outputs);
+                }
+                //每一位的下一状态为 not(reset) and (enable ? d : q)，再接上升沿的 .latch
+                NodeType::Register(_) => {
+                    let value = self.register_value(node.get_output()[0]);
+                    for (k, (q, d)) in node_outputs.iter().zip(&operands[3..]).enumerate() {
+                        writeln!(v, ".names {} {} {} {} {}_next\n011- 1\n00-1 1", operands[2], operands[1], d, q, q).unwrap();
+                        writeln!(v, ".latch {}_next {} re {} {}", q, q, operands[0], value >> k & 1).unwrap();
+                    }
+                }
+                //rom 的每一位写成以地址为输入的真值表
+                NodeType::Rom(address_width, _) => {
+                    let words = self.get_states().get(&node.get_output()[0]).map(|s| s.get_data().clone()).unwrap_or_default();
+                    for (k, q) in node_outputs.iter().enumerate() {
+                        writeln!(v, ".names {} {}", operands.join(" "), q).unwrap();
+                        for (a, _) in words.iter().enumerate().filter(|(_, w)| *w >> k & 1 == 1) {
+                            let row = (0..*address_width).map(|j| if a >> j & 1 == 1 { '1' } else { '0' }).collect::<String>();
+                            writeln!(v, "{} 1", row).unwrap();
+                        }
+                    }
+                }
+                NodeType::Ram(..) => return Err(BlifError::Unsupported(format!("{:?}", node.get_type()))),
+                gate => write_names(&mut v, gate, &operands, &node_outputs[0]),
+            }
+        }
+        for (name, s) in outputs {
+            writeln!(v, ".names {} {}\n1 1", signal(*s), name).unwrap();
+        }
+        v.push_str(".end\n");
+
+        //只作为 .latch 出现的 dlatch 不需要单独的 model
+        let nested = |name: &str| self.used_patterns().iter()
+            .any(|p| self.get_pattern(p).get_pattern().iter().any(|n| *n.get_type() == NodeType::Pattern(name.to_string())));
+        for name in self.used_patterns() {
+            if name != "dlatch" || nested(&name) {
+                v.push('\n');
+                v.push_str(&self.get_pattern(&name).to_blif(&name, self));
+            }
+        }
+        Ok(v)
+    }
+
+    //top 为 None 时取第一个 model，其余 model 各自成为一个 pattern
+    //电平有效的 .latch 成为 dlatch，re/fe 的 .latch 成为一位的寄存器
+    pub fn from_blif(src: &str, top: Option<&str>) -> Result<(Circuit, Vec<(String, usize)>), BlifError> {
+        let netlist = Netlist::new(parse(src)?)?;
+        let top = match top {
+            Some(name) => netlist.module(name)?,
+            None => netlist.first()?,
+        };
+        Ok(netlist.build(top)?)
+    }
+}
+
+//去掉注释、合并以 \ 结尾的续行，返回 (行号, 单词)
+fn lines(src: &str) -> Vec<(usize, Vec<String>)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (i, line) in src.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let (number, mut text) = pending.take().unwrap_or((i + 1, String::new()));
+        match line.trim_end().strip_suffix('\\') {
+            Some(rest) => {
+                text.push_str(rest);
+                text.push(' ');
+                pending = Some((number, text));
+            }
+            None => {
+                text.push_str(line);
+                let words = text.split_whitespace().map(|w| w.to_string()).collect::<Vec<String>>();
+                if !words.is_empty() { lines.push((number, words)); }
+            }
+        }
+    }
+    if let Some((number, text)) = pending {
+        lines.push((number, text.split_whitespace().map(|w| w.to_string()).collect()));
+    }
+    lines
+}
+
+//正在读取的 .names
+struct Names {
+    line: usize,
+    inputs: Vec<String>,
+    output: String,
+    rows: Vec<(String, char)>,
+}
+
+impl Names {
+    fn cells(&self) -> Result<Vec<Cell>, BlifError> {
+        cover_cells(&self.inputs, &self.output, &self.rows).map_err(|m| BlifError::Syntax(self.line, m))
+    }
+}
+
+fn parse(src: &str) -> Result<Vec<Module>, BlifError> {
+    let mut modules = Vec::new();
+    let mut module: Option<Module> = None;
+    let mut names: Option<Names> = None;
+
+    for (number, words) in lines(src) {
+        let error = |message: &str| Err(BlifError::Syntax(number, message.to_string()));
+        if !words[0].starts_with('.') {
+            let Some(Names { inputs, rows, .. }) = names.as_mut() else { return error("cover row outside .names") };
+            let (plane, value) = match words.as_slice() {
+                [value] if inputs.is_empty() => ("", value.as_str()),
+                [plane, value] => (plane.as_str(), value.as_str()),
+                _ => return error("malformed cover row"),
+            };
+            if plane.len() != inputs.len() || !plane.chars().all(|c| matches!(c, '0' | '1' | '-'))
+                || !matches!(value, "0" | "1") {
+                return error("malformed cover row");
+            }
+            rows.push((plane.to_string(), value.chars().next().unwrap()));
+            continue;
+        }
+
+        if let Some(names) = names.take() {
+            module.as_mut().unwrap().cells.extend(names.cells()?);
+        }
+        let directive = words[0].as_str();
+        if directive == ".model" {
+            if module.is_some() { return error("missing .end"); }
+            let name = words.get(1).cloned().unwrap_or_default();
+            module = Some(Module { name, ..Default::default() });
+            continue;
+        }
+        let Some(current) = module.as_mut() else { return error("directive outside .model") };
+        match directive {
+            ".inputs" => current.inputs.extend(words[1..].iter().cloned()),
+            ".outputs" => current.outputs.extend(words[1..].iter().cloned()),
+            ".names" => {
+                let Some((output, inputs)) = words[1..].split_last() else { return error(".names without output") };
+                names = Some(Names { line: number, inputs: inputs.to_vec(), output: output.clone(), rows: Vec::new() });
+            }
+            ".latch" => current.cells.extend(latch_cells(&words[1..]).map_err(|m| BlifError::Syntax(number, m))?),
+            ".subckt" => {
+                let Some(name) = words.get(1) else { return error(".subckt without model") };
+                let connection = words[2..].iter()
+                    .map(|w| w.split_once('=').map(|(p, n)| (p.to_string(), Some(n.to_string()))))
+                    .collect::<Option<Vec<(String, Option<String>)>>>();
+                let Some(connection) = connection else { return error("expected formal=actual") };
+                current.add_instance(name.clone(), Connection::Named(connection));
+            }
+            ".end" => modules.push(module.take().unwrap()),
+            _ => return error(&format!("unsupported directive `{}`", directive)),
+        }
+    }
+    if let Some(names) = names.take() {
+        module.as_mut().unwrap().cells.extend(names.cells()?);
+    }
+    //最后一个 model 可以省略 .end
+    modules.extend(module);
+    Ok(modules)
+}
+
+//.latch <input> <output> [<type> <control>] [<init-val>]
+fn latch_cells(args: &[String]) -> Result<Vec<Cell>, String> {
+    let (data, output, control, init) = match args {
+        [d, q] => (d, q, None, None),
+        [d, q, init] => (d, q, None, Some(init)),
+        [d, q, kind, control] => (d, q, Some((kind, control)), None),
+        [d, q, kind, control, init] => (d, q, Some((kind, control)), Some(init)),
+        _ => return Err("malformed .latch".to_string()),
+    };
+    let init = match init.map(|s| s.as_str()) {
+        Some("0") => Some(false),
+        Some("1") => Some(true),
+        None | Some("2") | Some("3") => None,
+        _ => return Err("latch initial value must be 0..3".to_string()),
+    };
+
+    //没有控制信号时每次执行都更新；电平有效的成为 dlatch，边沿触发的成为寄存器
+    //低电平、下降沿有效的控制信号取反
+    let mut cells = Vec::new();
+    let clock = format!("{}.clock", output);
+    let kind = match control.map(|(k, c)| (k.as_str(), c.as_str())) {
+        None | Some(("as", _)) | Some((_, "NIL")) => {
+            cells.push(Cell::new(CellKind::Constant(true), Vec::new(), vec![clock.clone()]));
+            CellKind::Latch(init)
+        }
+        Some((kind @ ("re" | "ah"), control)) => {
+            cells.push(Cell::new(CellKind::Assign, vec![control.to_string()], vec![clock.clone()]));
+            if kind == "re" { CellKind::Flop(init) } else { CellKind::Latch(init) }
+        }
+        Some((kind @ ("fe" | "al"), control)) => {
+            cells.push(Cell::new(CellKind::Gate(NodeType::Not), vec![control.to_string()], vec![clock.clone()]));
+            if kind == "fe" { CellKind::Flop(init) } else { CellKind::Latch(init) }
+        }
+        Some((kind, _)) => return Err(format!("unknown latch type `{}`", kind)),
+    };
+    cells.push(Cell::new(kind, vec![clock, data.clone()], vec![output.clone()]));
+    Ok(cells)
+}
+
+//把 .names 的覆盖表转换成门；能识别为单个门时直接使用该门，否则按积之和展开
+fn cover_cells(inputs: &[String], output: &str, rows: &[(String, char)]) -> Result<Vec<Cell>, String> {
+    let n = inputs.len();
+    let on_set = match rows.first() {
+        Some((_, value)) => *value == '1',
+        None => return Ok(vec![Cell::new(CellKind::Constant(false), Vec::new(), vec![output.to_string()])]),
+    };
+    if rows.iter().any(|(_, value)| (*value == '1') != on_set) {
+        return Err("cover mixes on-set and off-set rows".to_string());
+    }
+    let gate = |kind: CellKind| Ok(vec![Cell::new(kind, inputs.to_vec(), vec![output.to_string()])]);
+
+    if n <= 8 {
+        //第一个输入为最高位
+        let matches = |m: usize, plane: &str| plane.chars().enumerate().all(|(i, c)| {
+            let bit = m >> (n - 1 - i) & 1;
+            c == '-' || (c == '1') == (bit == 1)
+        });
+        let table = (0..1usize << n)
+            .map(|m| rows.iter().any(|(plane, _)| matches(m, plane)) == on_set)
+            .collect::<Vec<bool>>();
+        let is = |f: &dyn Fn(usize) -> bool| table.iter().enumerate().all(|(m, v)| *v == f(m));
+        let full = (1usize << n) - 1;
+        if table.iter().all(|v| *v == table[0]) {
+            return Ok(vec![Cell::new(CellKind::Constant(table[0]), Vec::new(), vec![output.to_string()])]);
+        } else if n == 1 && table[1] {
+            return gate(CellKind::Assign);
+        } else if n == 1 {
+            return gate(CellKind::Gate(NodeType::Not));
+        } else if is(&|m| m == full) {
+            return gate(CellKind::Gate(NodeType::And));
+        } else if is(&|m| m != full) {
+            return gate(CellKind::Gate(NodeType::NAnd));
+        } else if is(&|m| m != 0) {
+            return gate(CellKind::Gate(NodeType::Or));
+        } else if n == 2 && is(&|m| m == 1 || m == 2) {
+            return gate(CellKind::Gate(NodeType::Xor));
+        }
+    }
+
+    let mut cells = Vec::new();
+    let mut inverted = HashMap::new();
+    let mut terms = Vec::new();
+    for (k, (plane, _)) in rows.iter().enumerate() {
+        let mut literals = Vec::new();
+        for (i, c) in plane.chars().enumerate() {
+            match c {
+                '1' => literals.push(inputs[i].clone()),
+                '0' => literals.push(inverted.entry(i).or_insert_with(|| {
+                    let net = format!("{}.n{}", output, i);
+                    cells.push(Cell::new(CellKind::Gate(NodeType::Not), vec![inputs[i].clone()], vec![net.clone()]));
+                    net
+                }).clone()),
+                _ => {}
+            }
+        }
+        match literals.len() {
+            0 => return Ok(vec![Cell::new(CellKind::Constant(on_set), Vec::new(), vec![output.to_string()])]),
+            1 => terms.push(literals.pop().unwrap()),
+            _ => {
+                let net = format!("{}.t{}", output, k);
+                cells.push(Cell::new(CellKind::Gate(NodeType::And), literals, vec![net.clone()]));
+                terms.push(net);
+            }
+        }
+    }
+
+    let sum = if on_set { output.to_string() } else { format!("{}.sum", output) };
+    match terms.len() {
+        1 => cells.push(Cell::new(CellKind::Assign, terms, vec![sum.clone()])),
+        _ => cells.push(Cell::new(CellKind::Gate(NodeType::Or), terms, vec![sum.clone()])),
+    }
+    if !on_set {
+        cells.push(Cell::new(CellKind::Gate(NodeType::Not), vec![sum], vec![output.to_string()]));
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::Equivalence;
+
+    #[test]
+    fn test_round_trip() {
+        let mut circuit = Circuit::new_with_basic_pattern(4);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input[..3]).get_output().to_vec();
+        let n = circuit.add_gate("nand", &[sum[0], sum[1], input[3]]);
+        let o = circuit.add_gate("or", &[n, input[0]]);
+
+        let v = circuit.to_blif("top", &["c0", "a", "b", "r"], &[("s", sum[0]), ("c1", sum[1]), ("o", o)]).unwrap();
+        assert!(v.starts_with(".model top\n.inputs c0 a b r\n.outputs s c1 o\n"));
+        assert!(v.contains(".subckt full_adder c0=c0 a=a b=b s=s4 c1=s5\n"));
+        assert!(v.contains(".names s4 s5 r s6\n0-- 1\n-0- 1\n--0 1\n"));
+        assert!(v.contains(".model full_adder\n"));
+        assert!(v.contains(".names a b n0\n10 1\n01 1\n"));
+
+        let mut xor = String::new();
+        write_names(&mut xor, &NodeType::Xor, &["a".to_string(), "b".to_string(), "c".to_string()], "y");
+        assert_eq!(xor, ".names a b c y\n10- 1\n01- 1\n");
+        xor.clear();
+        write_names(&mut xor, &NodeType::Xor, &["a".to_string()], "y");
+        assert_eq!(xor, ".names a y\n1 1\n");
+
+        let (imported, outputs) = Circuit::from_blif(&v, None).unwrap();
+        let outputs = outputs.iter().map(|(_, s)| *s).collect::<Vec<usize>>();
+        assert_eq!(imported.get_pattern("full_adder").get_pattern().len(), 5);
+        assert_eq!(imported.get_pipeline().len(), 3);
+        assert_eq!(imported.equivalent(&outputs, &circuit, &[sum[0], sum[1], o]).unwrap(), Equivalence::Equal);
+    }
+
+    #[test]
+    fn test_counter() {
+        let src = "
+            # 两位计数器
+            .model counter
+            .inputs clk en
+            .outputs q0 q1 maj nz
+            .latch d0 q0 re clk 0
+            .latch d1 q1 re clk 0
+            .subckt ha a=q0 b=en s=d0 c=c0
+            .subckt ha a=q1 \\
+                    b=c0 s=d1 c=c1
+            .names en q0 q1 maj
+            11- 1
+            1-1 1
+            -11 1
+            .names q0 q1 nz
+            00 0
+            .end
+
+            .model ha
+            .inputs a b
+            .outputs s c
+            .names a b s
+            10 1
+            01 1
+            .names a b c
+            11 1
+            .end
+        ";
+        let (mut circuit, outputs) = Circuit::from_blif(src, None).unwrap();
+        let outputs = outputs.iter().map(|(_, s)| *s).collect::<Vec<usize>>();
+        assert!(!circuit.get_patterns().contains_key("dlatch"));
+        let count = |circuit: &Circuit| {
+            let q = [outputs[0], outputs[1]].map(|o| circuit.get_signal(o).get_unchecked() as usize);
+            q[0] + 2 * q[1]
+        };
+        //只在 clk 的上升沿计数，clk 保持高电平时不变
+        for k in 1..6 {
+            circuit.execute_sequential_mut(&[0, 1]);
+            circuit.execute_sequential_mut(&[1, 1]);
+            assert_eq!(count(&circuit), k % 4);
+            circuit.execute_sequential_mut(&[1, 1]);
+            assert_eq!(count(&circuit), k % 4);
+            let q = count(&circuit);
+            assert_eq!(circuit.get_signal(outputs[2]).get_unchecked(), q != 0);
+            assert_eq!(circuit.get_signal(outputs[3]).get_unchecked(), q != 0);
+        }
+
+        //写出的 .latch re 重新读入后仍然是上升沿触发，并保留当前的计数
+        let v = circuit.to_blif("counter", &["clk", "en"], &[("q0", outputs[0]), ("q1", outputs[1])]).unwrap();
+        assert!(v.contains(" re clk 1\n"));
+        assert!(!v.contains(".model dlatch"));
+        let (mut imported, outputs) = Circuit::from_blif(&v, None).unwrap();
+        let outputs = outputs.iter().map(|(_, s)| *s).collect::<Vec<usize>>();
+        let count = |circuit: &Circuit| outputs.iter().enumerate()
+            .map(|(i, o)| (circuit.get_signal(*o).get_unchecked() as usize) << i)
+            .sum::<usize>();
+        imported.execute_sequential_mut(&[0, 1]);
+        assert_eq!(count(&imported), 1);
+        for k in 2..6 {
+            imported.execute_sequential_mut(&[0, 1]);
+            imported.execute_sequential_mut(&[1, 1]);
+            imported.execute_sequential_mut(&[1, 0]);
+            assert_eq!(count(&imported), k % 4);
+        }
+
+        //电平有效的 .latch 仍然是 dlatch
+        let (latch, _) = Circuit::from_blif(".model m\n.inputs d g\n.outputs q\n.latch d q ah g 0\n.end", None).unwrap();
+        assert!(latch.get_patterns().contains_key("dlatch"));
+        assert!(matches!(Circuit::from_blif(".model m\n.inputs a\n.outputs y\n.names a y\n1 1 1\n.end", None),
+                         Err(BlifError::Syntax(5, _))));
+        assert!(matches!(Circuit::from_blif(".model m\n.inputs a\n.outputs y\n.end", None),
+                         Err(BlifError::Netlist(NetlistError::Undriven(_, _)))));
+
+        //没有用到的时序 model 被忽略，用到的在顶层展开成寄存器
+        let ff = ".model ff\n.inputs d c\n.outputs q\n.latch d q re c 0\n.end\n";
+        let plain = format!(".model top\n.inputs a\n.outputs y\n.names a y\n0 1\n.end\n{}", ff);
+        let (imported, _) = Circuit::from_blif(&plain, None).unwrap();
+        assert!(imported.get_patterns().is_empty());
+        let used = format!(".model top\n.inputs d c\n.outputs y\n.subckt ff d=d c=c q=y\n.end\n{}", ff);
+        let (mut imported, outputs) = Circuit::from_blif(&used, None).unwrap();
+        assert!(imported.get_pipeline().iter().any(|n| matches!(n.get_type(), NodeType::Register(1))));
+        imported.execute_sequential_mut(&[1, 0]);
+        imported.execute_sequential_mut(&[1, 1]);
+        assert!(imported.get_signal(outputs[0].1).get_unchecked());
+
+        //ram 写出时给出错误
+        let mut circuit = Circuit::new(4);
+        let q = circuit.add_ram(0, 1, &[2], &[3]).unwrap();
+        assert!(matches!(circuit.to_blif("ram", &[], &[("q", q[0])]), Err(BlifError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,322 @@
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::msic::gain_gate_type;
+use crate::node::NodeType;
+use crate::signal::Signal;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BusError {
+    #[error("no bus named {0}")]
+    UnknownBus(String),
+    #[error("value {1} does not fit in bus {0} of width {2}")]
+    Overflow(String, i128, usize),
+    #[error("bus {0} has width {1}, wider than 64 bits")]
+    TooWide(String, usize),
+    #[error("bus {0} has undetermined bits")]
+    Undetermined(String),
+}
+
+//整数与总线各位的对应方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BitOrder {
+    //signals[0] 为最低位
+    #[default]
+    LsbFirst,
+    //signals[0] 为最高位
+    MsbFirst,
+}
+
+//可以写到总线上的整数，负数按补码写入
+pub trait BusValue: Copy {
+    fn to_i128(self) -> i128;
+}
+
+macro_rules! bus_value {
+    ($($t:ty),*) => {
+        $(impl BusValue for $t {
+            fn to_i128(self) -> i128 { self as i128 }
+        })*
+    };
+}
+bus_value!(bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+//多位信号，默认 signals[0] 为最低位
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Bus {
+    name: String,
+    signals: Vec<usize>,
+    #[serde(default)]
+    order: BitOrder,
+}
+
+impl Bus {
+    pub fn new(name: &str, signals: &[usize]) -> Bus {
+        Self { name: name.to_string(), signals: signals.to_vec(), order: BitOrder::LsbFirst }
+    }
+
+    pub fn get_name(&self) -> &str { &self.name }
+    pub fn get_signals(&self) -> &Vec<usize> { &self.signals }
+    pub fn get_order(&self) -> BitOrder { self.order }
+    pub fn width(&self) -> usize { self.signals.len() }
+    pub fn bit(&self, index: usize) -> usize { self.signals[index] }
+
+    pub fn with_order(mut self, order: BitOrder) -> Bus {
+        self.order = order;
+        self
+    }
+
+    //按权重从低到高排列的信号
+    pub fn lsb_first(&self) -> Vec<usize> {
+        match self.order {
+            BitOrder::LsbFirst => self.signals.clone(),
+            BitOrder::MsbFirst => self.signals.iter().rev().copied().collect(),
+        }
+    }
+
+    pub fn named(mut self, name: &str) -> Bus {
+        self.name = name.to_string();
+        self
+    }
+
+    //取 [range.start, range.end) 位
+    pub fn slice(&self, range: Range<usize>) -> Bus {
+        Bus::new("", &self.signals[range])
+    }
+
+    //buses[0] 在最低位
+    pub fn concat(buses: &[&Bus]) -> Bus {
+        let signals = buses.iter().flat_map(|b| b.signals.iter().copied()).collect::<Vec<usize>>();
+        Bus::new("", &signals)
+    }
+
+    //重复 n 次，例如把一位信号扩展成整条总线
+    pub fn replicate(&self, n: usize) -> Bus {
+        Bus::new("", &self.signals.repeat(n))
+    }
+}
+
+impl Circuit {
+    //登记有名字的总线，返回登记的总线
+    pub fn add_bus(&mut self, bus: Bus) -> Bus {
+        self.get_buses_mut().insert(bus.get_name().to_string(), bus.clone());
+        bus
+    }
+
+    pub fn get_bus(&self, name: &str) -> &Bus { &self.get_buses()[name] }
+
+    //由第 range 个输入组成的总线
+    pub fn input_bus(&mut self, name: &str, range: Range<usize>) -> Bus {
+        let signals = range.collect::<Vec<usize>>();
+        self.add_bus(Bus::new(name, &signals))
+    }
+
+    pub fn bus_not(&mut self, a: &Bus) -> Bus {
+        let signals = a.signals.iter().map(|s| self.add_gate("not", &[*s])).collect::<Vec<usize>>();
+        Bus::new("", &signals)
+    }
+
+    //按位的两输入门，两条总线宽度必须相同
+    pub fn bus_gate(&mut self, gate_type: &str, a: &Bus, b: &Bus) -> Bus {
+        assert_eq!(a.width(), b.width(), "bus width mismatch: {} and {}", a.name, b.name);
+        let signals = a.signals.iter().zip(&b.signals)
+            .map(|(x, y)| self.add_gate(gate_type, &[*x, *y]))
+            .collect::<Vec<usize>>();
+        Bus::new("", &signals)
+    }
+
+    //把所有位归约成一位；and/or/nand 是多输入门，xor 只用前两个输入，所以两两组成平衡树
+    pub fn bus_reduce(&mut self, gate_type: &str, a: &Bus) -> usize {
+        match gain_gate_type(gate_type) {
+            NodeType::Xor => {
+                let mut level = a.signals.clone();
+                while level.len() > 1 {
+                    level = level.chunks(2)
+                        .map(|pair| match pair {
+                            [x, y] => self.add_gate("xor", &[*x, *y]),
+                            [x] => *x,
+                            _ => unreachable!(),
+                        })
+                        .collect();
+                }
+                level[0]
+            }
+            NodeType::Not => unreachable!(),
+            _ => self.add_gate(gate_type, &a.signals),
+        }
+    }
+
+    //总线当前的无符号值，有无效位时返回 None
+    pub fn bus_value(&self, bus: &Bus) -> Option<u64> {
+        assert!(bus.width() <= 64);
+        bus.lsb_first().iter().enumerate().try_fold(0, |acc, (i, s)| {
+            let signal = self.get_signal(*s);
+            signal.is_valid().then(|| acc | ((signal.get_unchecked() as u64) << i))
+        })
+    }
+
+    //把整数写到总线的各个信号上，放不下时报错；写到输入上的值在下一次执行时生效
+    pub fn set_bus<T: BusValue>(&mut self, bus: &Bus, value: T) -> Result<(), BusError> {
+        let (value, width) = (value.to_i128(), bus.width());
+        if width < 128 && (value >= 1 << width || value < -(1 << width.saturating_sub(1))) {
+            return Err(BusError::Overflow(bus.name.clone(), value, width));
+        }
+        for (i, s) in bus.lsb_first().iter().enumerate() {
+            let bit = (value >> i.min(127)) & 1;
+            self.get_signals_mut()[*s].set_value(Signal::from_usize(bit as usize));
+        }
+        Ok(())
+    }
+
+    pub fn set<T: BusValue>(&mut self, name: &str, value: T) -> Result<(), BusError> {
+        let bus = self.find_bus(name)?.clone();
+        self.set_bus(&bus, value)
+    }
+
+    pub fn get_u64(&self, name: &str) -> Result<u64, BusError> {
+        let bus = self.find_bus(name)?;
+        if bus.width() > 64 {
+            return Err(BusError::TooWide(name.to_string(), bus.width()));
+        }
+        self.bus_value(bus).ok_or_else(|| BusError::Undetermined(name.to_string()))
+    }
+
+    //最高位为符号位
+    pub fn get_i64(&self, name: &str) -> Result<i64, BusError> {
+        let value = self.get_u64(name)?;
+        let shift = 64 - self.get_bus(name).width() as u32;
+        Ok(((value << (shift % 64)) as i64) >> (shift % 64))
+    }
+
+    fn find_bus(&self, name: &str) -> Result<&Bus, BusError> {
+        self.get_buses().get(name).ok_or_else(|| BusError::UnknownBus(name.to_string()))
+    }
+
+    //用当前输入信号的值执行一次
+    pub fn execute_mut(&mut self) {
+        self.execute_sequential_mut(&[]);
+    }
+
+    //按总线的整数值设置输入后执行一次，没有给出的输入保持当前值，无效的输入视为 0
+    pub fn execute_buses_mut(&mut self, inputs: &[(&Bus, u64)]) {
+        let mut values = self.get_input().iter()
+            .map(|i| {
+                let signal = self.get_signal(*i);
+                (signal.is_valid() && signal.get_unchecked()) as usize
+            })
+            .collect::<Vec<usize>>();
+        for (bus, value) in inputs {
+            for (i, s) in bus.lsb_first().iter().enumerate() {
+                assert!(*s < values.len(), "signal {} of bus {} is not an input", s, bus.name);
+                values[*s] = (value >> i & 1) as usize;
+            }
+        }
+        self.execute_sequential_mut(&values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn test_bus() {
+        let mut circuit = Circuit::new(9);
+        let a = circuit.input_bus("a", 0..4);
+        let b = circuit.input_bus("b", 4..8);
+        let c = Bus::new("c", &[8]);
+        let and = circuit.bus_gate("and", &a, &b);
+        let or = circuit.bus_gate("or", &a, &b);
+        let xor = circuit.bus_gate("xor", &a, &b).named("xor");
+        circuit.add_bus(xor.clone());
+        let not = circuit.bus_not(&a);
+        let all = circuit.bus_reduce("and", &a);
+        let any = circuit.bus_reduce("or", &a);
+        let parity = circuit.bus_reduce("xor", &Bus::concat(&[&a, &c]));
+        let mix = Bus::concat(&[&a.slice(0..2), &b.slice(2..4)]);
+        let wide = c.replicate(4);
+        assert_eq!(mix.get_signals(), &vec![0, 1, 6, 7]);
+        assert_eq!(wide.width(), 4);
+        assert_eq!(circuit.get_bus("xor").get_signals(), xor.get_signals());
+
+        for (x, y, z) in [(0b1100, 0b1010, 1), (0b1111, 0b0101, 0), (0b0000, 0b1111, 1)] {
+            circuit.execute_buses_mut(&[(&a, x), (&b, y), (&c, z)]);
+            let value = |bus: &Bus| circuit.bus_value(bus).unwrap();
+            assert_eq!(value(&and), x & y);
+            assert_eq!(value(&or), x | y);
+            assert_eq!(value(&xor), x ^ y);
+            assert_eq!(value(&not), !x & 0b1111);
+            assert_eq!(value(&mix), (x & 0b11) | (y >> 2) << 2);
+            assert_eq!(value(&wide), if z == 1 { 0b1111 } else { 0 });
+            assert_eq!(circuit.get_signal(all).get_unchecked(), x == 0b1111);
+            assert_eq!(circuit.get_signal(any).get_unchecked(), x != 0);
+            assert_eq!(circuit.get_signal(parity).get_unchecked(), (x.count_ones() + z as u32) % 2 == 1);
+        }
+
+        //只给出部分输入时其它输入保持不变
+        circuit.execute_buses_mut(&[(&b, 0b0011)]);
+        assert_eq!(circuit.bus_value(&a), Some(0));
+        assert_eq!(circuit.bus_value(&and), Some(0));
+        assert_eq!(circuit.bus_value(&Bus::concat(&[&b, &c])), Some(0b10011));
+
+        let json = serde_json::to_string(&circuit).unwrap();
+        let loaded = serde_json::from_str::<Circuit>(&json).unwrap();
+        assert_eq!(loaded.get_bus("b"), &b);
+    }
+
+    #[test]
+    fn test_integer() {
+        let mut circuit = Circuit::new(8);
+        Pattern::build_full_adder(&mut circuit);
+        let a = circuit.input_bus("a", 0..4);
+        let b = circuit.input_bus("b", 4..8);
+        let mut carry = circuit.advance_output_with_value(&[0])[0];
+        let mut sum = Vec::new();
+        for i in 0..4 {
+            let outputs = circuit.apply_pattern("full_adder", &[carry, a.bit(i), b.bit(i)]).get_output().to_vec();
+            sum.push(outputs[0]);
+            carry = outputs[1];
+        }
+        sum.push(carry);
+        circuit.add_bus(Bus::new("sum", &sum));
+        circuit.add_bus(Bus::new("low", &sum[..4]));
+
+        circuit.set("a", 13u8).unwrap();
+        circuit.set("b", 9u8).unwrap();
+        circuit.execute_mut();
+        assert_eq!(circuit.get_u64("sum"), Ok(22));
+        for x in 0..16u8 {
+            for y in 0..16u8 {
+                circuit.set("a", x).unwrap();
+                circuit.set("b", y).unwrap();
+                circuit.execute_mut();
+                assert_eq!(circuit.get_u64("sum"), Ok((x + y) as u64));
+            }
+        }
+
+        //有符号数按补码相加，低四位即结果
+        circuit.set("a", -3i8).unwrap();
+        circuit.set("b", 5i64).unwrap();
+        circuit.execute_mut();
+        assert_eq!(circuit.get_u64("a"), Ok(13));
+        assert_eq!(circuit.get_i64("a"), Ok(-3));
+        assert_eq!(circuit.get_i64("low"), Ok(2));
+        assert_eq!(circuit.set("a", 16u8), Err(BusError::Overflow("a".to_string(), 16, 4)));
+        assert_eq!(circuit.set("b", -9i8), Err(BusError::Overflow("b".to_string(), -9, 4)));
+        assert_eq!(circuit.get_u64("c"), Err(BusError::UnknownBus("c".to_string())));
+
+        //高位在前的总线
+        let msb = circuit.add_bus(Bus::new("msb", &[0, 1, 2, 3]).with_order(BitOrder::MsbFirst));
+        circuit.set("msb", 0b1000u8).unwrap();
+        assert!(circuit.get_signal(0).get_unchecked());
+        assert_eq!(circuit.get_u64("a"), Ok(1));
+        assert_eq!(circuit.get_u64("msb"), Ok(8));
+        assert_eq!(msb.lsb_first(), vec![3, 2, 1, 0]);
+
+        let mut empty = Circuit::new(2);
+        empty.add_bus(Bus::new("x", &[0, 1]));
+        assert_eq!(empty.get_u64("x"), Err(BusError::Undetermined("x".to_string())));
+    }
+}
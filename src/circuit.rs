@@ -4,8 +4,10 @@ use eframe::egui::UserAttentionType;
 use serde::{Deserialize, Serialize};
 use crate::signal::Signal;
 use crate::node::{Node, NodeType};
-use crate::pattern::{Pattern, PatternGate, PniType};
+use crate::pattern::{Pattern, PatternNode, PniType};
 use crate::msic::*;
+use crate::bus::Bus;
+use crate::register::State;
 
 pub enum BuildError {
     SampleOutput(usize, usize),
@@ -20,6 +22,11 @@ pub struct Circuit
     pattern_range: (usize, usize),
     patterns: HashMap<String, Pattern>,
     pipeline: Vec<Node>,
+    #[serde(default)]
+    buses: HashMap<String, Bus>,
+    //行为级节点的内部状态，以节点的第一个输出信号为键
+    #[serde(default)]
+    states: HashMap<usize, State>,
 }
 
 impl Circuit {
@@ -31,6 +38,8 @@ impl Circuit {
             pattern_range: (0, 0),
             patterns: HashMap::new(),
             pipeline: Vec::new(),
+            buses: HashMap::new(),
+            states: HashMap::new(),
         }
     }
     
@@ -42,17 +51,17 @@ impl Circuit {
     }
 
     pub fn get_input(&self) -> Vec<usize> {
-        let mut res = vec![0; self.input];
-        for i in 0..self.input {
-            res[i] = i;
-        }
-        res
+        (0..self.input).collect()
     }
     pub fn get_patterns_mut(&mut self) -> &mut HashMap<String, Pattern> { &mut self.patterns }
     pub fn get_patterns(&self) -> &HashMap<String, Pattern> { &self.patterns }
+    pub fn get_buses_mut(&mut self) -> &mut HashMap<String, Bus> { &mut self.buses }
+    pub fn get_buses(&self) -> &HashMap<String, Bus> { &self.buses }
+    pub fn get_states_mut(&mut self) -> &mut HashMap<usize, State> { &mut self.states }
+    pub fn get_states(&self) -> &HashMap<usize, State> { &self.states }
     pub fn get_signals_mut(&mut self) -> &mut Vec<Signal> { &mut self.signals }
     pub fn get_signals(&self) -> &Vec<Signal> { &self.signals }
-    pub fn get_signal(&self, index: usize) -> Signal { self.signals[index].clone() }
+    pub fn get_signal(&self, index: usize) -> Signal { self.signals[index] }
     pub fn signals_size(&self) -> usize { self.signals.len() }
 
     pub fn get_node(&self, index: usize) -> &Node { &self.pipeline[index] }
@@ -61,15 +70,7 @@ impl Circuit {
     pub fn get_pattern(&self, name: &str) -> &Pattern { &self.patterns[name] }
 
     pub fn get_parent_node_index(&self, signal: usize) -> Option<usize> {
-        if let Some(signal) = self.signals.get(signal) {
-            if let Some(node) = signal.get_parent() {
-                Some(node)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        self.signals.get(signal).and_then(|s| s.get_parent())
     }
     
     pub fn get_parent_node(&self, signal: usize) -> Option<&Node> {
@@ -90,31 +91,33 @@ impl Circuit {
         signals: &[(&str, usize)]) -> &Node {
         let pattern = self.get_pattern(pattern_name);
         let (inputs, outputs) = pattern.gain_inputs_outputs(signals);
-        self.pipeline.push(Node::new(
-            NodeType::Pattern(pattern_name.to_string()),
-            inputs,
-            outputs.clone(),
-        ));
-        &self.pipeline.last().unwrap()
+        self.add_node(NodeType::Pattern(pattern_name.to_string()), inputs, outputs)
     }
     pub fn apply_pattern(&mut self, pattern_name: &str, inputs: &[usize]) -> &Node {
         let output_size = self.get_pattern(pattern_name).output_size();
         let outputs = self.advance_output(output_size);
-        self.pipeline.push(Node::new(
-            NodeType::Pattern(pattern_name.to_string()),
-            inputs.to_vec(),
-            outputs.clone()));
-        &self.pipeline.last().unwrap()
+        self.add_node(NodeType::Pattern(pattern_name.to_string()), inputs.to_vec(), outputs)
     }
 
     pub fn apply_flipflop(&mut self, pattern_name: &str, inputs: &[usize]) -> &Node {
         let outputs = self.advance_output_for_flipflop();
-        self.pipeline.push(Node::new(
-            NodeType::Pattern(pattern_name.to_string()),
-            inputs.to_vec(),
-            outputs.clone()
-        ));
-        &self.pipeline.last().unwrap()
+        self.add_node(NodeType::Pattern(pattern_name.to_string()), inputs.to_vec(), outputs)
+    }
+
+    //加入pipeline，并把输出信号的来源指向该node
+    //pattern 有内部状态时另外分配初始为 0 的 state 信号
+    pub fn add_node(&mut self, node_type: NodeType, inputs: Vec<usize>, outputs: Vec<usize>) -> &Node {
+        let node_index = self.pipeline.len();
+        let state_size = match &node_type {
+            NodeType::Pattern(name) => self.patterns.get(name).map_or(0, |p| p.get_state().len()),
+            _ => 0,
+        };
+        let state = self.advance_output_with_value(&vec![0; state_size]);
+        for output in outputs.iter().chain(&state) {
+            self.signals[*output].set_parent(node_index);
+        }
+        self.pipeline.push(Node::new(node_type, inputs, outputs).with_state(state));
+        self.pipeline.last().unwrap()
     }
 
     pub fn build_begin(&mut self) { 
@@ -139,19 +142,17 @@ impl Circuit {
                 if let Some(outside_input) = inputs.iter().position(|x| x == input) {
                     node_inputs.push(PniType::Input(outside_input));
                 } else {
-                    node_inputs.push(PniType::NodeOutput(
-                        self.get_parent_node_index(*input).unwrap() - pattern_start
-                    ))
+                    node_inputs.push(PniType::NodeOutput(self.locate_output(*input, pattern_start)))
                 }
             }
-            pattern_nodes.push(PatternGate::new(
+            pattern_nodes.push(PatternNode::new(
                 node.get_type().clone(),
                 node_inputs,
             ));
         }
 
         let output_node = outputs.iter()
-            .map(|x| self.get_parent_node_index(*x).unwrap() - pattern_start)
+            .map(|x| self.locate_output(*x, pattern_start))
             .collect();
 
         let new_pattern = Pattern::new(
@@ -167,6 +168,15 @@ impl Circuit {
         self.patterns.get_mut(pattern_name).unwrap()
     }
 
+    //信号在pattern中的位置：(node下标, 该node的第几个输出)
+    fn locate_output(&self, signal: usize, pattern_start: usize) -> (usize, usize) {
+        let node = self.get_parent_node_index(signal).unwrap();
+        let index = self.pipeline[node].get_output().iter()
+            .position(|o| *o == signal)
+            .unwrap();
+        (node - pattern_start, index)
+    }
+
     pub fn add_gate_t(&mut self, gate_type: NodeType, input: &[usize]) -> usize {
         let new_gate = Node::new(
             gate_type,
@@ -199,8 +209,8 @@ impl Circuit {
     }
     pub fn advance_output_with_value(&mut self, signals: &[usize]) -> Vec<usize> {
         let mut outputs = Vec::new();
-        for i in 0..signals.len() {
-            self.signals.push(Signal::from_usize(signals[i]));
+        for signal in signals {
+            self.signals.push(Signal::from_usize(*signal));
             outputs.push(self.signals_size() - 1);
         }
         outputs
@@ -212,8 +222,12 @@ impl Circuit {
         }
     }
 
+    //执行一轮后由时钟沿更新行为级节点的状态，状态改变时再执行一轮把新状态传播出去
     pub fn execute_sequential_mut(&mut self, input_signals: &[usize]) {
         self.signals = self.execute_sequential(input_signals);
+        if self.clock_mut() {
+            self.signals = self.execute_sequential(input_signals);
+        }
     }
     pub fn execute_sequential(&self, input_signals: &[usize]) -> Vec<Signal> {
         let input_signals = usize_signal_vec(input_signals);
@@ -236,7 +250,7 @@ impl Circuit {
         let n = 2u32.pow(input_size as u32) as usize;
         let mut result = vec![(vec![], vec![]); n];
 
-        for i in 0..n {
+        for (i, line) in result.iter_mut().enumerate() {
             let mut temp = i;
             let mut line_input = vec![0usize; input_size];
             for j in 0..input_size {
@@ -244,10 +258,10 @@ impl Circuit {
                 temp /= 2;
                 line_input[input_size - j - 1] = v;
             }
-            result[i].0 = usize_signal_vec(&line_input);
+            line.0 = usize_signal_vec(&line_input);
             let res = circuit.execute_sequential(&line_input);
-            result[i].1 = choose_outputs.iter()
-                .map(|&x| res.get(x).unwrap().clone()).collect()
+            line.1 = choose_outputs.iter()
+                .map(|&x| *res.get(x).unwrap()).collect()
         }
         result
     }
@@ -348,7 +362,9 @@ mod tests {
         
         println!("{}", fa_pattern);
 
-        circuit.apply_pattern("full_adder", &input);
+        let output = circuit.apply_pattern("full_adder", &input)
+            .get_output()
+            .to_vec();
 
         let res = circuit.execute_sequential(&[1, 1, 0]);
 
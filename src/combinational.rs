@@ -16,12 +16,12 @@ impl Pattern {
         add_node(
             &mut pattern,
             "xor",
-            vec![c0, NodeOutput(n0)]
+            vec![c0, NodeOutput((n0, 0))]
         );
         let n1 = add_node(
             &mut pattern,
             "and",
-            vec![c0, NodeOutput(n0)]
+            vec![c0, NodeOutput((n0, 0))]
         );
         let n2 = add_node(
             &mut pattern,
@@ -31,11 +31,11 @@ impl Pattern {
         add_node(
             &mut pattern,
             "or",
-            vec![NodeOutput(n1), NodeOutput(n2)]
+            vec![NodeOutput((n1, 0)), NodeOutput((n2, 0))]
         );
         let mut pattern = Pattern::new(
             3,
-            vec![s, c1],
+            vec![(s, 0), (c1, 0)],
             pattern
         );
         pattern.set_name(&["c0", "a", "b"], &["s", "c1"]);
@@ -50,7 +50,7 @@ impl Pattern {
         let b_output = add_node(&mut pattern, "and", input.clone());
         let mut pattern = Pattern::new(
             2,
-            vec![a_output, b_output],
+            vec![(a_output, 0), (b_output, 0)],
             pattern
         );
         pattern.set_name(
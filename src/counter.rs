@@ -0,0 +1,291 @@
+use crate::arithmetic::named;
+use crate::circuit::Circuit;
+use crate::generator::{build, select_width};
+use crate::pattern::Pattern;
+use crate::sequential::edge_bits;
+
+//计数器和移位寄存器都由上升沿触发器组成，输出依次为 q[] 和附加输出，实例的输出都要有初始值
+
+//terms 为 (条件, 值)，条件互斥，取条件成立的那一项的值，都不成立时为 0
+fn select(circuit: &mut Circuit, terms: &[(usize, usize)]) -> usize {
+    let terms = terms.iter().map(|(c, v)| circuit.add_gate("and", &[*c, *v])).collect::<Vec<usize>>();
+    circuit.add_gate("or", &terms)
+}
+
+//同步清零：rst 为 1 时下一状态为 0
+fn clear(circuit: &mut Circuit, rst: usize, next: &[usize]) -> Vec<usize> {
+    let nrst = circuit.add_gate("not", &[rst]);
+    next.iter().map(|d| circuit.add_gate("and", &[nrst, *d])).collect()
+}
+
+//按位翻转：t[0] = en，t[i + 1] = t[i] and step[i]，下一状态为 q xor t，返回 (下一状态, t[n])
+fn toggle(circuit: &mut Circuit, q: &[usize], en: usize, step: &[usize]) -> (Vec<usize>, usize) {
+    let mut t = en;
+    let mut next = Vec::new();
+    for i in 0..q.len() {
+        next.push(circuit.add_gate("xor", &[q[i], t]));
+        t = circuit.add_gate("and", &[t, step[i]]);
+    }
+    (next, t)
+}
+
+fn ports(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+impl Pattern {
+    //n 位可逆计数器 counter{n}：输入 cp, rst, en, up，up 为 1 时加一否则减一；
+    //co 在 en 有效且将要溢出（加到全 1 或减到全 0）时为 1，n 至少为 1
+    pub fn build_counter(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "counter needs at least 1 bit");
+        let name = build(circuit, &format!("counter{}", n), &ports(&["cp", "rst", "en", "up"]), |circuit, inputs| {
+            let (cp, rst, en, up) = (inputs[0], inputs[1], inputs[2], inputs[3]);
+            let down = circuit.add_gate("not", &[up]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let step = q.iter().map(|x| circuit.add_gate("xor", &[*x, down])).collect::<Vec<usize>>();
+                let (next, _) = toggle(circuit, q, en, &step);
+                clear(circuit, rst, &next)
+            });
+            //输出要在触发器之后由新的 q 算出
+            let step = q.iter().map(|x| circuit.add_gate("xor", &[*x, down])).collect::<Vec<usize>>();
+            let co = circuit.add_gate("and", &[&[en], &step[..]].concat());
+            [named("q", &q), vec![("co".to_string(), co)]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("q = q + 1 when up else q - 1 on rising edge when en");
+        name
+    }
+
+    //模 modulus 计数器 mod{modulus}：输入 cp, rst, en，从 0 数到 modulus - 1 后回到 0，tc 为 q == modulus - 1，modulus 至少为 1
+    pub fn build_mod_counter(circuit: &mut Circuit, modulus: usize) -> String {
+        assert!(modulus >= 1, "mod counter needs a modulus of at least 1");
+        let n = select_width(modulus).max(1);
+        let last = |circuit: &mut Circuit, q: &[usize]| {
+            let bits = (0..n)
+                .map(|i| if (modulus - 1) >> i & 1 == 1 { q[i] } else { circuit.add_gate("not", &[q[i]]) })
+                .collect::<Vec<usize>>();
+            circuit.add_gate("and", &bits)
+        };
+        let name = build(circuit, &format!("mod{}", modulus), &ports(&["cp", "rst", "en"]), |circuit, inputs| {
+            let (cp, rst, en) = (inputs[0], inputs[1], inputs[2]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let (next, _) = toggle(circuit, q, en, q);
+                let tc = last(circuit, q);
+                let wrap = circuit.add_gate("and", &[en, tc]);
+                let reset = circuit.add_gate("or", &[rst, wrap]);
+                clear(circuit, reset, &next)
+            });
+            let tc = last(circuit, &q);
+            [named("q", &q), vec![("tc".to_string(), tc)]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description(&format!("q = (q + 1) mod {} on rising edge when en", modulus));
+        name
+    }
+
+    //n 位环形计数器 ring{n}：输入 cp, rst，rst 使 q 回到 q[0] = 1，之后每个上升沿循环移动一位，n 至少为 1
+    pub fn build_ring_counter(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "ring counter needs at least 1 bit");
+        let name = build(circuit, &format!("ring{}", n), &ports(&["cp", "rst"]), |circuit, inputs| {
+            let (cp, rst) = (inputs[0], inputs[1]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let first = circuit.add_gate("or", &[rst, q[n - 1]]);
+                [vec![first], clear(circuit, rst, &q[..n - 1])].concat()
+            });
+            named("q", &q)
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("rotate q by one bit on rising edge");
+        name
+    }
+
+    //n 位扭环计数器 johnson{n}：输入 cp, rst，q[0] 取 q[n - 1] 的反，共 2n 个状态，n 至少为 1
+    pub fn build_johnson_counter(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "johnson counter needs at least 1 bit");
+        let name = build(circuit, &format!("johnson{}", n), &ports(&["cp", "rst"]), |circuit, inputs| {
+            let (cp, rst) = (inputs[0], inputs[1]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let first = circuit.add_gate("not", &[q[n - 1]]);
+                clear(circuit, rst, &[&[first], &q[..n - 1]].concat())
+            });
+            named("q", &q)
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("shift not(q[n - 1]) into q[0] on rising edge");
+        name
+    }
+
+    //n 位串入并出移位寄存器 sipo{n}：输入 cp, si，每个上升沿 si 移入 q[0]，其余各位向高位移动，n 至少为 1
+    pub fn build_sipo(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "sipo needs at least 1 bit");
+        let name = build(circuit, &format!("sipo{}", n), &ports(&["cp", "si"]), |circuit, inputs| {
+            let (cp, si) = (inputs[0], inputs[1]);
+            let q = edge_bits(circuit, cp, None, n, |_, q| [&[si], &q[..n - 1]].concat());
+            named("q", &q)
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("shift si into q[0] on rising edge");
+        name
+    }
+
+    //n 位并入串出移位寄存器 piso{n}：输入 cp, load, d[]，load 为 1 时装入 d，否则向高位移动并补 0，so = q[n - 1]，n 至少为 1
+    pub fn build_piso(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "piso needs at least 1 bit");
+        let input_name = [ports(&["cp", "load"]), Self::port_names("d", n)].concat();
+        let name = build(circuit, &format!("piso{}", n), &input_name, |circuit, inputs| {
+            let (cp, load, d) = (inputs[0], inputs[1], &inputs[2..]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let shift = circuit.add_gate("not", &[load]);
+                (0..n)
+                    .map(|i| match i {
+                        0 => circuit.add_gate("and", &[load, d[0]]),
+                        i => select(circuit, &[(load, d[i]), (shift, q[i - 1])]),
+                    })
+                    .collect()
+            });
+            [named("q", &q), vec![("so".to_string(), q[n - 1])]].concat()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("load d or shift q towards so on rising edge");
+        name
+    }
+
+    //n 位通用移位寄存器 shift{n}：输入 cp, s[0..2], sr, sl, d[]。
+    //s 为 0 保持，1 右移（sr 移入 q[0]，其余向高位移动），2 左移（sl 移入 q[n - 1]），3 并行装入 d，n 至少为 1
+    pub fn build_universal_shift(circuit: &mut Circuit, n: usize) -> String {
+        assert!(n >= 1, "shift register needs at least 1 bit");
+        let input_name = [ports(&["cp"]), Self::port_names("s", 2), ports(&["sr", "sl"]), Self::port_names("d", n)].concat();
+        let name = build(circuit, &format!("shift{}", n), &input_name, |circuit, inputs| {
+            let (cp, s, sr, sl, d) = (inputs[0], &inputs[1..3], inputs[3], inputs[4], &inputs[5..]);
+            let q = edge_bits(circuit, cp, None, n, |circuit, q| {
+                let ns = s.iter().map(|x| circuit.add_gate("not", &[*x])).collect::<Vec<usize>>();
+                let hold = circuit.add_gate("and", &[ns[0], ns[1]]);
+                let right = circuit.add_gate("and", &[s[0], ns[1]]);
+                let left = circuit.add_gate("and", &[ns[0], s[1]]);
+                let load = circuit.add_gate("and", &[s[0], s[1]]);
+                (0..n)
+                    .map(|i| {
+                        let lower = if i == 0 { sr } else { q[i - 1] };
+                        let upper = if i == n - 1 { sl } else { q[i + 1] };
+                        select(circuit, &[(hold, q[i]), (right, lower), (left, upper), (load, d[i])])
+                    })
+                    .collect()
+            });
+            named("q", &q)
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("s: 0 hold, 1 shift right, 2 shift left, 3 load");
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::read;
+
+    //实例化计数器，返回 q[] 和附加输出
+    fn instance(circuit: &mut Circuit, name: &str, width: usize) -> (Vec<usize>, Vec<usize>) {
+        let outputs = crate::testing::instance(circuit, name);
+        (outputs[..width].to_vec(), outputs[width..].to_vec())
+    }
+
+    //cp 依次为 0 和 1，其余输入不变，返回 q 组成的整数
+    fn clock(circuit: &mut Circuit, q: &[usize], inputs: &[usize]) -> usize {
+        circuit.execute_sequential_mut(&[&[0], inputs].concat());
+        circuit.execute_sequential_mut(&[&[1], inputs].concat());
+        read(circuit, q)
+    }
+
+    #[test]
+    fn test_counters() {
+        let mut circuit = Circuit::new(4);
+        assert_eq!(Pattern::build_counter(&mut circuit, 3), "counter3");
+        assert_eq!(circuit.get_pattern("counter3").get_output_name(), &["q[0]", "q[1]", "q[2]", "co"]);
+        assert_eq!(circuit.get_pattern("counter3").get_state().len(), 3);
+        let (q, co) = instance(&mut circuit, "counter3", 3);
+        for expected in (1..8).chain([0, 1]) {
+            assert_eq!(clock(&mut circuit, &q, &[0, 1, 1]), expected);
+        }
+        //保持高电平、en 为 0 时不变
+        circuit.execute_sequential_mut(&[1, 0, 1, 1]);
+        assert_eq!(clock(&mut circuit, &q, &[0, 0, 1]), 1);
+        for expected in [0, 7, 6] {
+            assert_eq!(clock(&mut circuit, &q, &[0, 1, 0]), expected);
+        }
+        assert_eq!(clock(&mut circuit, &q, &[1, 1, 1]), 0);
+        circuit.execute_sequential_mut(&[0, 0, 1, 0]);
+        assert!(circuit.get_signal(co[0]).get_unchecked());
+
+        let mut circuit = Circuit::new(3);
+        Pattern::build_mod_counter(&mut circuit, 6);
+        let (q, tc) = instance(&mut circuit, "mod6", 3);
+        for expected in [1, 2, 3, 4, 5, 0, 1, 2] {
+            assert_eq!(clock(&mut circuit, &q, &[0, 1]), expected);
+        }
+        assert_eq!(clock(&mut circuit, &q, &[0, 0]), 2);
+        assert_eq!(clock(&mut circuit, &q, &[1, 1]), 0);
+        for _ in 0..5 { clock(&mut circuit, &q, &[0, 1]); }
+        assert!(circuit.get_signal(tc[0]).get_unchecked());
+
+        let mut circuit = Circuit::new(2);
+        Pattern::build_ring_counter(&mut circuit, 4);
+        let (q, _) = instance(&mut circuit, "ring4", 4);
+        assert_eq!(clock(&mut circuit, &q, &[1]), 1);
+        for expected in [2, 4, 8, 1, 2] {
+            assert_eq!(clock(&mut circuit, &q, &[0]), expected);
+        }
+
+        let mut circuit = Circuit::new(2);
+        Pattern::build_johnson_counter(&mut circuit, 3);
+        let (q, _) = instance(&mut circuit, "johnson3", 3);
+        for expected in [1, 3, 7, 6, 4, 0, 1] {
+            assert_eq!(clock(&mut circuit, &q, &[0]), expected);
+        }
+        assert_eq!(clock(&mut circuit, &q, &[1]), 0);
+    }
+
+    #[test]
+    fn test_shift_registers() {
+        let mut circuit = Circuit::new(2);
+        Pattern::build_sipo(&mut circuit, 4);
+        let (q, _) = instance(&mut circuit, "sipo4", 4);
+        for (si, expected) in [(1, 0b0001), (0, 0b0010), (1, 0b0101), (1, 0b1011), (0, 0b0110)] {
+            assert_eq!(clock(&mut circuit, &q, &[si]), expected);
+        }
+
+        let mut circuit = Circuit::new(6);
+        Pattern::build_piso(&mut circuit, 4);
+        let (q, so) = instance(&mut circuit, "piso4", 4);
+        assert_eq!(clock(&mut circuit, &q, &[1, 1, 0, 1, 1]), 0b1101);
+        let mut serial = Vec::new();
+        for _ in 0..4 {
+            serial.push(circuit.get_signal(so[0]).get_unchecked() as usize);
+            clock(&mut circuit, &q, &[0, 0, 0, 0, 0]);
+        }
+        assert_eq!(serial, [1, 1, 0, 1]);
+        assert_eq!(clock(&mut circuit, &q, &[0, 0, 0, 0, 0]), 0);
+
+        let mut circuit = Circuit::new(9);
+        Pattern::build_universal_shift(&mut circuit, 4);
+        assert_eq!(circuit.get_pattern("shift4").get_input_name()[..5], ["cp", "s[0]", "s[1]", "sr", "sl"]);
+        let (q, _) = instance(&mut circuit, "shift4", 4);
+        //输入为 s[0], s[1], sr, sl, d[]
+        let steps = [
+            ([1, 1, 0, 0, 0, 1, 1, 0], 0b0110),
+            ([0, 0, 1, 1, 1, 1, 1, 1], 0b0110),
+            ([1, 0, 1, 0, 0, 0, 0, 0], 0b1101),
+            ([1, 0, 0, 0, 0, 0, 0, 0], 0b1010),
+            ([0, 1, 0, 1, 0, 0, 0, 0], 0b1101),
+            ([0, 1, 0, 0, 0, 0, 0, 0], 0b0110),
+        ];
+        for (inputs, expected) in steps {
+            assert_eq!(clock(&mut circuit, &q, &inputs), expected, "{:?}", inputs);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1 bit")]
+    fn test_zero_width() {
+        Pattern::build_johnson_counter(&mut Circuit::new(0), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus of at least 1")]
+    fn test_zero_modulus() {
+        Pattern::build_mod_counter(&mut Circuit::new(0), 0);
+    }
+}
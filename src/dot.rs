@@ -0,0 +1,194 @@
+use std::fmt::Write;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PniType};
+use crate::signal::Signal;
+
+//graphviz 没有逻辑门的形状，用不同的形状区分门的种类
+fn gate_style(node_type: &NodeType) -> (&'static str, &'static str) {
+    match node_type {
+        NodeType::And => ("AND", "box"),
+        NodeType::Or => ("OR", "ellipse"),
+        NodeType::Not => ("NOT", "invtriangle"),
+        NodeType::Xor => ("XOR", "hexagon"),
+        NodeType::NAnd => ("NAND", "octagon"),
+        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+//有效值为 1 时为红色，0 为蓝色，无效时为灰色虚线
+fn value_style(signal: Signal) -> &'static str {
+    if !signal.is_valid() {
+        " [color=gray, style=dashed]"
+    } else if signal.get_unchecked() {
+        " [color=red, penwidth=2]"
+    } else {
+        " [color=blue]"
+    }
+}
+
+//写出 pattern 内部的节点和边，端口为 {prefix}i{j} / {prefix}o{k}，内部的 pattern 写成嵌套的 cluster
+fn write_pattern(dot: &mut String, circuit: &Circuit, pattern: &Pattern, prefix: &str, indent: usize) {
+    let pad = " ".repeat(indent);
+    for j in 0..pattern.input_size() {
+        writeln!(dot, "{}{}i{} [shape=plaintext, label={}];", pad, prefix, j, quote(&pattern.input_port(j))).unwrap();
+    }
+    for k in 0..pattern.output_size() {
+        writeln!(dot, "{}{}o{} [shape=plaintext, label={}];", pad, prefix, k, quote(&pattern.output_port(k))).unwrap();
+    }
+
+    let nodes = pattern.get_pattern();
+    let source = |input: &PniType| match input {
+        PniType::Input(j) => format!("{}i{}", prefix, j),
+        PniType::NodeOutput((n, k)) => match nodes[*n].get_type() {
+            NodeType::Pattern(_) => format!("{}n{}_o{}", prefix, n, k),
+            _ => format!("{}n{}", prefix, n),
+        },
+    };
+    for (i, node) in nodes.iter().enumerate() {
+        match node.get_type() {
+            NodeType::Pattern(name) => {
+                let inner = format!("{}n{}_", prefix, i);
+                writeln!(dot, "{}subgraph cluster_{}n{} {{", pad, prefix, i).unwrap();
+                writeln!(dot, "{}    label={};", pad, quote(name)).unwrap();
+                write_pattern(dot, circuit, circuit.get_pattern(name), &inner, indent + 4);
+                writeln!(dot, "{}}}", pad).unwrap();
+                for (j, input) in node.get_input().iter().enumerate() {
+                    writeln!(dot, "{}{} -> {}i{};", pad, source(input), inner, j).unwrap();
+                }
+            }
+            gate => {
+                let (label, shape) = gate_style(gate);
+                writeln!(dot, "{}{}n{} [shape={}, label=\"{}\"];", pad, prefix, i, shape, label).unwrap();
+                for input in node.get_input() {
+                    writeln!(dot, "{}{} -> {}n{};", pad, source(input), prefix, i).unwrap();
+                }
+            }
+        }
+    }
+    for (k, output) in pattern.get_output().iter().enumerate() {
+        writeln!(dot, "{}{} -> {}o{};", pad, source(&PniType::NodeOutput(*output)), prefix, k).unwrap();
+    }
+}
+
+impl Pattern {
+    //circuit 用来查找内部用到的其它 pattern
+    pub fn to_dot(&self, name: &str, circuit: &Circuit) -> String {
+        let mut dot = format!("digraph {} {{\n    rankdir=LR;\n", quote(name));
+        if !self.get_description().is_empty() {
+            writeln!(dot, "    label={};", quote(self.get_description())).unwrap();
+        }
+        write_pattern(&mut dot, circuit, self, "", 4);
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Circuit {
+    //参数与 to_verilog 相同；values 为 true 时按信号的当前值给电路层的连线着色
+    pub fn to_dot(&self, input_name: &[&str], outputs: &[(&str, usize)], values: bool) -> String {
+        let input_size = self.get_input().len();
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n");
+        for i in 0..input_size {
+            let name = input_name.get(i).map(|s| s.to_string()).unwrap_or_else(|| format!("i{}", i));
+            writeln!(dot, "    s{} [shape=plaintext, label={}];", i, quote(&name)).unwrap();
+        }
+
+        //信号的来源：输入、门、pattern 的输出端口，没有驱动的信号单独画出
+        let source = |s: usize| match self.get_parent_node_index(s) {
+            _ if s < input_size => format!("s{}", s),
+            Some(p) => match self.get_node(p).get_type() {
+                NodeType::Pattern(_) => {
+                    let k = self.get_node(p).get_output().iter().position(|o| *o == s).unwrap();
+                    format!("u{}_o{}", p, k)
+                }
+                NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => {
+                    let k = self.get_node(p).get_output().iter().position(|o| *o == s).unwrap();
+                    format!("g{}:q{}", p, k)
+                }
+                _ => format!("g{}", p),
+            },
+            None => format!("s{}", s),
+        };
+        for (s, signal) in self.get_signals().iter().enumerate().skip(input_size) {
+            if signal.get_parent().is_none() {
+                let label = if signal.is_valid() { (signal.get_unchecked() as usize).to_string() } else { "?".to_string() };
+                writeln!(dot, "    s{} [shape=plaintext, label=\"{}\"];", s, label).unwrap();
+            }
+        }
+        let style = |s: usize| if values { value_style(self.get_signal(s)) } else { "" };
+
+        for (k, node) in self.get_pipeline().iter().enumerate() {
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    writeln!(dot, "    subgraph cluster_u{} {{", k).unwrap();
+                    writeln!(dot, "        label={};", quote(name)).unwrap();
+                    write_pattern(&mut dot, self, self.get_pattern(name), &format!("u{}_", k), 8);
+                    writeln!(dot, "    }}").unwrap();
+                    for (j, s) in node.get_input().iter().enumerate() {
+                        writeln!(dot, "    {} -> u{}_i{}{};", source(*s), k, j, style(*s)).unwrap();
+                    }
+                }
+                NodeType::Register(width) | NodeType::Rom(_, width) | NodeType::Ram(_, width) => {
+                    let label = match node.get_type() {
+                        NodeType::Register(_) => "REG",
+                        NodeType::Rom(..) => "ROM",
+                        _ => "RAM",
+                    };
+                    let q = (0..*width).map(|i| format!("<q{}> q{}", i, i)).collect::<Vec<String>>();
+                    writeln!(dot, "    g{} [shape=record, label=\"{}|{{{}}}\"];", k, label, q.join("|")).unwrap();
+                    for s in node.get_input() {
+                        writeln!(dot, "    {} -> g{}{};", source(*s), k, style(*s)).unwrap();
+                    }
+                }
+                gate => {
+                    let (label, shape) = gate_style(gate);
+                    writeln!(dot, "    g{} [shape={}, label=\"{}\"];", k, shape, label).unwrap();
+                    for s in node.get_input() {
+                        writeln!(dot, "    {} -> g{}{};", source(*s), k, style(*s)).unwrap();
+                    }
+                }
+            }
+        }
+        for (j, (name, s)) in outputs.iter().enumerate() {
+            writeln!(dot, "    out{} [shape=plaintext, label={}];", j, quote(name)).unwrap();
+            writeln!(dot, "    {} -> out{}{};", source(*s), j, style(*s)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot() {
+        let mut circuit = Circuit::new_with_basic_pattern(4);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input[..3]).get_output().to_vec();
+        let n = circuit.add_gate("nand", &[sum[0], input[3]]);
+        circuit.execute_sequential_mut(&[0, 1, 1, 1]);
+
+        let dot = circuit.to_dot(&["c0", "a", "b", "r"], &[("c1", sum[1]), ("n", n)], true);
+        assert!(dot.starts_with("digraph circuit {\n    rankdir=LR;\n    s0 [shape=plaintext, label=\"c0\"];"));
+        assert!(dot.contains("    subgraph cluster_u0 {\n        label=\"full_adder\";\n        u0_i0 [shape=plaintext, label=\"c0\"];"));
+        assert!(dot.contains("        u0_n0 [shape=hexagon, label=\"XOR\"];\n        u0_i1 -> u0_n0;"));
+        assert!(dot.contains("        u0_n4 -> u0_o1;"));
+        assert!(dot.contains("    s1 -> u0_i1 [color=red, penwidth=2];"));
+        assert!(dot.contains("    g1 [shape=octagon, label=\"NAND\"];\n    u0_o0 -> g1 [color=blue];"));
+        assert!(dot.contains("    u0_o1 -> out0 [color=red, penwidth=2];"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+
+        let plain = circuit.get_pattern("rsff").to_dot("rsff", &circuit);
+        assert!(plain.starts_with("digraph \"rsff\" {"));
+        assert!(plain.contains("    n3 -> n2;"));
+        assert!(!plain.contains("color"));
+    }
+}
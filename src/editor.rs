@@ -0,0 +1,665 @@
+use std::collections::{HashMap, HashSet};
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2};
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::Pattern;
+use crate::schematic::{Block, BlockKind, Edge};
+use crate::waveform::{History, Lane, WaveformViewer};
+
+//鼠标与引脚的距离小于该值时认为选中了引脚
+const PIN_RADIUS: f32 = 6.0;
+//时序电路反复执行直到稳定的最大次数
+const SETTLE: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum EditorError {
+    //编辑器没有对应的元件
+    #[error("behavioural node {0:?} cannot be edited")]
+    Unsupported(NodeType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    //输入开关及其当前值
+    Switch(String, bool),
+    Constant(bool),
+    Lamp(String),
+    //门的类型和输入个数
+    Gate(NodeType, usize),
+    Pattern(String),
+}
+
+//编辑器中的电路：元件、元件左上角的位置和元件间的连线
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    parts: Vec<Part>,
+    positions: Vec<(f32, f32)>,
+    wires: Vec<Edge>,
+}
+
+impl Document {
+    pub fn get_parts(&self) -> &Vec<Part> { &self.parts }
+    pub fn get_wires(&self) -> &Vec<Edge> { &self.wires }
+
+    pub fn add_part(&mut self, part: Part, position: (f32, f32)) -> usize {
+        self.parts.push(part);
+        self.positions.push(position);
+        self.parts.len() - 1
+    }
+
+    //删除元件及与之相连的线，后面元件的下标前移
+    pub fn remove_part(&mut self, index: usize) {
+        self.parts.remove(index);
+        self.positions.remove(index);
+        self.wires.retain(|(from, to)| from.0 != index && to.0 != index);
+        let shift = |i: &mut usize| if *i > index { *i -= 1 };
+        for (from, to) in self.wires.iter_mut() {
+            shift(&mut from.0);
+            shift(&mut to.0);
+        }
+    }
+
+    //一个输入引脚只能连一条线，新的连线替换旧的
+    pub fn connect(&mut self, from: (usize, usize), to: (usize, usize)) {
+        self.disconnect(to);
+        self.wires.push((from, to));
+    }
+
+    pub fn disconnect(&mut self, to: (usize, usize)) {
+        self.wires.retain(|(_, t)| *t != to);
+    }
+
+    pub fn driver(&self, to: (usize, usize)) -> Option<(usize, usize)> {
+        self.wires.iter().find(|(_, t)| *t == to).map(|(f, _)| *f)
+    }
+
+    //元件的外形和引脚位置，patterns 用来查找 pattern 的端口名
+    pub fn block(&self, index: usize, patterns: &HashMap<String, Pattern>) -> Block {
+        let (kind, input_size, output_size) = match &self.parts[index] {
+            Part::Switch(name, _) => (BlockKind::Input(name.clone()), 0, 1),
+            Part::Constant(value) => (BlockKind::Input((*value as usize).to_string()), 0, 1),
+            Part::Lamp(name) => (BlockKind::Output(name.clone()), 1, 0),
+            Part::Gate(gate, size) => (BlockKind::Gate(gate.clone()), *size, 1),
+            Part::Pattern(name) => {
+                let pattern = &patterns[name];
+                let inputs = (0..pattern.input_size()).map(|i| pattern.input_port(i)).collect();
+                let outputs = (0..pattern.output_size()).map(|i| pattern.output_port(i)).collect();
+                let kind = BlockKind::Pattern { name: name.clone(), inputs, outputs };
+                (kind, pattern.input_size(), pattern.output_size())
+            }
+        };
+        let mut block = Block::new(kind, input_size, output_size);
+        let (x, y) = self.positions[index];
+        block.place(x, y, input_size, output_size);
+        block
+    }
+
+    //开关按顺序成为电路的输入，返回电路和每个元件输出引脚对应的信号。
+    //门按连线拓扑排序，有环时先放下标最小的，没有连线的输入引脚接常量 0
+    pub fn to_circuit(&self, patterns: &HashMap<String, Pattern>) -> (Circuit, Vec<Vec<usize>>) {
+        let switches = self.parts.iter().filter(|p| matches!(p, Part::Switch(..))).count();
+        let mut circuit = Circuit::new(switches);
+        *circuit.get_patterns_mut() = patterns.clone();
+        let mut signals = vec![Vec::new(); self.parts.len()];
+        let mut next_input = 0;
+        for (i, part) in self.parts.iter().enumerate() {
+            signals[i] = match part {
+                Part::Switch(..) => {
+                    next_input += 1;
+                    vec![next_input - 1]
+                }
+                Part::Constant(value) => circuit.advance_output_with_value(&[*value as usize]),
+                Part::Lamp(_) => Vec::new(),
+                Part::Gate(..) => circuit.advance_output_with_value(&[0]),
+                Part::Pattern(name) => circuit.advance_output_with_value(&vec![0; patterns[name].output_size()]),
+            };
+        }
+        let zero = circuit.advance_output_with_value(&[0])[0];
+
+        let input_size = |i: usize| match &self.parts[i] {
+            Part::Gate(_, size) => *size,
+            Part::Pattern(name) => patterns[name].input_size(),
+            _ => 0,
+        };
+        let mut remaining = (0..self.parts.len())
+            .filter(|i| matches!(self.parts[*i], Part::Gate(..) | Part::Pattern(_)))
+            .collect::<Vec<usize>>();
+        while !remaining.is_empty() {
+            let ready = remaining.iter()
+                .position(|i| (0..input_size(*i)).all(|j| match self.driver((*i, j)) {
+                    Some((p, _)) => !remaining.contains(&p),
+                    None => true,
+                }))
+                .unwrap_or(0);
+            let i = remaining.remove(ready);
+            let inputs = (0..input_size(i))
+                .map(|j| self.driver((i, j)).map_or(zero, |(p, k)| signals[p][k]))
+                .collect();
+            let node_type = match &self.parts[i] {
+                Part::Gate(gate, _) => gate.clone(),
+                Part::Pattern(name) => NodeType::Pattern(name.clone()),
+                _ => unreachable!(),
+            };
+            circuit.add_node(node_type, inputs, signals[i].clone());
+        }
+        (circuit, signals)
+    }
+
+    //读入的电路没有位置信息，用 schematic 自动布局；没有被读取的节点输出画成灯
+    //编辑器没有寄存器、存储器这类行为级元件，含有它们的电路不能打开
+    pub fn from_circuit(circuit: &Circuit) -> Result<Document, EditorError> {
+        if let Some(node) = circuit.get_pipeline().iter()
+            .find(|n| matches!(n.get_type(), NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..))) {
+            return Err(EditorError::Unsupported(node.get_type().clone()));
+        }
+        let input_size = circuit.get_input().len();
+        let read = circuit.get_pipeline().iter()
+            .flat_map(|n| n.get_input().iter().copied())
+            .collect::<HashSet<usize>>();
+        let sinks = circuit.get_pipeline().iter()
+            .flat_map(|n| n.get_output().iter().copied())
+            .filter(|s| !read.contains(s))
+            .collect::<Vec<usize>>();
+        let names = sinks.iter().enumerate().map(|(k, _)| format!("o{}", k)).collect::<Vec<String>>();
+        let outputs = names.iter().zip(&sinks).map(|(n, s)| (n.as_str(), *s)).collect::<Vec<(&str, usize)>>();
+        let schematic = circuit.schematic(&[], &outputs);
+
+        let mut document = Document::default();
+        for (b, block) in schematic.get_blocks().iter().enumerate() {
+            let part = match (b, block.get_kind()) {
+                (b, BlockKind::Input(name)) if b < input_size => {
+                    let signal = circuit.get_signal(b);
+                    Part::Switch(name.clone(), signal.is_valid() && signal.get_unchecked())
+                }
+                (b, BlockKind::Input(label)) => Part::Constant(label == "1"),
+                (_, BlockKind::Output(name)) => Part::Lamp(name.clone()),
+                (_, BlockKind::Gate(gate)) => Part::Gate(gate.clone(), block.input_size()),
+                (_, BlockKind::Pattern { name, .. }) => Part::Pattern(name.clone()),
+            };
+            let (x, y, _, _) = block.get_rect();
+            document.add_part(part, (x, y));
+        }
+        document.wires = schematic.get_edges().clone();
+        Ok(document)
+    }
+
+    fn switch_values(&self) -> Vec<usize> {
+        self.parts.iter()
+            .filter_map(|p| match p {
+                Part::Switch(_, value) => Some(*value as usize),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+//按开关的值反复执行，直到所有信号不再变化
+fn settle(circuit: &mut Circuit, inputs: &[usize]) {
+    for _ in 0..SETTLE {
+        let before = circuit.get_signals().clone();
+        circuit.execute_sequential_mut(inputs);
+        if *circuit.get_signals() == before { break; }
+    }
+}
+
+fn value_color(circuit: &Circuit, signal: usize) -> Color32 {
+    let signal = circuit.get_signal(signal);
+    if !signal.is_valid() {
+        Color32::GRAY
+    } else if signal.get_unchecked() {
+        Color32::RED
+    } else {
+        Color32::BLUE
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    Move(usize),
+    Wire(usize, usize),
+}
+
+pub struct Editor {
+    //提供可以放置的 pattern
+    library: Circuit,
+    document: Document,
+    circuit: Circuit,
+    signals: Vec<Vec<usize>>,
+    tool: Option<Part>,
+    gate_inputs: usize,
+    drag: Option<Drag>,
+    selected: Option<usize>,
+    path: String,
+    status: String,
+    //每次仿真后的信号都记入历史，在波形窗口中查看
+    history: History,
+    waveform: WaveformViewer,
+    show_waveform: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        let mut library = Circuit::new_with_basic_pattern(0);
+        Pattern::build_full_adder(&mut library);
+        Pattern::build_half_adder(&mut library);
+        Pattern::build_jkff(&mut library);
+        Pattern::build_tff(&mut library);
+        Pattern::build_dff(&mut library);
+        Pattern::build_dlatch(&mut library);
+        let mut editor = Self {
+            library,
+            document: Document::default(),
+            circuit: Circuit::new(0),
+            signals: Vec::new(),
+            tool: None,
+            gate_inputs: 2,
+            drag: None,
+            selected: None,
+            path: "circuit.json".to_string(),
+            status: String::new(),
+            history: History::new(),
+            waveform: WaveformViewer::default(),
+            show_waveform: false,
+        };
+        editor.rebuild();
+        editor
+    }
+}
+
+impl Editor {
+    //结构改变后重新生成电路，信号从初始值开始，波形默认显示开关和灯
+    fn rebuild(&mut self) {
+        (self.circuit, self.signals) = self.document.to_circuit(self.library.get_patterns());
+        self.history.clear();
+        let parts = &self.document.parts;
+        let lanes = parts.iter().enumerate()
+            .filter_map(|(i, part)| match part {
+                Part::Switch(name, _) => Some(Lane::bit(name, self.signals[i][0])),
+                Part::Lamp(name) => self.document.driver((i, 0)).map(|(p, k)| Lane::bit(name, self.signals[p][k])),
+                _ => None,
+            })
+            .collect();
+        self.waveform = WaveformViewer::new(lanes);
+        self.simulate();
+    }
+
+    //只改变开关时保留电路状态
+    fn simulate(&mut self) {
+        settle(&mut self.circuit, &self.document.switch_values());
+        self.history.push(self.circuit.get_signals());
+    }
+
+    fn signal_names(&self) -> Vec<String> {
+        let switches = self.document.parts.iter()
+            .filter_map(|p| match p {
+                Part::Switch(name, _) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>();
+        self.circuit.signal_names(&switches)
+    }
+
+    fn block(&self, index: usize) -> Block {
+        self.document.block(index, self.library.get_patterns())
+    }
+
+    fn part_at(&self, pos: Pos2) -> Option<usize> {
+        (0..self.document.parts.len()).rev().find(|i| {
+            let (x, y, w, h) = self.block(*i).get_rect();
+            Rect::from_min_size(Pos2::new(x, y), Vec2::new(w, h)).contains(pos)
+        })
+    }
+
+    fn output_pin_at(&self, pos: Pos2) -> Option<(usize, usize)> {
+        (0..self.document.parts.len()).find_map(|i| {
+            let block = self.block(i);
+            (0..block.output_size())
+                .find(|k| Pos2::from(block.output_pin(*k)).distance(pos) < PIN_RADIUS)
+                .map(|k| (i, k))
+        })
+    }
+
+    fn input_pin_at(&self, pos: Pos2) -> Option<(usize, usize)> {
+        (0..self.document.parts.len()).find_map(|i| {
+            let block = self.block(i);
+            (0..block.input_size())
+                .find(|j| Pos2::from(block.input_pin(*j)).distance(pos) < PIN_RADIUS)
+                .map(|j| (i, j))
+        })
+    }
+
+    fn place(&mut self, part: Part, pos: Pos2) {
+        let count = |f: fn(&Part) -> bool| self.document.parts.iter().filter(|p| f(p)).count();
+        let part = match part {
+            Part::Switch(..) => Part::Switch(format!("i{}", count(|p| matches!(p, Part::Switch(..)))), false),
+            Part::Lamp(_) => Part::Lamp(format!("o{}", count(|p| matches!(p, Part::Lamp(_))))),
+            part => part,
+        };
+        self.document.add_part(part, (pos.x, pos.y));
+        self.rebuild();
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.document.remove_part(index);
+        self.selected = None;
+        self.rebuild();
+    }
+
+    fn save(&mut self) {
+        let result = serde_json::to_string_pretty(&self.circuit)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&self.path, json).map_err(|e| e.to_string()));
+        self.status = match result {
+            Ok(()) => format!("saved {}", self.path),
+            Err(e) => format!("save failed: {}", e),
+        };
+    }
+
+    //读入电路中的 pattern 并入元件库
+    fn open(&mut self) {
+        let result = std::fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<Circuit>(&json).map_err(|e| e.to_string()))
+            .and_then(|circuit| Document::from_circuit(&circuit)
+                .map(|document| (circuit, document))
+                .map_err(|e| e.to_string()));
+        match result {
+            Ok((mut circuit, document)) => {
+                self.document = document;
+                self.library.get_patterns_mut().extend(std::mem::take(circuit.get_patterns_mut()));
+                self.selected = None;
+                self.rebuild();
+                self.status = format!("opened {}", self.path);
+            }
+            Err(e) => self.status = format!("open failed: {}", e),
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("New").clicked() {
+                self.document = Document::default();
+                self.selected = None;
+                self.rebuild();
+            }
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button("Open").clicked() { self.open(); }
+            if ui.button("Save").clicked() { self.save(); }
+            ui.separator();
+            if ui.button("Step").clicked() {
+                self.circuit.execute_sequential_mut(&self.document.switch_values());
+                self.history.push(self.circuit.get_signals());
+            }
+            if ui.button("Reset").clicked() { self.rebuild(); }
+            ui.toggle_value(&mut self.show_waveform, "Waveform");
+            ui.separator();
+            ui.label(&self.status);
+        });
+    }
+
+    fn palette(&mut self, ui: &mut egui::Ui) {
+        let mut names = self.library.get_patterns().keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        ui.heading("Gates");
+        ui.add(egui::Slider::new(&mut self.gate_inputs, 2..=8).text("inputs"));
+        let size = self.gate_inputs;
+        let mut tool = |ui: &mut egui::Ui, label: &str, part: Part| {
+            if ui.selectable_label(self.tool.as_ref() == Some(&part), label).clicked() {
+                self.tool = Some(part);
+            }
+        };
+        tool(ui, "AND", Part::Gate(NodeType::And, size));
+        tool(ui, "OR", Part::Gate(NodeType::Or, size));
+        tool(ui, "NOT", Part::Gate(NodeType::Not, 1));
+        tool(ui, "XOR", Part::Gate(NodeType::Xor, 2));
+        tool(ui, "NAND", Part::Gate(NodeType::NAnd, size));
+        ui.heading("I/O");
+        tool(ui, "Switch", Part::Switch(String::new(), false));
+        tool(ui, "Constant 0", Part::Constant(false));
+        tool(ui, "Constant 1", Part::Constant(true));
+        tool(ui, "Lamp", Part::Lamp(String::new()));
+        ui.heading("Patterns");
+        for name in names {
+            tool(ui, &name, Part::Pattern(name.clone()));
+        }
+    }
+
+    fn canvas(&mut self, ui: &mut egui::Ui) {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let origin = response.rect.min.to_vec2();
+        let local = |pos: Pos2| pos - origin;
+
+        if response.drag_started() && let Some(pos) = ui.input(|i| i.pointer.press_origin()) {
+            let pos = local(pos);
+            self.drag = match self.output_pin_at(pos) {
+                Some((i, k)) => Some(Drag::Wire(i, k)),
+                None => self.part_at(pos).map(Drag::Move),
+            };
+        }
+        if response.dragged() && let Some(Drag::Move(i)) = self.drag {
+            let delta = response.drag_delta();
+            let (x, y) = self.document.positions[i];
+            self.document.positions[i] = (x + delta.x, y + delta.y);
+        }
+        if response.drag_stopped() {
+            if let (Some(Drag::Wire(i, k)), Some(pos)) = (self.drag, response.interact_pointer_pos())
+                && let Some(to) = self.input_pin_at(local(pos))
+            {
+                self.document.connect((i, k), to);
+                self.rebuild();
+            }
+            self.drag = None;
+        }
+        if response.clicked() && let Some(pos) = response.interact_pointer_pos() {
+            let pos = local(pos);
+            if let Some(part) = self.tool.take() {
+                self.place(part, pos);
+            } else {
+                self.selected = self.part_at(pos);
+                if let Some(i) = self.selected && let Part::Switch(_, value) = &mut self.document.parts[i] {
+                    *value = !*value;
+                    self.simulate();
+                }
+            }
+        }
+        if response.secondary_clicked() && let Some(pos) = response.interact_pointer_pos() {
+            let pos = local(pos);
+            if let Some(to) = self.input_pin_at(pos) {
+                self.document.disconnect(to);
+                self.rebuild();
+            } else if let Some(i) = self.part_at(pos) {
+                self.remove(i);
+            }
+        }
+        if let Some(i) = self.selected && ui.input(|i| i.key_pressed(egui::Key::Delete)) {
+            self.remove(i);
+        }
+
+        let at = |(x, y): (f32, f32)| Pos2::new(x, y) + origin;
+        let font = FontId::monospace(12.0);
+        //连线按源信号的值着色
+        for (from, to) in &self.document.wires {
+            let (a, b) = (at(self.block(from.0).output_pin(from.1)), at(self.block(to.0).input_pin(to.1)));
+            let color = value_color(&self.circuit, self.signals[from.0][from.1]);
+            let mid = (a.x + b.x) / 2.0;
+            painter.line(vec![a, Pos2::new(mid, a.y), Pos2::new(mid, b.y), b], Stroke::new(2.0, color));
+        }
+        if let (Some(Drag::Wire(i, k)), Some(pos)) = (self.drag, response.hover_pos()) {
+            painter.line_segment([at(self.block(i).output_pin(k)), pos], Stroke::new(1.0, Color32::DARK_GRAY));
+        }
+        for (i, part) in self.document.parts.iter().enumerate() {
+            let block = self.block(i);
+            let (x, y, w, h) = block.get_rect();
+            let rect = Rect::from_min_size(at((x, y)), Vec2::new(w, h));
+            let stroke = if self.selected == Some(i) { Stroke::new(2.0, Color32::GOLD) } else { Stroke::new(1.5, Color32::BLACK) };
+            match part {
+                Part::Switch(name, value) => {
+                    let fill = if *value { Color32::RED } else { Color32::LIGHT_BLUE };
+                    painter.rect(rect, 3.0, fill, stroke, StrokeKind::Middle);
+                    painter.text(rect.center(), Align2::CENTER_CENTER, name, font.clone(), Color32::BLACK);
+                }
+                Part::Constant(value) => {
+                    painter.text(rect.right_center(), Align2::RIGHT_CENTER, *value as usize, font.clone(), Color32::BLACK);
+                }
+                Part::Lamp(name) => {
+                    let color = match self.document.driver((i, 0)) {
+                        Some((p, k)) => value_color(&self.circuit, self.signals[p][k]),
+                        None => Color32::GRAY,
+                    };
+                    painter.circle(rect.left_center(), 5.0, color, stroke);
+                    painter.text(rect.left_center() + Vec2::new(8.0, 0.0), Align2::LEFT_CENTER, name, font.clone(), Color32::BLACK);
+                }
+                Part::Gate(gate, _) => {
+                    //IEC 矩形符号，取反的门在输出端画小圆圈
+                    let (symbol, bubble) = match gate {
+                        NodeType::And => ("&", false),
+                        NodeType::Or => ("≥1", false),
+                        NodeType::Not => ("1", true),
+                        NodeType::Xor => ("=1", false),
+                        NodeType::NAnd => ("&", true),
+                        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+                    };
+                    let body = if bubble { Rect::from_min_max(rect.min, rect.max - Vec2::new(6.0, 0.0)) } else { rect };
+                    painter.rect(body, 2.0, Color32::WHITE, stroke, StrokeKind::Middle);
+                    painter.text(body.center(), Align2::CENTER_CENTER, symbol, font.clone(), Color32::BLACK);
+                    if bubble {
+                        painter.circle(Pos2::new(rect.max.x - 3.0, rect.center().y), 3.0, Color32::WHITE, stroke);
+                    }
+                }
+                Part::Pattern(name) => {
+                    painter.rect(rect, 2.0, Color32::WHITE, stroke, StrokeKind::Middle);
+                    painter.text(rect.center_top() + Vec2::new(0.0, 8.0), Align2::CENTER_CENTER, name, font.clone(), Color32::BLACK);
+                    if let BlockKind::Pattern { inputs, outputs, .. } = block.get_kind() {
+                        for (j, port) in inputs.iter().enumerate() {
+                            let pin = at(block.input_pin(j));
+                            painter.text(pin + Vec2::new(3.0, 0.0), Align2::LEFT_CENTER, port, font.clone(), Color32::BLACK);
+                        }
+                        for (k, port) in outputs.iter().enumerate() {
+                            let pin = at(block.output_pin(k));
+                            painter.text(pin - Vec2::new(3.0, 0.0), Align2::RIGHT_CENTER, port, font.clone(), Color32::BLACK);
+                        }
+                    }
+                }
+            }
+            for j in 0..block.input_size() {
+                painter.circle_filled(at(block.input_pin(j)), 2.5, Color32::DARK_GRAY);
+            }
+            for k in 0..block.output_size() {
+                painter.circle_filled(at(block.output_pin(k)), 2.5, value_color(&self.circuit, self.signals[i][k]));
+            }
+        }
+    }
+}
+
+impl eframe::App for Editor {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| self.toolbar(ui));
+        egui::SidePanel::left("palette").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.palette(ui));
+        });
+        if self.show_waveform {
+            let names = self.signal_names();
+            egui::Window::new("Waveform")
+                .open(&mut self.show_waveform)
+                .default_size([640.0, 320.0])
+                .show(ctx, |ui| self.waveform.show(ui, &self.history, &names));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| self.canvas(ui));
+    }
+}
+
+pub fn run() -> eframe::Result {
+    eframe::run_native(
+        "digicir",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(Editor::default()))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        let editor = Editor::default();
+        let patterns = editor.library.get_patterns();
+        let mut document = Document::default();
+        let a = document.add_part(Part::Switch("a".to_string(), true), (0.0, 0.0));
+        let b = document.add_part(Part::Switch("b".to_string(), true), (0.0, 40.0));
+        let c = document.add_part(Part::Switch("c".to_string(), false), (0.0, 80.0));
+        let s = document.add_part(Part::Lamp("s".to_string()), (300.0, 0.0));
+        let co = document.add_part(Part::Lamp("co".to_string()), (300.0, 40.0));
+        //先放下游的门，检查拓扑排序
+        let or = document.add_part(Part::Gate(NodeType::Or, 2), (200.0, 40.0));
+        let fa = document.add_part(Part::Pattern("full_adder".to_string()), (100.0, 0.0));
+        document.connect((c, 0), (fa, 0));
+        document.connect((a, 0), (fa, 1));
+        document.connect((b, 0), (fa, 2));
+        document.connect((fa, 0), (s, 0));
+        document.connect((fa, 1), (or, 0));
+        document.connect((fa, 0), (or, 1));
+        document.connect((or, 0), (co, 0));
+        //替换已有的连线
+        document.connect((a, 0), (or, 1));
+        assert_eq!(document.get_wires().len(), 7);
+
+        let (mut circuit, signals) = document.to_circuit(patterns);
+        settle(&mut circuit, &document.switch_values());
+        let value = |part: usize| {
+            let (p, k) = document.driver((part, 0)).unwrap();
+            circuit.get_signal(signals[p][k]).get_unchecked()
+        };
+        assert!(!value(s));
+        assert!(value(co));
+
+        let pin = document.block(or, patterns).input_pin(1);
+        assert_eq!(pin, (200.0, 40.0 + 4.0 + 16.0 * 1.5));
+
+        //保存为 Circuit 再读入，结构和取值不变
+        let json = serde_json::to_string(&circuit).unwrap();
+        let loaded = serde_json::from_str::<Circuit>(&json).unwrap();
+        let reopened = Document::from_circuit(&loaded).unwrap();
+        assert_eq!(reopened.get_parts().iter().filter(|p| matches!(p, Part::Switch(_, true))).count(), 2);
+        //灯不属于电路，没有被读取的 s 和或门输出成为新的灯
+        let lamps = (0..reopened.get_parts().len())
+            .filter(|p| matches!(reopened.get_parts()[*p], Part::Lamp(_)))
+            .collect::<Vec<usize>>();
+        assert_eq!(lamps.len(), 2);
+        assert!(reopened.get_parts().contains(&Part::Pattern("full_adder".to_string())));
+        let (mut circuit, signals) = reopened.to_circuit(patterns);
+        settle(&mut circuit, &reopened.switch_values());
+        let values = lamps.iter()
+            .map(|l| {
+                let (p, k) = reopened.driver((*l, 0)).unwrap();
+                (reopened.get_parts()[p].clone(), circuit.get_signal(signals[p][k]).get_unchecked())
+            })
+            .collect::<Vec<(Part, bool)>>();
+        assert!(values.contains(&(Part::Pattern("full_adder".to_string()), false)));
+        assert!(values.contains(&(Part::Gate(NodeType::Or, 2), true)));
+
+        document.remove_part(fa);
+        assert_eq!(document.get_wires().len(), 2);
+        assert!(document.get_wires().contains(&((a, 0), (or, 1))));
+    }
+
+    #[test]
+    fn test_behavioural() {
+        //保存后再读入，含有寄存器或 rom 的电路给出错误而不是变成找不到的 pattern
+        let mut circuit = Circuit::new(4);
+        let input = circuit.get_input();
+        circuit.add_register(input[0], input[1], input[2], &input[3..]).unwrap();
+        let json = serde_json::to_string(&circuit).unwrap();
+        let loaded = serde_json::from_str::<Circuit>(&json).unwrap();
+        let error = Document::from_circuit(&loaded).unwrap_err();
+        assert!(matches!(error, EditorError::Unsupported(NodeType::Register(1))));
+        assert_eq!(error.to_string(), "behavioural node Register(1) cannot be edited");
+
+        let mut circuit = Circuit::new(1);
+        circuit.add_rom(&[0], 2, vec![1, 2]).unwrap();
+        let json = serde_json::to_string(&circuit).unwrap();
+        let loaded = serde_json::from_str::<Circuit>(&json).unwrap();
+        assert!(matches!(Document::from_circuit(&loaded), Err(EditorError::Unsupported(NodeType::Rom(1, 2)))));
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use thiserror::Error;
+use crate::bdd::Bdd;
+use crate::circuit::Circuit;
+use crate::logic::{Logic, Simulation};
+use crate::pattern::Pattern;
+use crate::sat::{Cnf, Lit};
+use crate::signal::Signal;
+
+//输入不超过该数量时直接穷举，否则构造 BDD
+pub const EXHAUSTIVE_LIMIT: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum EquivError {
+    #[error("input size differs: {0} vs {1}")]
+    InputSize(usize, usize),
+    #[error("output size differs: {0} vs {1}")]
+    OutputSize(usize, usize),
+    #[error("output {0} depends on an uninitialized signal")]
+    Undetermined(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equal,
+    //反例，可以直接交给 execute_sequential
+    Differ(Vec<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Auto,
+    Exhaustive,
+    Bdd,
+    Sat,
+}
+
+enum Target<'a> {
+    Circuit(&'a Circuit, &'a [usize]),
+    //嵌套的 pattern 在后一个表中查找
+    Pattern(&'a Pattern, &'a HashMap<String, Pattern>),
+}
+
+impl Target<'_> {
+    fn input_size(&self) -> usize {
+        match self {
+            Target::Circuit(circuit, _) => circuit.get_input().len(),
+            Target::Pattern(pattern, _) => pattern.input_size(),
+        }
+    }
+
+    fn output_size(&self) -> usize {
+        match self {
+            Target::Circuit(_, outputs) => outputs.len(),
+            Target::Pattern(pattern, _) => pattern.output_size(),
+        }
+    }
+
+    fn evaluate<L: Logic>(&self, logic: &mut L, inputs: &[L::Value]) -> Result<Vec<L::Value>, EquivError> {
+        let values = match self {
+            Target::Circuit(circuit, outputs) => {
+                let values = circuit.evaluate(logic, inputs);
+                outputs.iter().map(|o| values[*o].clone()).collect()
+            }
+            Target::Pattern(pattern, patterns) => pattern.evaluate(logic, patterns, inputs, &[]),
+        };
+        values.into_iter()
+            .enumerate()
+            .map(|(i, v)| v.ok_or(EquivError::Undetermined(i)))
+            .collect()
+    }
+}
+
+fn check(a: Target, b: Target, method: Method) -> Result<Equivalence, EquivError> {
+    let input_size = a.input_size();
+    if input_size != b.input_size() {
+        return Err(EquivError::InputSize(input_size, b.input_size()));
+    }
+    if a.output_size() != b.output_size() {
+        return Err(EquivError::OutputSize(a.output_size(), b.output_size()));
+    }
+
+    match method {
+        Method::Exhaustive => check_exhaustive(&a, &b),
+        Method::Bdd => check_bdd(&a, &b),
+        Method::Sat => check_sat(&a, &b),
+        Method::Auto if input_size <= EXHAUSTIVE_LIMIT => check_exhaustive(&a, &b),
+        Method::Auto => check_bdd(&a, &b),
+    }
+}
+
+fn check_exhaustive(a: &Target, b: &Target) -> Result<Equivalence, EquivError> {
+    let n = a.input_size();
+    for row in 0..1usize << n {
+        //与 Circuit::truth_table 相同，第一个输入为最高位
+        let input = (0..n).map(|i| row >> (n - i - 1) & 1).collect::<Vec<usize>>();
+        let signals = input.iter().map(|v| Signal::from_usize(*v)).collect::<Vec<Signal>>();
+        if a.evaluate(&mut Simulation, &signals)? != b.evaluate(&mut Simulation, &signals)? {
+            return Ok(Equivalence::Differ(input));
+        }
+    }
+    Ok(Equivalence::Equal)
+}
+
+fn check_bdd(a: &Target, b: &Target) -> Result<Equivalence, EquivError> {
+    let mut bdd = Bdd::new(a.input_size());
+    let inputs = (0..a.input_size()).map(|i| bdd.var(i)).collect::<Vec<usize>>();
+    let fa = a.evaluate(&mut bdd, &inputs)?;
+    let fb = b.evaluate(&mut bdd, &inputs)?;
+    for (x, y) in fa.into_iter().zip(fb) {
+        let diff = bdd.xor(x, y);
+        if let Some(assignment) = bdd.any_sat(diff) {
+            return Ok(Equivalence::Differ(assignment.iter().map(|b| *b as usize).collect()));
+        }
+    }
+    Ok(Equivalence::Equal)
+}
+
+//对每一对输出构造 miter 交给 SAT 求解
+fn check_sat(a: &Target, b: &Target) -> Result<Equivalence, EquivError> {
+    let mut cnf = Cnf::new();
+    let inputs = (0..a.input_size()).map(|_| cnf.new_var()).collect::<Vec<Lit>>();
+    let fa = a.evaluate(&mut cnf, &inputs)?;
+    let fb = b.evaluate(&mut cnf, &inputs)?;
+    for (x, y) in fa.into_iter().zip(fb) {
+        let diff = cnf.xor(x, y);
+        if let Some(model) = cnf.solve(&[diff]) {
+            return Ok(Equivalence::Differ(model[..inputs.len()].iter().map(|b| *b as usize).collect()));
+        }
+    }
+    Ok(Equivalence::Equal)
+}
+
+impl Circuit {
+    //比较 outputs 与 other 的 other_outputs 是否逐个相同，两个电路的输入一一对应
+    pub fn equivalent(
+        &self,
+        outputs: &[usize],
+        other: &Circuit,
+        other_outputs: &[usize],
+    ) -> Result<Equivalence, EquivError> {
+        self.equivalent_by(outputs, other, other_outputs, Method::Auto)
+    }
+
+    pub fn equivalent_by(
+        &self,
+        outputs: &[usize],
+        other: &Circuit,
+        other_outputs: &[usize],
+        method: Method,
+    ) -> Result<Equivalence, EquivError> {
+        check(Target::Circuit(self, outputs), Target::Circuit(other, other_outputs), method)
+    }
+}
+
+impl Pattern {
+    //只适用于组合逻辑，带反馈的 pattern 会得到 Undetermined
+    //嵌套的 pattern 在 patterns 中查找，一般为 circuit.get_patterns()
+    pub fn equivalent(&self, other: &Pattern, patterns: &HashMap<String, Pattern>) -> Result<Equivalence, EquivError> {
+        self.equivalent_by(other, patterns, Method::Auto)
+    }
+
+    pub fn equivalent_by(
+        &self,
+        other: &Pattern,
+        patterns: &HashMap<String, Pattern>,
+        method: Method,
+    ) -> Result<Equivalence, EquivError> {
+        check(Target::Pattern(self, patterns), Target::Pattern(other, patterns), method)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::node::NodeType;
+    use crate::pattern::{PatternNode, PniType};
+
+    fn nand_full_adder(circuit: &mut Circuit) {
+        let t = "(a ~& b)";
+        let u = format!("((a ~& {t}) ~& (b ~& {t}))");
+        let v = format!("({u} ~& c0)");
+        let src = format!("s = ({u} ~& {v}) ~& (c0 ~& {v}); c1 = {v} ~& {t}");
+        Pattern::build_expression(circuit, "nand_full_adder", &src).unwrap();
+    }
+
+    #[test]
+    fn test_full_adder() {
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        nand_full_adder(&mut circuit);
+        Pattern::build_expression(&mut circuit, "or_adder", "s = a ^ b ^ c0; c1 = a | b | c0").unwrap();
+
+        //build_full_adder 的输入顺序为 c0 a b
+        let reordered = Expression::parse("s = a ^ b ^ c0; c1 = (a & b) | (c0 & (a ^ b))")
+            .unwrap()
+            .set_input_name(&["c0", "a", "b"])
+            .to_pattern();
+        let full_adder = circuit.get_pattern("full_adder");
+        let nand = circuit.get_pattern("nand_full_adder");
+        let wrong = circuit.get_pattern("or_adder");
+        for method in [Method::Exhaustive, Method::Bdd, Method::Sat] {
+            let patterns = circuit.get_patterns();
+            assert_eq!(full_adder.equivalent_by(&reordered, patterns, method).unwrap(), Equivalence::Equal);
+            assert_eq!(nand.equivalent_by(wrong, patterns, method).unwrap(), Equivalence::Differ(vec![0, 0, 1]));
+        }
+
+        let input = circuit.get_input();
+        let output = circuit.apply_pattern("nand_full_adder", &input).get_output().to_vec();
+        let (other, other_output) = Expression::parse("s = a ^ b ^ c0; c1 = (a & b) | (c0 & (a ^ b))")
+            .unwrap()
+            .build_circuit();
+        assert_eq!(circuit.equivalent(&output, &other, &other_output).unwrap(), Equivalence::Equal);
+    }
+
+    #[test]
+    fn test_wide() {
+        let names = (0..24).map(|i| format!("x{}", i)).collect::<Vec<String>>();
+        let chain = Expression::parse(&format!("y = {}", names.join(" ^ "))).unwrap();
+        let tree = Expression::parse(&format!("y = ({}) ^ ({})",
+            names[..12].join(" ^ "), names[12..].join(" ^ "))).unwrap();
+        let (a, a_out) = chain.build_circuit();
+        let (b, b_out) = tree.build_circuit();
+        assert_eq!(a.equivalent(&a_out, &b, &b_out).unwrap(), Equivalence::Equal);
+
+        let broken = Expression::parse(&format!("y = ({}) | ({})",
+            names[..12].join(" ^ "), names[12..].join(" ^ "))).unwrap();
+        let (c, c_out) = broken.build_circuit();
+        let Equivalence::Differ(input) = a.equivalent(&a_out, &c, &c_out).unwrap() else { panic!() };
+        assert_ne!(a.execute_sequential(&input)[a_out[0]], c.execute_sequential(&input)[c_out[0]]);
+    }
+
+    #[test]
+    fn test_hierarchical() {
+        //三输入的异或门只看前两个输入
+        let xor = |n: usize| Pattern::new(3, vec![(0, 0)], vec![
+            PatternNode::new(NodeType::Xor, (0..n).map(PniType::Input).collect()),
+        ]);
+        let (xor3, xor2) = (xor(3), xor(2));
+        for method in [Method::Exhaustive, Method::Bdd, Method::Sat] {
+            assert_eq!(xor3.equivalent_by(&xor2, &HashMap::new(), method).unwrap(), Equivalence::Equal);
+        }
+
+        //由两个半加器拼成的全加器
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        Pattern::build_expression(&mut circuit, "half_adder", "s = a ^ b; c = a & b").unwrap();
+        circuit.build_begin();
+        let inputs = circuit.advance_output(3);
+        let h0 = circuit.apply_pattern("half_adder", &inputs[1..]).get_output().to_vec();
+        let h1 = circuit.apply_pattern("half_adder", &[inputs[0], h0[0]]).get_output().to_vec();
+        let c1 = circuit.add_gate("or", &[h0[1], h1[1]]);
+        circuit.build_end("composed", &inputs, &[h1[0], c1]);
+
+        let patterns = circuit.get_patterns();
+        let composed = circuit.get_pattern("composed");
+        let full_adder = circuit.get_pattern("full_adder");
+        for method in [Method::Exhaustive, Method::Bdd, Method::Sat] {
+            assert_eq!(composed.equivalent_by(full_adder, patterns, method).unwrap(), Equivalence::Equal);
+        }
+        assert!(matches!(composed.equivalent(full_adder, &HashMap::new()), Err(EquivError::Undetermined(0))));
+    }
+}
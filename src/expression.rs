@@ -0,0 +1,656 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::LazyLock;
+use pest::Parser;
+use pest::iterators::Pairs;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use pest_derive::Parser;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PatternNode, PniType};
+
+#[derive(Parser)]
+#[grammar = "expression.pest"]
+struct ExpressionParser;
+
+//优先级从低到高：| ^ (& ~&) !
+static PRATT: LazyLock<PrattParser<Rule>> = LazyLock::new(|| {
+    PrattParser::new()
+        .op(Op::infix(Rule::or, Assoc::Left))
+        .op(Op::infix(Rule::xor, Assoc::Left))
+        .op(Op::infix(Rule::and, Assoc::Left) | Op::infix(Rule::nand, Assoc::Left))
+        .op(Op::prefix(Rule::not))
+});
+
+#[derive(Debug, Error)]
+pub enum ExprError {
+    #[error(transparent)]
+    Syntax(#[from] Box<pest::error::Error<Rule>>),
+    #[error("`{0}` is assigned more than once or used before its assignment")]
+    Redefined(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Var(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+    NAnd(Box<Expr>, Box<Expr>),
+}
+
+//一组形如 `s = a ^ b; c = a & b` 的赋值语句
+#[derive(Debug, Clone)]
+pub struct Expression {
+    statements: Vec<(String, Expr)>,
+    input_name: Vec<String>,
+}
+
+impl Expression {
+    pub fn parse(src: &str) -> Result<Expression, ExprError> {
+        let program = ExpressionParser::parse(Rule::program, src)
+            .map_err(Box::new)?
+            .next()
+            .unwrap();
+        let statements = program.into_inner()
+            .filter(|pair| pair.as_rule() == Rule::statement)
+            .map(|pair| {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                (name, Self::parse_expr(inner.next().unwrap().into_inner()))
+            })
+            .collect::<Vec<(String, Expr)>>();
+
+        let mut expression = Self { statements, input_name: Vec::new() };
+        expression.input_name = expression.free_variables()?;
+        Ok(expression)
+    }
+
+    fn parse_expr(pairs: Pairs<Rule>) -> Expr {
+        PRATT
+            .map_primary(|primary| match primary.as_rule() {
+                Rule::ident => Expr::Var(primary.as_str().to_string()),
+                Rule::expr => Self::parse_expr(primary.into_inner()),
+                _ => unreachable!(),
+            })
+            .map_prefix(|_, rhs| Expr::Not(Box::new(rhs)))
+            .map_infix(|lhs, op, rhs| {
+                let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+                match op.as_rule() {
+                    Rule::and => Expr::And(lhs, rhs),
+                    Rule::or => Expr::Or(lhs, rhs),
+                    Rule::xor => Expr::Xor(lhs, rhs),
+                    Rule::nand => Expr::NAnd(lhs, rhs),
+                    _ => unreachable!(),
+                }
+            })
+            .parse(pairs)
+    }
+
+    //按首次出现的顺序收集未被赋值的变量，作为输入
+    fn free_variables(&self) -> Result<Vec<String>, ExprError> {
+        let mut inputs = Vec::new();
+        let mut defined = Vec::new();
+        for (name, expr) in &self.statements {
+            expr.collect_vars(&mut |var: &str| {
+                if !defined.iter().any(|d| d == var) && !inputs.iter().any(|i| i == var) {
+                    inputs.push(var.to_string());
+                }
+            });
+            if defined.contains(name) || inputs.contains(name) {
+                return Err(ExprError::Redefined(name.clone()));
+            }
+            defined.push(name.clone());
+        }
+        Ok(inputs)
+    }
+
+    pub fn get_statements(&self) -> &Vec<(String, Expr)> { &self.statements }
+    pub fn get_input_name(&self) -> &Vec<String> { &self.input_name }
+    pub fn get_output_name(&self) -> Vec<String> {
+        self.statements.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    //指定输入顺序，未列出的输入保持原有顺序排在后面
+    pub fn set_input_name(&mut self, input_name: &[&str]) -> &mut Expression {
+        let mut inputs = input_name.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        for name in &self.input_name {
+            if !inputs.contains(name) { inputs.push(name.clone()); }
+        }
+        self.input_name = inputs;
+        self
+    }
+
+    //生成门级网表，相同的子表达式只生成一次
+    pub fn lower(&self) -> (Vec<PatternNode>, Vec<(usize, usize)>) {
+        let mut lowering = Lowering {
+            nodes: Vec::new(),
+            shared: HashMap::new(),
+            names: self.input_name.iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), PniType::Input(i)))
+                .collect(),
+        };
+        let mut outputs = Vec::new();
+        for (name, expr) in &self.statements {
+            let output = match lowering.lower(expr) {
+                PniType::NodeOutput(output) => output,
+                //直接输出某个输入时，用单输入的与门作为缓冲
+                input => (lowering.add(NodeType::And, vec![input]), 0),
+            };
+            lowering.names.insert(name.clone(), PniType::NodeOutput(output));
+            outputs.push(output);
+        }
+        (lowering.nodes, outputs)
+    }
+
+    pub fn to_pattern(&self) -> Pattern {
+        let (nodes, outputs) = self.lower();
+        let mut pattern = Pattern::new(self.input_name.len(), outputs, nodes);
+        let input_name = self.input_name.iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>();
+        let output_name = self.get_output_name();
+        let output_name = output_name.iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>();
+        pattern.set_name(&input_name, &output_name);
+        pattern
+    }
+
+    //返回的电路以 get_input_name 的顺序作为输入，同时返回各输出信号的下标
+    pub fn build_circuit(&self) -> (Circuit, Vec<usize>) {
+        let (nodes, outputs) = self.lower();
+        let mut circuit = Circuit::new(self.input_name.len());
+        let mut node_signals = Vec::new();
+        for node in &nodes {
+            let inputs = node.get_input().iter()
+                .map(|i| match i {
+                    PniType::Input(i) => *i,
+                    PniType::NodeOutput((n, _)) => node_signals[*n],
+                })
+                .collect::<Vec<usize>>();
+            node_signals.push(circuit.add_gate_t(node.get_type().clone(), &inputs));
+        }
+        let outputs = outputs.iter().map(|(n, _)| node_signals[*n]).collect();
+        (circuit, outputs)
+    }
+}
+
+//描述表达式时采用的形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExprForm {
+    //按电路结构直接展开
+    Structural,
+    //标准最小项之和
+    Minterms,
+    //Quine-McCluskey 化简后的与或式
+    Simplified,
+}
+
+impl Expr {
+    pub fn var(name: &str) -> Expr { Expr::Var(name.to_string()) }
+
+    //由门的类型和各输入的表达式组合出门输出的表达式，多输入的门按从左到右展开
+    //与 Node::execute_gate 一致，异或门只取前两个输入
+    pub fn from_gate(node_type: &NodeType, inputs: Vec<Expr>) -> Option<Expr> {
+        let fold = |inputs: Vec<Expr>, f: fn(Box<Expr>, Box<Expr>) -> Expr| {
+            inputs.into_iter().reduce(|l, r| f(Box::new(l), Box::new(r)))
+        };
+        match node_type {
+            NodeType::Not => inputs.into_iter().next().map(|e| Expr::Not(Box::new(e))),
+            NodeType::And => fold(inputs, Expr::And),
+            NodeType::Or => fold(inputs, Expr::Or),
+            NodeType::Xor => fold(inputs.into_iter().take(2).collect(), Expr::Xor),
+            NodeType::NAnd if inputs.len() == 2 => fold(inputs, Expr::NAnd),
+            NodeType::NAnd => fold(inputs, Expr::And).map(|e| Expr::Not(Box::new(e))),
+            NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => None,
+        }
+    }
+
+    pub fn eval(&self, value: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::Var(name) => value(name),
+            Expr::Not(e) => !e.eval(value),
+            Expr::And(l, r) => l.eval(value) && r.eval(value),
+            Expr::Or(l, r) => l.eval(value) || r.eval(value),
+            Expr::Xor(l, r) => l.eval(value) != r.eval(value),
+            Expr::NAnd(l, r) => !(l.eval(value) && r.eval(value)),
+        }
+    }
+
+    //按首次出现的顺序返回表达式中的变量
+    pub fn get_vars(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        self.collect_vars(&mut |var: &str| {
+            if !vars.iter().any(|v| v == var) { vars.push(var.to_string()); }
+        });
+        vars
+    }
+
+    pub fn to_minterms(&self) -> Sop { Sop::minterms(self, &self.get_vars()) }
+
+    pub fn simplify(&self) -> Sop { self.to_minterms().simplify() }
+
+    pub fn to_string_as(&self, form: ExprForm) -> String {
+        match form {
+            ExprForm::Structural => self.to_string(),
+            ExprForm::Minterms => self.to_minterms().to_string(),
+            ExprForm::Simplified => self.simplify().to_string(),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::Xor(..) => 2,
+            Expr::And(..) | Expr::NAnd(..) => 3,
+            Expr::Not(_) => 4,
+            Expr::Var(_) => 5,
+        }
+    }
+
+    fn fmt_child(&self, f: &mut fmt::Formatter, child: &Expr, min: u8) -> fmt::Result {
+        if child.precedence() < min {
+            write!(f, "({})", child)
+        } else {
+            write!(f, "{}", child)
+        }
+    }
+
+    pub fn collect_vars(&self, f: &mut impl FnMut(&str)) {
+        match self {
+            Expr::Var(name) => f(name),
+            Expr::Not(e) => e.collect_vars(f),
+            Expr::And(l, r) | Expr::Or(l, r) | Expr::Xor(l, r) | Expr::NAnd(l, r) => {
+                l.collect_vars(f);
+                r.collect_vars(f);
+            }
+        }
+    }
+}
+
+//输出的写法与解析器一致，可以重新解析
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (l, op, r) = match self {
+            Expr::Var(name) => return write!(f, "{}", name),
+            Expr::Not(e) => {
+                write!(f, "!")?;
+                return self.fmt_child(f, e, self.precedence());
+            }
+            Expr::And(l, r) => (l, "&", r),
+            Expr::Or(l, r) => (l, "|", r),
+            Expr::Xor(l, r) => (l, "^", r),
+            Expr::NAnd(l, r) => (l, "~&", r),
+        };
+        self.fmt_child(f, l, self.precedence())?;
+        write!(f, " {} ", op)?;
+        self.fmt_child(f, r, self.precedence() + 1)
+    }
+}
+
+//与或式，每一项中 Some(true)/Some(false) 表示原变量/反变量，None 表示该变量不出现
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sop {
+    vars: Vec<String>,
+    terms: Vec<Vec<Option<bool>>>,
+}
+
+impl Sop {
+    //vars[0] 为最高位，与 Circuit::truth_table 的行顺序一致
+    pub fn minterms(expr: &Expr, vars: &[String]) -> Sop {
+        let n = vars.len();
+        let terms = (0..1usize << n)
+            .map(|row| (0..n).map(|i| row >> (n - i - 1) & 1 == 1).collect::<Vec<bool>>())
+            .filter(|values| expr.eval(&|var: &str| {
+                values[vars.iter().position(|v| v == var).unwrap()]
+            }))
+            .map(|values| values.into_iter().map(Some).collect())
+            .collect();
+        Self { vars: vars.to_vec(), terms }
+    }
+
+    pub fn get_vars(&self) -> &Vec<String> { &self.vars }
+    pub fn get_terms(&self) -> &Vec<Vec<Option<bool>>> { &self.terms }
+
+    //求出全部素蕴含项后，先选必要项，再贪心地选覆盖最多剩余最小项的项
+    pub fn simplify(&self) -> Sop {
+        let n = self.vars.len();
+        let minterms = self.terms.iter()
+            .flat_map(|term| Self::expand(term))
+            .collect::<HashSet<usize>>();
+        let mut minterms = minterms.into_iter().collect::<Vec<usize>>();
+        minterms.sort();
+
+        //(取值, 无关位掩码)
+        let mut cubes = minterms.iter().map(|m| (*m, 0usize)).collect::<Vec<(usize, usize)>>();
+        let mut primes = Vec::new();
+        while !cubes.is_empty() {
+            let mut merged = HashSet::new();
+            let mut next = Vec::new();
+            for (i, a) in cubes.iter().enumerate() {
+                for b in &cubes[i + 1..] {
+                    let diff = a.0 ^ b.0;
+                    if a.1 == b.1 && diff.count_ones() == 1 {
+                        merged.insert(*a);
+                        merged.insert(*b);
+                        let cube = (a.0 & !diff, a.1 | diff);
+                        if !next.contains(&cube) { next.push(cube); }
+                    }
+                }
+            }
+            primes.extend(cubes.iter().filter(|c| !merged.contains(c)));
+            cubes = next;
+        }
+
+        let covers = |cube: &(usize, usize), m: usize| m & !cube.1 == cube.0;
+        let mut chosen = Vec::new();
+        let mut uncovered = minterms.clone();
+        for m in &minterms {
+            let covering = primes.iter().filter(|p| covers(p, *m)).collect::<Vec<_>>();
+            if covering.len() == 1 && !chosen.contains(covering[0]) {
+                chosen.push(*covering[0]);
+            }
+        }
+        uncovered.retain(|m| !chosen.iter().any(|c| covers(c, *m)));
+        while !uncovered.is_empty() {
+            let best = *primes.iter()
+                .max_by_key(|p| uncovered.iter().filter(|m| covers(p, **m)).count())
+                .unwrap();
+            chosen.push(best);
+            uncovered.retain(|m| !covers(&best, *m));
+        }
+        chosen.sort();
+
+        let terms = chosen.iter()
+            .map(|(value, mask)| (0..n)
+                .map(|i| {
+                    let bit = 1 << (n - i - 1);
+                    (mask & bit == 0).then_some(value & bit != 0)
+                })
+                .collect())
+            .collect();
+        Self { vars: self.vars.clone(), terms }
+    }
+
+    //一项所覆盖的全部最小项
+    fn expand(term: &[Option<bool>]) -> Vec<usize> {
+        term.iter().fold(vec![0], |acc, literal| {
+            acc.into_iter()
+                .flat_map(|m| match literal {
+                    Some(b) => vec![m << 1 | *b as usize],
+                    None => vec![m << 1, m << 1 | 1],
+                })
+                .collect()
+        })
+    }
+}
+
+impl fmt::Display for Sop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() { return write!(f, "0"); }
+        let terms = self.terms.iter()
+            .map(|term| {
+                let literals = term.iter().zip(&self.vars)
+                    .filter_map(|(literal, var)| literal.map(|b| {
+                        if b { var.clone() } else { format!("!{}", var) }
+                    }))
+                    .collect::<Vec<String>>();
+                if literals.is_empty() { "1".to_string() } else { literals.join(" & ") }
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", terms.join(" | "))
+    }
+}
+
+struct Lowering {
+    nodes: Vec<PatternNode>,
+    shared: HashMap<(NodeType, Vec<PniType>), usize>,
+    names: HashMap<String, PniType>,
+}
+
+impl Lowering {
+    fn lower(&mut self, expr: &Expr) -> PniType {
+        let (node_type, inputs) = match expr {
+            Expr::Var(name) => return self.names[name],
+            Expr::Not(e) => (NodeType::Not, vec![self.lower(e)]),
+            Expr::And(l, r) => (NodeType::And, vec![self.lower(l), self.lower(r)]),
+            Expr::Or(l, r) => (NodeType::Or, vec![self.lower(l), self.lower(r)]),
+            Expr::Xor(l, r) => (NodeType::Xor, vec![self.lower(l), self.lower(r)]),
+            Expr::NAnd(l, r) => (NodeType::NAnd, vec![self.lower(l), self.lower(r)]),
+        };
+        PniType::NodeOutput((self.add(node_type, inputs), 0))
+    }
+
+    fn add(&mut self, node_type: NodeType, inputs: Vec<PniType>) -> usize {
+        //这几种门都满足交换律，排序后 a & b 与 b & a 可以共用
+        let mut key = (node_type, inputs.clone());
+        key.1.sort();
+        if let Some(node) = self.shared.get(&key) {
+            return *node;
+        }
+        self.nodes.push(PatternNode::new(key.0.clone(), inputs));
+        self.shared.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+}
+
+impl Pattern {
+    pub fn build_expression<'a>(
+        circuit: &'a mut Circuit,
+        pattern_name: &str,
+        src: &str,
+    ) -> Result<&'a mut Pattern, ExprError> {
+        let pattern = Expression::parse(src)?.to_pattern();
+        circuit.get_patterns_mut().insert(pattern_name.to_string(), pattern);
+        Ok(circuit.get_patterns_mut().get_mut(pattern_name).unwrap())
+    }
+}
+
+impl Pattern {
+    //输出 output 关于端口名的表达式，反馈回路在回到已访问的 node 处截断，用输出名表示状态
+    //patterns 用来展开嵌套的 pattern，一般为 circuit.get_patterns()
+    pub fn output_expr(&self, output: usize, patterns: &HashMap<String, Pattern>) -> Expr {
+        let inputs = (0..self.input_size())
+            .map(|i| Expr::Var(self.input_port(i)))
+            .collect::<Vec<Expr>>();
+        self.output_expr_with(output, &inputs, patterns)
+    }
+
+    //以给定的表达式代替各输入，展开输出 output
+    pub fn output_expr_with(&self, output: usize, inputs: &[Expr], patterns: &HashMap<String, Pattern>) -> Expr {
+        self.node_expr(self.get_output()[output], inputs, patterns, &mut Vec::new())
+    }
+
+    //node 的第 index 个输出，找不到定义的嵌套 pattern 记为 n<node>_<index>
+    fn node_expr(
+        &self,
+        (node, index): (usize, usize),
+        inputs: &[Expr],
+        patterns: &HashMap<String, Pattern>,
+        visiting: &mut Vec<usize>,
+    ) -> Expr {
+        let state = Expr::Var(format!("n{}_{}", node, index));
+        if visiting.contains(&node) {
+            return match self.get_output().iter().position(|o| *o == (node, index)) {
+                Some(output) => Expr::Var(self.output_port(output)),
+                None => state,
+            };
+        }
+        visiting.push(node);
+        let pattern_node = &self.get_pattern()[node];
+        let operands = pattern_node.get_input().iter()
+            .map(|i| match i {
+                PniType::Input(i) => inputs[*i].clone(),
+                PniType::NodeOutput(output) => self.node_expr(*output, inputs, patterns, visiting),
+            })
+            .collect::<Vec<Expr>>();
+        visiting.pop();
+        match pattern_node.get_type() {
+            NodeType::Pattern(name) => match patterns.get(name) {
+                Some(pattern) => pattern.output_expr_with(index, &operands, patterns),
+                None => state,
+            },
+            gate => Expr::from_gate(gate, operands).unwrap_or(state),
+        }
+    }
+
+    //根据电路结构自动生成描述，形如 `s = ...; c1 = ...`
+    pub fn describe(&mut self, form: ExprForm, patterns: &HashMap<String, Pattern>) -> &mut Pattern {
+        let vars = (0..self.input_size())
+            .map(|i| self.input_port(i))
+            .collect::<Vec<String>>();
+        let description = (0..self.output_size())
+            .map(|o| {
+                let expr = self.output_expr(o, patterns);
+                //状态变量排在输入之后
+                let mut all_vars = vars.clone();
+                all_vars.extend(expr.get_vars().into_iter().filter(|v| !vars.contains(v)));
+                let text = match form {
+                    ExprForm::Structural => expr.to_string(),
+                    ExprForm::Minterms => Sop::minterms(&expr, &all_vars).to_string(),
+                    ExprForm::Simplified => Sop::minterms(&expr, &all_vars).simplify().to_string(),
+                };
+                format!("{} = {}", self.output_port(o), text)
+            })
+            .collect::<Vec<String>>()
+            .join("; ");
+        self.set_description(&description)
+    }
+}
+
+impl Circuit {
+    //沿着 get_parent_node 回溯到输入，得到信号 signal 的表达式
+    //输入依次以 input_name 命名，不足时记为 i0, i1...；没有来源或构成回路的信号记为 s<下标>
+    pub fn signal_expr(&self, signal: usize, input_name: &[&str]) -> Expr {
+        self.signal_expr_inner(signal, input_name, &mut Vec::new())
+    }
+
+    fn signal_expr_inner(&self, signal: usize, input_name: &[&str], visiting: &mut Vec<usize>) -> Expr {
+        if signal < self.get_input().len() {
+            return match input_name.get(signal) {
+                Some(name) => Expr::var(name),
+                None => Expr::Var(format!("i{}", signal)),
+            };
+        }
+        let state = Expr::Var(format!("s{}", signal));
+        let Some(node_index) = self.get_parent_node_index(signal) else { return state };
+        if visiting.contains(&node_index) { return state; }
+
+        visiting.push(node_index);
+        let node = self.get_node(node_index);
+        let inputs = node.get_input().iter()
+            .map(|i| self.signal_expr_inner(*i, input_name, visiting))
+            .collect::<Vec<Expr>>();
+        visiting.pop();
+
+        match node.get_type() {
+            NodeType::Pattern(name) => {
+                let output = node.get_output().iter().position(|o| *o == signal).unwrap();
+                self.get_pattern(name).output_expr_with(output, &inputs, self.get_patterns())
+            }
+            gate => Expr::from_gate(gate, inputs).unwrap_or(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_ADDER: &str = "s = a ^ b ^ c0; c1 = (a & b) | (c0 & (a ^ b))";
+
+    #[test]
+    fn test_parse() {
+        let expression = Expression::parse("y = !a | b ~& c ^ d").unwrap();
+        let var = |s: &str| Box::new(Expr::Var(s.to_string()));
+        assert_eq!(expression.get_statements()[0].1, Expr::Or(
+            Box::new(Expr::Not(var("a"))),
+            Box::new(Expr::Xor(Box::new(Expr::NAnd(var("b"), var("c"))), var("d"))),
+        ));
+        assert_eq!(expression.get_input_name(), &["a", "b", "c", "d"]);
+        assert!(Expression::parse("y = a &").is_err());
+        assert!(Expression::parse("y = a; y = b").is_err());
+    }
+
+    #[test]
+    fn test_full_adder() {
+        let expression = Expression::parse(FULL_ADDER).unwrap();
+        let (nodes, _) = expression.lower();
+        assert_eq!(nodes.len(), 5);
+
+        let (circuit, outputs) = expression.build_circuit();
+        let table = Circuit::truth_table(&circuit, 3, &outputs);
+        for (input, output) in table {
+            let sum = input.iter().filter(|s| s.get_unchecked()).count();
+            assert_eq!(output[0].get_unchecked(), sum % 2 == 1);
+            assert_eq!(output[1].get_unchecked(), sum >= 2);
+        }
+
+        let mut circuit = Circuit::new(3);
+        let pattern = Pattern::build_expression(&mut circuit, "fa", FULL_ADDER).unwrap();
+        assert_eq!(pattern.get_input_name(), &["a", "b", "c0"]);
+        assert_eq!(pattern.get_output_name(), &["s", "c1"]);
+        let input = circuit.get_input();
+        let output = circuit.apply_pattern("fa", &input).get_output().to_vec();
+        let res = circuit.execute_sequential(&[1, 0, 1]);
+        assert!(!res[output[0]].get_unchecked());
+        assert!(res[output[1]].get_unchecked());
+    }
+
+    #[test]
+    fn test_derive() {
+        let expression = Expression::parse(FULL_ADDER).unwrap();
+        let (circuit, outputs) = expression.build_circuit();
+        let s = circuit.signal_expr(outputs[0], &["a", "b", "c0"]);
+        let c1 = circuit.signal_expr(outputs[1], &["a", "b", "c0"]);
+        assert_eq!(s.to_string(), "a ^ b ^ c0");
+        assert_eq!(c1.to_string(), "a & b | c0 & (a ^ b)");
+        assert_eq!(Expression::parse(&format!("y = {}", c1)).unwrap().get_statements()[0].1, c1);
+
+        assert_eq!(c1.to_minterms().to_string(),
+                   "!a & b & c0 | a & !b & c0 | a & b & !c0 | a & b & c0");
+        assert_eq!(c1.simplify().to_string(), "b & c0 | a & c0 | a & b");
+        assert_eq!(s.simplify().get_terms().len(), 4);
+
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        let patterns = circuit.get_patterns().clone();
+        let pattern = circuit.get_patterns_mut().get_mut("full_adder").unwrap();
+        pattern.describe(ExprForm::Structural, &patterns);
+        assert_eq!(pattern.get_description(), "s = c0 ^ (a ^ b); c1 = c0 & (a ^ b) | a & b");
+
+        let input = circuit.get_input();
+        let output = circuit.apply_pattern("full_adder", &input).get_output().to_vec();
+        circuit.execute_sequential_mut(&[1, 1, 0]);
+        let c1 = circuit.signal_expr(output[1], &["x", "y", "z"]);
+        assert_eq!(c1.to_string(), "x & (y ^ z) | y & z");
+    }
+
+    #[test]
+    fn test_hierarchical() {
+        let (a, b, c) = (Expr::var("a"), Expr::var("b"), Expr::var("c"));
+        assert_eq!(Expr::from_gate(&NodeType::Xor, vec![a, b, c]).unwrap().to_string(), "a ^ b");
+
+        //只取全加器的进位输出，再取反
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        circuit.build_begin();
+        let inputs = circuit.advance_output(3);
+        let fa = circuit.apply_pattern("full_adder", &inputs).get_output().to_vec();
+        let nc = circuit.add_gate("not", &[fa[1]]);
+        circuit.build_end("borrow", &inputs, &[nc]).set_name(&["x", "y", "z"], &["nc"]);
+
+        let patterns = circuit.get_patterns().clone();
+        let pattern = circuit.get_patterns_mut().get_mut("borrow").unwrap();
+        assert_eq!(pattern.output_expr(0, &patterns).to_string(), "!(x & (y ^ z) | y & z)");
+        assert_eq!(pattern.output_expr(0, &HashMap::new()).to_string(), "!n0_1");
+        pattern.describe(ExprForm::Simplified, &patterns);
+        assert_eq!(pattern.get_description(), "nc = !x & !y | !x & !z | !y & !z");
+    }
+}
@@ -0,0 +1,174 @@
+use crate::circuit::Circuit;
+use crate::pattern::Pattern;
+use crate::sequential::edge_bits;
+
+//在 circuit 中临时搭出电路再转换成 pattern，pattern 内部不能嵌套 pattern，所以只用门
+pub(crate) fn build(
+    circuit: &mut Circuit,
+    name: &str,
+    input_name: &[String],
+    body: impl FnOnce(&mut Circuit, &[usize]) -> Vec<(String, usize)>,
+) -> String {
+    circuit.build_begin();
+    let inputs = circuit.advance_output(input_name.len());
+    let outputs = body(circuit, &inputs);
+    //pattern 的输出必须来自节点，直接输出的输入经过单输入与门
+    let output_signals = outputs.iter()
+        .map(|(_, s)| if inputs.contains(s) { circuit.add_gate("and", &[*s]) } else { *s })
+        .collect::<Vec<usize>>();
+    let input_name = input_name.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+    let output_name = outputs.iter().map(|(n, _)| n.as_str()).collect::<Vec<&str>>();
+    circuit.build_end(name, &inputs, &output_signals).set_name(&input_name, &output_name);
+    name.to_string()
+}
+
+//选择信号的位数
+pub(crate) fn select_width(n: usize) -> usize {
+    (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
+
+impl Pattern {
+    //总线端口名 name[0] .. name[width - 1]
+    pub fn port_names(name: &str, width: usize) -> Vec<String> {
+        (0..width).map(|i| format!("{}[{}]", name, i)).collect()
+    }
+
+    //n 位行波进位加法器 adder{n}：输入 ci, a[], b[]，输出 s[], co
+    pub fn build_adder(circuit: &mut Circuit, n: usize) -> String {
+        let input_name = [vec!["ci".to_string()], Self::port_names("a", n), Self::port_names("b", n)].concat();
+        let name = build(circuit, &format!("adder{}", n), &input_name, |circuit, inputs| {
+            let (a, b) = (&inputs[1..n + 1], &inputs[n + 1..]);
+            let mut carry = inputs[0];
+            let mut outputs = Vec::new();
+            for i in 0..n {
+                let p = circuit.add_gate("xor", &[a[i], b[i]]);
+                let s = circuit.add_gate("xor", &[p, carry]);
+                let g = circuit.add_gate("and", &[a[i], b[i]]);
+                let t = circuit.add_gate("and", &[p, carry]);
+                carry = circuit.add_gate("or", &[g, t]);
+                outputs.push((format!("s[{}]", i), s));
+            }
+            outputs.push(("co".to_string(), carry));
+            outputs
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description(&format!("ci + a + b = co s, {} bits", n));
+        name
+    }
+
+    //n 位寄存器 register{n}：每一位是主从结构的上升沿触发器，cp 上升沿时 q[] 装入 d[]
+    pub fn build_register(circuit: &mut Circuit, n: usize) -> String {
+        let input_name = [vec!["cp".to_string()], Self::port_names("d", n)].concat();
+        let name = build(circuit, &format!("register{}", n), &input_name, |circuit, inputs| {
+            let d = inputs[1..].to_vec();
+            let q = edge_bits(circuit, inputs[0], None, n, |_, _| d);
+            q.into_iter().enumerate().map(|(i, q)| (format!("q[{}]", i), q)).collect()
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("Qn+1 = D on rising edge of CP");
+        name
+    }
+
+    //n 位无符号比较器 comparator{n}：输入 a[], b[]，输出 lt, eq, gt
+    pub fn build_comparator(circuit: &mut Circuit, n: usize) -> String {
+        let input_name = [Self::port_names("a", n), Self::port_names("b", n)].concat();
+        let name = build(circuit, &format!("comparator{}", n), &input_name, |circuit, inputs| {
+            let (a, b) = (&inputs[..n], &inputs[n..]);
+            //从最高位开始，前面各位都相等时由这一位决定大小
+            let mut equal = Vec::new();
+            let (mut lt, mut gt) = (Vec::new(), Vec::new());
+            for i in (0..n).rev() {
+                let na = circuit.add_gate("not", &[a[i]]);
+                let nb = circuit.add_gate("not", &[b[i]]);
+                lt.push(circuit.add_gate("and", &[&equal[..], &[na, b[i]]].concat()));
+                gt.push(circuit.add_gate("and", &[&equal[..], &[a[i], nb]].concat()));
+                let x = circuit.add_gate("xor", &[a[i], b[i]]);
+                equal.push(circuit.add_gate("not", &[x]));
+            }
+            let lt = circuit.add_gate("or", &lt);
+            let gt = circuit.add_gate("or", &gt);
+            let eq = circuit.add_gate("and", &equal);
+            vec![("lt".to_string(), lt), ("eq".to_string(), eq), ("gt".to_string(), gt)]
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("compare unsigned a and b");
+        name
+    }
+
+    //n 选 1 数据选择器 mux{n}：输入 s[], d[]，输出 y = d[s]，s 超出范围时为 0
+    pub fn build_mux(circuit: &mut Circuit, n: usize) -> String {
+        let k = select_width(n);
+        let input_name = [Self::port_names("s", k), Self::port_names("d", n)].concat();
+        let name = build(circuit, &format!("mux{}", n), &input_name, |circuit, inputs| {
+            let (s, d) = (&inputs[..k], &inputs[k..]);
+            let ns = s.iter().map(|x| circuit.add_gate("not", &[*x])).collect::<Vec<usize>>();
+            let terms = (0..n)
+                .map(|i| {
+                    let select = (0..k).map(|j| if i >> j & 1 == 1 { s[j] } else { ns[j] });
+                    circuit.add_gate("and", &[d[i]].into_iter().chain(select).collect::<Vec<usize>>())
+                })
+                .collect::<Vec<usize>>();
+            vec![("y".to_string(), circuit.add_gate("or", &terms))]
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("y = d[s]");
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bits, instance, read};
+
+    #[test]
+    fn test_generators() {
+        let mut circuit = Circuit::new(9);
+        assert_eq!(Pattern::build_adder(&mut circuit, 4), "adder4");
+        let adder = circuit.get_pattern("adder4");
+        assert_eq!(adder.get_input_name()[..3], ["ci", "a[0]", "a[1]"]);
+        assert_eq!(adder.get_output_name()[3..], ["s[3]", "co"]);
+        assert_eq!(circuit.signals_size(), 9);
+        let outputs = instance(&mut circuit, "adder4");
+        for v in 0..512 {
+            circuit.execute_sequential_mut(&bits(v, 9));
+            let (ci, a, b) = (v & 1, v >> 1 & 15, v >> 5);
+            assert_eq!(read(&circuit, &outputs), ci + a + b);
+        }
+
+        let mut circuit = Circuit::new(6);
+        Pattern::build_comparator(&mut circuit, 3);
+        let outputs = instance(&mut circuit, "comparator3");
+        for v in 0..64 {
+            circuit.execute_sequential_mut(&bits(v, 6));
+            let (a, b) = (v & 7, v >> 3);
+            let expected = [a < b, a == b, a > b].map(|x| x as usize);
+            assert_eq!(read(&circuit, &outputs), expected[0] | expected[1] << 1 | expected[2] << 2);
+        }
+
+        for n in [1, 3, 4] {
+            let k = select_width(n);
+            let mut circuit = Circuit::new(k + n);
+            Pattern::build_mux(&mut circuit, n);
+            let outputs = instance(&mut circuit, &format!("mux{}", n));
+            for v in 0..1 << (k + n) {
+                circuit.execute_sequential_mut(&bits(v, k + n));
+                let (s, d) = (v & ((1 << k) - 1), v >> k);
+                assert_eq!(read(&circuit, &outputs), if s < n { d >> s & 1 } else { 0 });
+            }
+        }
+
+        let mut circuit = Circuit::new(4);
+        Pattern::build_register(&mut circuit, 3);
+        assert!(circuit.get_pattern("register3").is_sequential());
+        let outputs = instance(&mut circuit, "register3");
+        assert_eq!(circuit.get_pattern("register3").output_size(), 3);
+        circuit.execute_sequential_mut(&[0, 1, 0, 1]);
+        assert_eq!(read(&circuit, &outputs), 0);
+        circuit.execute_sequential_mut(&[1, 1, 0, 1]);
+        assert_eq!(read(&circuit, &outputs), 0b101);
+        //cp 保持高电平时 d[] 变化不影响 q[]
+        circuit.execute_sequential_mut(&[1, 0, 1, 0]);
+        assert_eq!(read(&circuit, &outputs), 0b101);
+        circuit.execute_sequential_mut(&[0, 0, 1, 0]);
+        assert_eq!(read(&circuit, &outputs), 0b101);
+        circuit.execute_sequential_mut(&[1, 0, 1, 0]);
+        assert_eq!(read(&circuit, &outputs), 0b010);
+    }
+}
@@ -5,13 +5,37 @@
 #![allow(unused)]
 pub mod signal;
 pub mod circuit;
+pub mod node;
+pub mod pattern;
+pub mod bus;
+pub mod register;
+pub mod memory;
+pub mod table;
+pub mod expression;
+pub mod logic;
+pub mod bdd;
+pub mod sat;
+pub mod verilog;
+pub mod vhdl;
+pub mod netlist;
+pub mod blif;
+pub mod aig;
+pub mod dot;
+pub mod schematic;
+pub mod editor;
+pub mod waveform;
+pub mod propagation;
+pub mod equivalence;
 
 mod msic;
-mod node;
-mod pattern;
-mod table;
 mod sequential;
 mod combinational;
+mod generator;
+mod arithmetic;
+mod alu;
+mod counter;
+#[cfg(test)]
+mod testing;
 
 
 
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use crate::circuit::Circuit;
+use crate::node::{Node, NodeType};
+use crate::pattern::{Pattern, PniType};
+use crate::signal::Signal;
+
+//各值可能未定，例如依赖未初始化的信号
+pub type Values<V> = Vec<Option<V>>;
+
+//门的取值域，可以是信号本身，也可以是 BDD、SAT 变量等符号表示
+pub trait Logic {
+    type Value: Clone;
+
+    fn constant(&mut self, value: bool) -> Self::Value;
+
+    //只会以基本门（非 Pattern）调用
+    fn gate(&mut self, gate: &NodeType, inputs: &[Self::Value]) -> Self::Value;
+}
+
+//直接用 Signal 求值，与 execute_sequential 的结果一致
+pub struct Simulation;
+
+impl Logic for Simulation {
+    type Value = Signal;
+
+    fn constant(&mut self, value: bool) -> Signal { Signal::from_bool(Some(value)) }
+
+    fn gate(&mut self, gate: &NodeType, inputs: &[Signal]) -> Signal {
+        Node::execute_gate(gate, inputs)
+    }
+}
+
+impl Circuit {
+    //按 execute_sequential 的顺序求值，返回每个信号的值
+    //还没有被驱动的非输入信号取其当前值，当前值无效时为 None
+    pub fn evaluate<L: Logic>(&self, logic: &mut L, inputs: &[L::Value]) -> Vec<Option<L::Value>> {
+        let values = self.get_signals().iter()
+            .enumerate()
+            .map(|(i, s)| match inputs.get(i) {
+                Some(v) => Some(v.clone()),
+                None => s.is_valid().then(|| logic.constant(s.get_unchecked())),
+            })
+            .collect::<Vec<Option<L::Value>>>();
+        self.evaluate_from(logic, values)
+    }
+
+    //从给定的各信号初值开始求值，例如把保存状态的信号替换成符号变量
+    pub fn evaluate_from<L: Logic>(&self, logic: &mut L, mut values: Vec<Option<L::Value>>) -> Vec<Option<L::Value>> {
+        for node in self.get_pipeline() {
+            let node_inputs = node.get_input().iter()
+                .map(|i| values[*i].clone())
+                .collect::<Option<Vec<L::Value>>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let read = |signals: &[usize]| signals.iter()
+                        .map(|o| values[*o].clone())
+                        .collect::<Vec<Option<L::Value>>>();
+                    let (state, hidden) = (read(node.get_output()), read(node.get_state()));
+                    let (outputs, hidden) = match node_inputs {
+                        Some(node_inputs) => self.get_pattern(name)
+                            .evaluate_with_state(logic, self.get_patterns(), &node_inputs, &state, &hidden),
+                        None => (vec![None; state.len()], vec![None; hidden.len()]),
+                    };
+                    for (o, v) in node.get_output().iter().chain(node.get_state()).zip(outputs.into_iter().chain(hidden)) {
+                        values[*o] = v;
+                    }
+                }
+                //rom 的读出是组合的，每一位展开成以地址为输入的积之和
+                NodeType::Rom(..) => {
+                    let outputs = match node_inputs {
+                        Some(address) => self.rom_outputs(logic, node, &address).into_iter().map(Some).collect(),
+                        None => vec![None; node.get_output().len()],
+                    };
+                    for (o, v) in node.get_output().iter().zip(outputs) {
+                        values[*o] = v;
+                    }
+                }
+                //寄存器和 ram 的输出由保存的状态决定，取信号的当前值
+                NodeType::Register(_) | NodeType::Ram(..) => {}
+                gate => {
+                    values[node.get_output()[0]] = node_inputs.map(|i| logic.gate(gate, &i));
+                }
+            }
+        }
+        values
+    }
+
+    fn rom_outputs<L: Logic>(&self, logic: &mut L, node: &Node, address: &[L::Value]) -> Vec<L::Value> {
+        let words = self.get_states().get(&node.get_output()[0]).map(|s| s.get_data().clone()).unwrap_or_default();
+        let mut product = |logic: &mut L, a: usize| {
+            let bits = address.iter().enumerate()
+                .map(|(j, x)| if a >> j & 1 == 1 { x.clone() } else { logic.gate(&NodeType::Not, std::slice::from_ref(x)) })
+                .collect::<Vec<L::Value>>();
+            if bits.is_empty() { logic.constant(true) } else { logic.gate(&NodeType::And, &bits) }
+        };
+        (0..node.get_output().len())
+            .map(|k| {
+                let terms = words.iter().enumerate()
+                    .filter(|(_, w)| *w >> k & 1 == 1)
+                    .map(|(a, _)| product(logic, a))
+                    .collect::<Vec<L::Value>>();
+                if terms.is_empty() { logic.constant(false) } else { logic.gate(&NodeType::Or, &terms) }
+            })
+            .collect()
+    }
+}
+
+impl Pattern {
+    //state 为各输出的当前值，供带反馈的触发器读取，与 execute_pattern_mut 的预读一致
+    //嵌套的 pattern 在 patterns 中查找并递归求值，找不到时其输出为 None
+    pub fn evaluate<L: Logic>(
+        &self,
+        logic: &mut L,
+        patterns: &HashMap<String, Pattern>,
+        inputs: &[L::Value],
+        state: &[Option<L::Value>],
+    ) -> Vec<Option<L::Value>> {
+        self.evaluate_with_state(logic, patterns, inputs, state, &[]).0
+    }
+
+    //hidden 为 get_state 中各内部状态的当前值，同时返回各输出和内部状态的新值
+    pub fn evaluate_with_state<L: Logic>(
+        &self,
+        logic: &mut L,
+        patterns: &HashMap<String, Pattern>,
+        inputs: &[L::Value],
+        state: &[Option<L::Value>],
+        hidden: &[Option<L::Value>],
+    ) -> (Values<L::Value>, Values<L::Value>) {
+        //每个 node 的各个输出
+        let mut values = self.get_pattern().iter()
+            .map(|node| match node.get_type() {
+                NodeType::Pattern(name) => vec![None; patterns.get(name).map_or(1, |p| p.output_size())],
+                _ => vec![None],
+            })
+            .collect::<Vec<Vec<Option<L::Value>>>>();
+        for (i, (node, index)) in self.get_output().iter().enumerate() {
+            if let Some(value) = values[*node].get_mut(*index) {
+                *value = state.get(i).cloned().flatten();
+            }
+        }
+        let state_nodes = self.get_state();
+        for (i, (node, index)) in state_nodes.iter().enumerate() {
+            if let Some(value) = values[*node].get_mut(*index) {
+                *value = hidden.get(i).cloned().flatten();
+            }
+        }
+
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            let node_inputs = node.get_input().iter()
+                .map(|input| match input {
+                    PniType::Input(i) => Some(inputs[*i].clone()),
+                    PniType::NodeOutput((n, k)) => values[*n].get(*k).cloned().flatten(),
+                })
+                .collect::<Option<Vec<L::Value>>>();
+            values[i] = match (node.get_type(), node_inputs) {
+                (NodeType::Pattern(name), Some(node_inputs)) => match patterns.get(name) {
+                    Some(pattern) => pattern.evaluate(logic, patterns, &node_inputs, &values[i]),
+                    None => vec![None; values[i].len()],
+                },
+                (NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..), _) | (_, None) => vec![None; values[i].len()],
+                (gate, Some(inputs)) => vec![Some(logic.gate(gate, &inputs))],
+            };
+        }
+
+        let read = |locations: &[(usize, usize)]| locations.iter()
+            .map(|(node, index)| values[*node].get(*index).cloned().flatten())
+            .collect();
+        (read(self.get_output()), read(&state_nodes))
+    }
+}
@@ -1,5 +1,6 @@
 use digicir::circuit::Circuit;
 
+#[allow(unused)]
 fn try_create_ff() {
    let mut circuit = Circuit::new(2);
    let inputs = circuit.get_input();
@@ -13,7 +14,6 @@ fn try_create_ff() {
    circuit.execute_sequential_mut(&[0, 1]);
    
 }
-fn main() {
-   
-   println!("hello world");
+fn main() -> eframe::Result {
+   digicir::editor::run()
 }
\ No newline at end of file
@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::{Node, NodeType};
+use crate::register::{is_high, read_bits, write_bits, State};
+use crate::signal::Signal;
+
+//存储器的内容整个放在内存里，地址位宽不能太大
+const MAX_ADDRESS_WIDTH: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    #[error("address width {0} is wider than {MAX_ADDRESS_WIDTH} bits")]
+    AddressTooWide(usize),
+    #[error("data width must be at least 1 bit")]
+    NoData,
+    #[error("data width {0} is wider than 64 bits")]
+    DataTooWide(usize),
+    #[error("{0} words do not fit in {1} address bits")]
+    TooManyWords(usize, usize),
+    #[error("word {0} value {1:#x} does not fit in {2} bits")]
+    WordTooWide(usize, u64, usize),
+    #[error("line {0}: invalid word `{1}`")]
+    InvalidWord(usize, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+//与 $readmemh 相同：以空白分隔的十六进制数，`//` 之后为注释
+pub fn parse_words(text: &str) -> Result<Vec<u64>, MemoryError> {
+    let mut words = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap();
+        for word in line.split_whitespace() {
+            let value = u64::from_str_radix(&word.replace('_', ""), 16)
+                .map_err(|_| MemoryError::InvalidWord(i + 1, word.to_string()))?;
+            words.push(value);
+        }
+    }
+    Ok(words)
+}
+
+fn check_size(address_width: usize, data_width: usize) -> Result<(), MemoryError> {
+    if address_width > MAX_ADDRESS_WIDTH {
+        return Err(MemoryError::AddressTooWide(address_width));
+    }
+    if data_width == 0 {
+        return Err(MemoryError::NoData);
+    }
+    if data_width > 64 {
+        return Err(MemoryError::DataTooWide(data_width));
+    }
+    Ok(())
+}
+
+impl Node {
+    //存储器的地址输入
+    fn address(&self) -> &[usize] {
+        match self.get_type() {
+            NodeType::Rom(width, _) => &self.get_input()[..*width],
+            NodeType::Ram(width, _) => &self.get_input()[2..2 + width],
+            _ => unreachable!(),
+        }
+    }
+
+    //读出是组合的：输出当前地址中保存的数据
+    pub(crate) fn execute_memory_mut(&self, circuit: &Circuit, signals: &mut [Signal]) {
+        let address = read_bits(signals, self.address());
+        let word = circuit.memory_word(self.get_output()[0], address as usize);
+        write_bits(signals, self.get_output(), word);
+    }
+
+    //写使能有效时的 (地址, 数据)，无效的写使能视为 0
+    pub(crate) fn ram_write(&self, signals: &[Signal]) -> Option<(usize, u64)> {
+        let inputs = self.get_input();
+        let address = self.address();
+        let data = &inputs[2 + address.len()..];
+        is_high(signals[inputs[1]])
+            .then(|| (read_bits(signals, address) as usize, read_bits(signals, data)))
+    }
+}
+
+impl Circuit {
+    //只读存储器 rom：输入 a[]，输出 q[]，contents 之外的地址读出 0
+    pub fn add_rom(&mut self, address: &[usize], data_width: usize, contents: Vec<u64>) -> Result<Vec<usize>, MemoryError> {
+        check_size(address.len(), data_width)?;
+        if contents.len() > 1 << address.len() {
+            return Err(MemoryError::TooManyWords(contents.len(), address.len()));
+        }
+        if let Some((i, word)) = contents.iter().enumerate().find(|(_, w)| data_width < 64 && **w >> data_width != 0) {
+            return Err(MemoryError::WordTooWide(i, *word, data_width));
+        }
+        let q = self.advance_output_with_value(&vec![0; data_width]);
+        self.add_node(NodeType::Rom(address.len(), data_width), address.to_vec(), q.clone());
+        self.get_states_mut().insert(q[0], State::new(contents));
+        Ok(q)
+    }
+
+    //从 parse_words 格式的文件读入 rom 的内容
+    pub fn load_rom<P: AsRef<Path>>(&mut self, path: P, address: &[usize], data_width: usize) -> Result<Vec<usize>, MemoryError> {
+        self.add_rom(address, data_width, parse_words(&fs::read_to_string(path)?)?)
+    }
+
+    //同步写的存储器 ram：输入 clock, we, a[], d[]，输出 q[]，初始内容为 0。
+    //clock 上升沿时 we 为 1 则把 d 写入地址 a，q 随时读出地址 a 中的数据
+    pub fn add_ram(&mut self, clock: usize, write_enable: usize, address: &[usize], data_in: &[usize]) -> Result<Vec<usize>, MemoryError> {
+        check_size(address.len(), data_in.len())?;
+        let q = self.advance_output_with_value(&vec![0; data_in.len()]);
+        let inputs = [&[clock, write_enable], address, data_in].concat();
+        self.add_node(NodeType::Ram(address.len(), data_in.len()), inputs, q.clone());
+        self.get_states_mut().insert(q[0], State::new(vec![0; 1 << address.len()]));
+        Ok(q)
+    }
+
+    //以存储器第一个输出信号表示的存储器中 address 处的数据
+    pub fn memory_word(&self, q: usize, address: usize) -> u64 {
+        self.get_states().get(&q)
+            .and_then(|s| s.get_data().get(address))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(circuit: &Circuit, q: &[usize]) -> u64 {
+        read_bits(circuit.get_signals(), q)
+    }
+
+    #[test]
+    fn test_memory() {
+        assert_eq!(parse_words("0f 1_0 // comment\n\nA").unwrap(), [0xf, 0x10, 0xa]);
+        assert!(matches!(parse_words("1\n2 x3"), Err(MemoryError::InvalidWord(2, w)) if w == "x3"));
+
+        let mut circuit = Circuit::new(3);
+        let address = circuit.get_input();
+        assert!(matches!(circuit.add_rom(&address[..2], 4, vec![0; 5]), Err(MemoryError::TooManyWords(5, 2))));
+        assert!(matches!(circuit.add_rom(&address, 4, vec![1, 0x10]), Err(MemoryError::WordTooWide(1, 0x10, 4))));
+        assert!(matches!(circuit.add_rom(&address, 0, vec![]), Err(MemoryError::NoData)));
+        assert!(matches!(circuit.load_rom("/nonexistent/rom.hex", &address, 4), Err(MemoryError::Io(_))));
+        let path = std::env::temp_dir().join(format!("digicir_rom_{}.hex", std::process::id()));
+        fs::write(&path, "3 1 4 1 5 9 2 6").unwrap();
+        let q = circuit.load_rom(&path, &address, 4).unwrap();
+        fs::remove_file(&path).unwrap();
+        for (a, expected) in [3, 1, 4, 1, 5, 9, 2, 6].into_iter().enumerate() {
+            circuit.execute_sequential_mut(&[a & 1, a >> 1 & 1, a >> 2]);
+            assert_eq!(read(&circuit, &q), expected);
+        }
+
+        //输入为 clock, we, a[0..2], d[0..3]
+        let mut circuit = Circuit::new(7);
+        let input = circuit.get_input();
+        let q = circuit.add_ram(input[0], input[1], &input[2..4], &input[4..]).unwrap();
+        let mut step = |clock: usize, we: usize, a: usize, d: usize| {
+            circuit.execute_sequential_mut(&[clock, we, a & 1, a >> 1, d & 1, d >> 1 & 1, d >> 2]);
+            read(&circuit, &q)
+        };
+        for (a, d) in [(0, 5), (1, 3), (3, 7)] {
+            assert_eq!(step(0, 1, a, d), 0);
+            //写入在上升沿发生，同一轮就能读出
+            assert_eq!(step(1, 1, a, d), d as u64);
+        }
+        //we 为 0 或时钟没有上升沿时不写入
+        assert_eq!(step(0, 0, 2, 6), 0);
+        assert_eq!(step(1, 0, 2, 6), 0);
+        assert_eq!(step(1, 1, 2, 6), 0);
+        for (a, d) in [(0, 5), (1, 3), (2, 0), (3, 7)] {
+            assert_eq!(step(0, 0, a, 0), d);
+        }
+        assert_eq!(circuit.memory_word(q[0], 3), 7);
+
+        let v = circuit.to_verilog("top", &["clk", "we"], &[("q0", q[0])]);
+        assert!(v.contains("always @(posedge clk) if (we) m0[{i3, i2}] <= {i6, i5, i4};"));
+        assert!(v.contains("m0[3] = 3'h7;"));
+
+        //we 还没有被驱动时上升沿不写入
+        let mut circuit = Circuit::new(3);
+        let we = circuit.advance_output(1)[0];
+        let q = circuit.add_ram(0, we, &[1], &[2]).unwrap();
+        circuit.execute_sequential_mut(&[0, 0, 1]);
+        circuit.execute_sequential_mut(&[1, 0, 1]);
+        assert_eq!(circuit.memory_word(q[0], 0), 0);
+    }
+}
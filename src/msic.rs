@@ -18,7 +18,7 @@ pub fn usize_signal_vec(usize_vec: &[usize]) -> Vec<Signal> {
 }
 
 pub fn choose_signals(signals: &[Signal], indexes: &[usize]) -> Vec<Signal> {
-    indexes.iter().map(|&i| signals[i].clone()).collect()
+    indexes.iter().map(|&i| signals[i]).collect()
 }
 
 pub fn print_signals(signals: &[Signal], indexes: &[usize]) {
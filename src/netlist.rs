@@ -0,0 +1,397 @@
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PatternNode, PniType};
+use crate::register::State;
+
+//Verilog、BLIF 等网表格式读入后共用的中间表示，负责展开实例、排序并生成电路
+
+#[derive(Debug, Error)]
+pub enum NetlistError {
+    #[error("no module found")]
+    NoModule,
+    #[error("unknown module `{0}`")]
+    UnknownModule(String),
+    #[error("module `{0}` has no port `{1}`")]
+    UnknownPort(String, String),
+    #[error("net `{1}` in module `{0}` is never driven")]
+    Undriven(String, String),
+    #[error("net `{1}` in module `{0}` has more than one driver")]
+    MultipleDrivers(String, String),
+    #[error("module `{0}`: {1}")]
+    Unsupported(String, String),
+}
+
+//Assign 为 `y = x` 的别名；Latch 的输入为 (控制, 数据)，输出为状态，附带初始值
+//Flop 与 Latch 相同，但只在控制信号的上升沿保存数据
+#[derive(Debug, Clone)]
+pub(crate) enum CellKind {
+    Gate(NodeType),
+    Instance(String),
+    Assign,
+    Constant(bool),
+    Latch(Option<bool>),
+    Flop(Option<bool>),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Cell {
+    pub kind: CellKind,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl Cell {
+    pub fn new(kind: CellKind, inputs: Vec<String>, outputs: Vec<String>) -> Cell {
+        Self { kind, inputs, outputs }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Connection {
+    Named(Vec<(String, Option<String>)>),
+    Positional(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Module {
+    pub name: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub cells: Vec<Cell>,
+    //与 cells 中 Instance 的顺序一致
+    pub connections: Vec<Connection>,
+}
+
+impl Module {
+    pub fn add_instance(&mut self, name: String, connection: Connection) {
+        self.cells.push(Cell::new(CellKind::Instance(name), Vec::new(), Vec::new()));
+        self.connections.push(connection);
+    }
+}
+
+pub(crate) struct Netlist {
+    modules: Vec<Module>,
+}
+
+impl Netlist {
+    //把实例的连接整理成被实例化模块的输入、输出顺序
+    pub fn new(mut modules: Vec<Module>) -> Result<Netlist, NetlistError> {
+        for m in 0..modules.len() {
+            let mut k = 0;
+            for c in 0..modules[m].cells.len() {
+                let CellKind::Instance(name) = &modules[m].cells[c].kind else { continue };
+                let sub = modules.iter()
+                    .find(|x| &x.name == name)
+                    .ok_or_else(|| NetlistError::UnknownModule(name.clone()))?;
+                let nets = match &modules[m].connections[k] {
+                    Connection::Positional(nets) => {
+                        let ports = sub.inputs.iter().chain(&sub.outputs);
+                        ports.zip(nets).map(|(p, n)| (p.clone(), n.clone())).collect()
+                    }
+                    Connection::Named(named) => {
+                        let mut nets = HashMap::new();
+                        for (port, net) in named {
+                            if !sub.inputs.contains(port) && !sub.outputs.contains(port) {
+                                return Err(NetlistError::UnknownPort(name.clone(), port.clone()));
+                            }
+                            if let Some(net) = net { nets.insert(port.clone(), net.clone()); }
+                        }
+                        nets
+                    }
+                };
+                let unconnected = format!("{}.unconnected.{}", name, c);
+                let inputs = sub.inputs.iter()
+                    .map(|p| nets.get(p).cloned().ok_or_else(|| NetlistError::Undriven(name.clone(), p.clone())))
+                    .collect::<Result<Vec<String>, NetlistError>>()?;
+                let outputs = sub.outputs.iter()
+                    .map(|p| nets.get(p).cloned().unwrap_or_else(|| format!("{}.{}", unconnected, p)))
+                    .collect();
+                modules[m].cells[c].inputs = inputs;
+                modules[m].cells[c].outputs = outputs;
+                k += 1;
+            }
+        }
+        Ok(Self { modules })
+    }
+
+    pub fn module(&self, name: &str) -> Result<&Module, NetlistError> {
+        self.modules.iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| NetlistError::UnknownModule(name.to_string()))
+    }
+
+    pub fn first(&self) -> Result<&Module, NetlistError> {
+        self.modules.first().ok_or(NetlistError::NoModule)
+    }
+
+    //没有被其它模块实例化的最后一个模块
+    pub fn top(&self) -> Result<&Module, NetlistError> {
+        let used = self.modules.iter()
+            .flat_map(|m| m.cells.iter())
+            .filter_map(|c| match &c.kind {
+                CellKind::Instance(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<String>>();
+        self.modules.iter()
+            .rev()
+            .find(|m| !used.contains(&m.name))
+            .or(self.modules.last())
+            .ok_or(NetlistError::NoModule)
+    }
+
+    fn instances(module: &Module) -> impl Iterator<Item = &String> {
+        module.cells.iter().filter_map(|c| match &c.kind {
+            CellKind::Instance(name) => Some(name),
+            _ => None,
+        })
+    }
+
+    //从 top 出发能实例化到的模块，不含 top 本身
+    fn reachable(&self, top: &Module) -> Result<Vec<&Module>, NetlistError> {
+        let mut seen = HashSet::from([top.name.clone()]);
+        let mut stack = vec![top];
+        let mut modules = Vec::new();
+        while let Some(module) = stack.pop() {
+            for name in Self::instances(module) {
+                if seen.insert(name.clone()) {
+                    let sub = self.module(name)?;
+                    modules.push(sub);
+                    stack.push(sub);
+                }
+            }
+        }
+        Ok(modules)
+    }
+
+    //自身或者实例化的模块中含有 latch、flop 的模块
+    fn is_sequential(&self, module: &Module, visiting: &mut HashSet<String>) -> bool {
+        if !visiting.insert(module.name.clone()) {
+            return false;
+        }
+        module.cells.iter().any(|c| matches!(c.kind, CellKind::Latch(_) | CellKind::Flop(_)))
+            || Self::instances(module).any(|name| self.module(name).is_ok_and(|sub| self.is_sequential(sub, visiting)))
+    }
+
+    //把模块的单元复制到 cells 中，bind 给出端口对应的外部连线；flatten 为 true 的实例被展开
+    fn collect(
+        &self,
+        module: &Module,
+        prefix: &str,
+        bind: &HashMap<String, String>,
+        flatten: &dyn Fn(&str) -> bool,
+        cells: &mut Vec<Cell>,
+    ) -> Result<(), NetlistError> {
+        let net = |n: &String| bind.get(n).cloned().unwrap_or_else(|| format!("{}{}", prefix, n));
+        for (c, cell) in module.cells.iter().enumerate() {
+            let inputs = cell.inputs.iter().map(net).collect::<Vec<String>>();
+            let outputs = cell.outputs.iter().map(net).collect::<Vec<String>>();
+            match &cell.kind {
+                CellKind::Instance(name) if flatten(name) => {
+                    let sub = self.module(name)?;
+                    let bind = sub.inputs.iter().chain(&sub.outputs)
+                        .cloned()
+                        .zip(inputs.into_iter().chain(outputs))
+                        .collect::<HashMap<String, String>>();
+                    self.collect(sub, &format!("{}{}.{}.", prefix, name, c), &bind, flatten, cells)?;
+                }
+                kind => cells.push(Cell::new(kind.clone(), inputs, outputs)),
+            }
+        }
+        Ok(())
+    }
+
+    //整理出按求值顺序排列的单元：消去 assign，检查驱动，并做拓扑排序
+    fn cells(&self, module: &Module, flatten: &dyn Fn(&str) -> bool) -> Result<Vec<Cell>, NetlistError> {
+        let mut cells = Vec::new();
+        self.collect(module, "", &HashMap::new(), flatten, &mut cells)?;
+
+        let ports = module.inputs.iter().chain(&module.outputs).cloned().collect::<HashSet<String>>();
+        let rename = |cells: &mut Vec<Cell>, from: &str, to: &str| {
+            for cell in cells.iter_mut() {
+                for n in cell.inputs.iter_mut().chain(cell.outputs.iter_mut()) {
+                    if n == from { *n = to.to_string(); }
+                }
+            }
+        };
+        let mut i = 0;
+        while i < cells.len() {
+            if let CellKind::Assign = cells[i].kind {
+                let (x, y) = (cells[i].inputs[0].clone(), cells[i].outputs[0].clone());
+                if !ports.contains(&y) {
+                    cells.remove(i);
+                    rename(&mut cells, &y, &x);
+                    continue;
+                } else if !ports.contains(&x) {
+                    cells.remove(i);
+                    rename(&mut cells, &x, &y);
+                    continue;
+                }
+                //两端都是端口时保留一个单输入与门作为缓冲
+                cells[i].kind = CellKind::Gate(NodeType::And);
+            }
+            i += 1;
+        }
+
+        let mut driver = HashMap::new();
+        for (i, cell) in cells.iter().enumerate() {
+            for o in &cell.outputs {
+                if module.inputs.contains(o) || driver.insert(o.clone(), i).is_some() {
+                    return Err(NetlistError::MultipleDrivers(module.name.clone(), o.clone()));
+                }
+            }
+        }
+        for n in cells.iter().flat_map(|c| c.inputs.iter()).chain(&module.outputs) {
+            if !driver.contains_key(n) && !module.inputs.contains(n) {
+                return Err(NetlistError::Undriven(module.name.clone(), n.clone()));
+            }
+        }
+
+        //依次取出第一个输入都已就绪的单元，存在回路时取声明顺序最靠前的单元
+        //latch 和 flop 总是就绪，它们读到的是上一次执行留下的数据
+        let mut placed = vec![false; cells.len()];
+        let mut order = Vec::new();
+        while order.len() < cells.len() {
+            let ready = |i: &usize| !placed[*i] && (matches!(cells[*i].kind, CellKind::Latch(_) | CellKind::Flop(_))
+                || cells[*i].inputs.iter().all(|n| driver.get(n).is_none_or(|d| placed[*d])));
+            let next = (0..cells.len()).find(ready)
+                .or_else(|| (0..cells.len()).find(|i| !placed[*i]))
+                .unwrap();
+            placed[next] = true;
+            order.push(next);
+        }
+        Ok(order.into_iter().map(|i| cells[i].clone()).collect())
+    }
+
+    fn to_pattern(&self, module: &Module) -> Result<Pattern, NetlistError> {
+        let cells = self.cells(module, &|_| true)?;
+        let unsupported = |what: &str| Err(NetlistError::Unsupported(module.name.clone(), what.to_string()));
+        //多输入的 xor 拆成两输入的链，常量由第一个输入和它的反构成，记录每个单元输出所在的 node
+        let mut output_node = HashMap::new();
+        let mut count = 0;
+        for cell in &cells {
+            count += match cell.kind {
+                CellKind::Gate(NodeType::Xor) => cell.inputs.len().max(2) - 1,
+                CellKind::Constant(_) if module.inputs.is_empty() => return unsupported("constant without inputs"),
+                CellKind::Constant(_) => 2,
+                CellKind::Latch(_) | CellKind::Flop(_) => return unsupported("latch inside a pattern"),
+                _ => 1,
+            };
+            output_node.insert(cell.outputs[0].clone(), count - 1);
+        }
+        let operand = |n: &String| match module.inputs.iter().position(|i| i == n) {
+            Some(i) => PniType::Input(i),
+            None => PniType::NodeOutput((output_node[n], 0)),
+        };
+
+        let mut nodes = Vec::new();
+        for cell in &cells {
+            let inputs = cell.inputs.iter().map(operand).collect::<Vec<PniType>>();
+            match &cell.kind {
+                CellKind::Constant(value) => {
+                    nodes.push(PatternNode::new(NodeType::Not, vec![PniType::Input(0)]));
+                    let inverse = PniType::NodeOutput((nodes.len() - 1, 0));
+                    let gate = if *value { NodeType::NAnd } else { NodeType::And };
+                    nodes.push(PatternNode::new(gate, vec![PniType::Input(0), inverse]));
+                }
+                CellKind::Gate(NodeType::Xor) if inputs.len() > 2 => {
+                    nodes.push(PatternNode::new(NodeType::Xor, inputs[..2].to_vec()));
+                    for input in &inputs[2..] {
+                        let last = PniType::NodeOutput((nodes.len() - 1, 0));
+                        nodes.push(PatternNode::new(NodeType::Xor, vec![last, *input]));
+                    }
+                }
+                CellKind::Gate(gate) => nodes.push(PatternNode::new(gate.clone(), inputs)),
+                _ => unreachable!(),
+            }
+        }
+        let outputs = module.outputs.iter().map(|o| (output_node[o], 0)).collect();
+
+        let mut pattern = Pattern::new(module.inputs.len(), outputs, nodes);
+        let input_name = module.inputs.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let output_name = module.outputs.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        pattern.set_name(&input_name, &output_name);
+        Ok(pattern)
+    }
+
+    //顶层模块成为电路本身，顶层用到的组合模块各自成为一个 pattern（模块内部的实例会被展开）
+    //含有 latch、flop 的模块不能成为 pattern，在顶层展开；没有用到的模块被忽略
+    //返回顶层的各个输出及其信号下标
+    pub fn build(&self, top: &Module) -> Result<(Circuit, Vec<(String, usize)>), NetlistError> {
+        let mut circuit = Circuit::new(top.inputs.len());
+        let mut sequential = HashSet::new();
+        for module in self.reachable(top)? {
+            if self.is_sequential(module, &mut HashSet::new()) {
+                sequential.insert(module.name.clone());
+            } else {
+                let pattern = self.to_pattern(module)?;
+                circuit.get_patterns_mut().insert(module.name.clone(), pattern);
+            }
+        }
+
+        let cells = self.cells(top, &|name| sequential.contains(name))?;
+        let mut signal = top.inputs.iter()
+            .enumerate()
+            .map(|(i, n)| (n.clone(), i))
+            .collect::<HashMap<String, usize>>();
+        //先读后写的连线（回路、latch 的数据）初始为 0
+        let mut driven = HashSet::new();
+        let mut feedback = HashSet::new();
+        for cell in &cells {
+            feedback.extend(cell.inputs.iter().filter(|n| !signal.contains_key(*n) && !driven.contains(*n)).cloned());
+            driven.extend(cell.outputs.iter().cloned());
+        }
+        for cell in &cells {
+            for n in &cell.outputs {
+                let s = match cell.kind {
+                    CellKind::Constant(value) => circuit.advance_output_with_value(&[value as usize])[0],
+                    CellKind::Latch(init) | CellKind::Flop(init) => {
+                        circuit.advance_output_with_value(&[init.unwrap_or(false) as usize])[0]
+                    }
+                    _ if feedback.contains(n) => circuit.advance_output_with_value(&[0])[0],
+                    _ => circuit.advance_output(1)[0],
+                };
+                signal.insert(n.clone(), s);
+            }
+        }
+
+        for cell in &cells {
+            let mut inputs = cell.inputs.iter().map(|n| signal[n]).collect::<Vec<usize>>();
+            let outputs = cell.outputs.iter().map(|n| signal[n]).collect::<Vec<usize>>();
+            match &cell.kind {
+                CellKind::Instance(name) => {
+                    circuit.add_node(NodeType::Pattern(name.clone()), inputs, outputs);
+                }
+                CellKind::Latch(_) => {
+                    if !circuit.get_patterns().contains_key("dlatch") {
+                        Pattern::build_dlatch(&mut circuit);
+                    }
+                    circuit.add_node(NodeType::Pattern("dlatch".to_string()), inputs, outputs);
+                }
+                //一位的寄存器，enable 恒为 1，reset 恒为 0
+                CellKind::Flop(init) => {
+                    let constants = circuit.advance_output_with_value(&[1, 0]);
+                    let inputs = vec![inputs[0], constants[0], constants[1], inputs[1]];
+                    circuit.add_node(NodeType::Register(1), inputs, outputs.clone());
+                    circuit.get_states_mut().insert(outputs[0], State::new(vec![init.unwrap_or(false) as u64]));
+                }
+                CellKind::Gate(NodeType::Xor) if inputs.len() > 2 => {
+                    let last = inputs.pop().unwrap();
+                    let first = inputs[0];
+                    let rest = inputs[1..].iter().fold(first, |acc, i| circuit.add_gate_t(NodeType::Xor, &[acc, *i]));
+                    circuit.add_node(NodeType::Xor, vec![rest, last], outputs);
+                }
+                CellKind::Gate(gate) => {
+                    circuit.add_node(gate.clone(), inputs, outputs);
+                }
+                CellKind::Constant(_) => {}
+                CellKind::Assign => unreachable!(),
+            }
+        }
+
+        let outputs = top.outputs.iter().map(|o| (o.clone(), signal[o])).collect();
+        Ok((circuit, outputs))
+    }
+}
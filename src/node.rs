@@ -6,21 +6,34 @@ use crate::signal::Signal;
 use NodeType::*;
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum NodeType {
     And, Or, Not, Xor, NAnd,
-    Pattern(String)
+    Pattern(String),
+    //行为级的上升沿寄存器，参数为位宽，状态保存在 Circuit 中
+    Register(usize),
+    //行为级的存储器，参数为地址位宽和数据位宽，内容保存在 Circuit 中
+    Rom(usize, usize),
+    Ram(usize, usize),
 }
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Node {
     node_type: NodeType,
     inputs: Vec<usize>,
     outputs: Vec<usize>,
+    //pattern 实例保存内部状态（Pattern::get_state）的信号，不是输出端口
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    state: Vec<usize>,
 }
 
 impl Node {
     pub fn new(node_type: NodeType, inputs: Vec<usize>, outputs: Vec<usize>) -> Self {
-        Node { node_type, inputs, outputs }
+        Node { node_type, inputs, outputs, state: Vec::new() }
+    }
+
+    pub fn with_state(mut self, state: Vec<usize>) -> Self {
+        self.state = state;
+        self
     }
 
     pub fn execute(&self, circuit: &Circuit, signals: &[Signal]) -> Vec<Signal> {
@@ -36,12 +49,14 @@ impl Node {
                     .map(|i| signals[*i])
                     .collect::<Vec<Signal>>();
                 let res = Self::execute_gate(self.get_type(), &input_signals);
-                signals[self.get_output()[0]] = res;
+                signals[self.get_output()[0]].set_value(res);
             }
             Pattern(name) => {
                 let pattern = circuit.get_patterns().get(name).unwrap();
                 self.execute_pattern_mut(pattern, signals);
             }
+            Register(_) => self.execute_register_mut(circuit, signals),
+            Rom(..) | Ram(..) => self.execute_memory_mut(circuit, signals),
         }
     }
 
@@ -56,22 +71,31 @@ impl Node {
         //Pattern中的node没有保存输出，这里的output实际上是node的索引下标，用来指向用作输出的node
         let output_nodes = pattern.get_output();
         let input_signals = self.get_input().iter()
-            .map(|i| signals[*i].clone())
+            .map(|i| signals[*i])
             .collect::<Vec<Signal>>();
         //用来存储每个node的输出
-        let mut output_signals = vec![Signal::default(); nodes.len()];
-        //预读信号提供给触发器使用
+        let mut output_signals = vec![vec![Signal::default()]; nodes.len()];
+        //预读信号提供给触发器使用，内部状态从 state 信号中读出
+        let state_nodes = pattern.get_state();
         self.get_output().iter().enumerate().for_each(|(i, o)| {
-            output_signals[output_nodes[i]] = signals[*o].clone();
+            let (node, index) = output_nodes[i];
+            output_signals[node][index] = signals[*o];
         });
+        for (s, (node, index)) in self.get_state().iter().zip(&state_nodes) {
+            output_signals[*node][*index] = signals[*s];
+        }
         for (i, node) in nodes.iter().enumerate() {
             let res = node.execute(&input_signals, &output_signals);
-            output_signals[i]= res.clone();
+            output_signals[i][0] = res;
         }
         self.get_output().iter().enumerate()
             .for_each(|(node, i)|  {
-                signals[*i] = output_signals[output_nodes[node]]
+                let (node, index) = output_nodes[node];
+                signals[*i].set_value(output_signals[node][index])
             });
+        for (s, (node, index)) in self.get_state().iter().zip(&state_nodes) {
+            signals[*s].set_value(output_signals[*node][*index]);
+        }
     }
 
     pub fn execute_gate(gate: &NodeType, input_signals: &[Signal]) -> Signal {
@@ -80,16 +104,16 @@ impl Node {
                 Self::execute_not(&input_signals[0])
             }
             And => {
-                Self::execute_and(&input_signals)
+                Self::execute_and(input_signals)
             }
             Or => {
-                Self::execute_or(&input_signals)
+                Self::execute_or(input_signals)
             }
             Xor => {
                 Self::execute_xor(&input_signals[0], &input_signals[1])
             }
             NAnd => {
-                Self::execute_nand(&input_signals)
+                Self::execute_nand(input_signals)
             }
             _ => unreachable!()
         }
@@ -125,6 +149,8 @@ impl Node {
 
     pub fn get_input(&self) -> &[usize] { &self.inputs }
 
+    pub fn get_state(&self) -> &[usize] { &self.state }
+
 }
 
 impl fmt::Debug for Node {
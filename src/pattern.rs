@@ -6,7 +6,7 @@ use crate::msic::gain_gate_type;
 use crate::node::{Node, NodeType};
 use crate::signal::Signal;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Pattern {
     input_size: usize,
     output: Vec<(usize, usize)>,
@@ -74,26 +74,59 @@ impl Pattern {
     
     pub fn get_input_name(&self) -> &Vec<String> { &self.port_name.0 }
     pub fn get_output_name(&self) -> &Vec<String> { &self.port_name.1 }
+
+    //没有设置端口名时依次记为 i0, i1... 和 o0, o1...
+    pub fn input_port(&self, index: usize) -> String {
+        self.port_name.0.get(index).cloned().unwrap_or_else(|| format!("i{}", index))
+    }
+    pub fn output_port(&self, index: usize) -> String {
+        self.port_name.1.get(index).cloned().unwrap_or_else(|| format!("o{}", index))
+    }
     
+    pub fn get_description(&self) -> &str { &self.description }
+
     pub fn set_description(&mut self, d: &str) -> &mut Pattern {
         self.description = d.to_string();
         self
     }
 
+    //存在引用自身或后面 node 的输入，即带反馈回路（触发器、锁存器）
+    pub fn is_sequential(&self) -> bool {
+        self.pattern.iter().enumerate().any(|(i, node)| {
+            node.get_input().iter().any(|input| matches!(input, PniType::NodeOutput((n, _)) if *n >= i))
+        })
+    }
+
+    //被自身或前面的 node 读取、又不是输出的 node 输出，例如主从触发器的主锁存器。
+    //它们不是端口，实例把这些值保存在 Node 的 state 信号中，在两次执行之间保持
+    pub fn get_state(&self) -> Vec<(usize, usize)> {
+        let mut state = Vec::new();
+        for (i, node) in self.pattern.iter().enumerate() {
+            for input in node.get_input() {
+                if let PniType::NodeOutput((n, k)) = input
+                    && *n >= i && !self.output.contains(&(*n, *k)) && !state.contains(&(*n, *k)) {
+                    state.push((*n, *k));
+                }
+            }
+        }
+        state.sort();
+        state
+    }
+
     pub fn print_information(&self) {
         print!("input: {:?}", self.get_input_name());
-        print!("output: {:?}\n", self.get_output_name());
+        println!("output: {:?}", self.get_output_name());
         println!("function: {}", self.description);
     }
 }
 
-#[derive(Clone, Copy, Deserialize, Serialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum PniType {
     Input(usize),
     NodeOutput((usize, usize)),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PatternNode {
     gate_type: NodeType,
     inputs: Vec<PniType>,
@@ -106,7 +139,7 @@ impl PatternNode {
     pub fn execute(
         &self,
         input_signals: &[Signal],
-        output_signals: &[&[Signal]],
+        output_signals: &[Vec<Signal>],
     ) -> Signal {
         let input_signals = self.inputs.iter().map(|i| {
             match i {
@@ -141,13 +174,13 @@ impl fmt::Display for PatternNode {
 
 impl fmt::Display for Pattern {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "input size: {}, output node: {:?}\n", self.input_size, self.output)?;
+        writeln!(f, "input size: {}, output node: {:?}", self.input_size, self.output)?;
         for (i, node) in self.pattern.iter().enumerate() {
             write!(f, "{} => N({})", node, i)?;
             if self.output.iter().any(|(x, _)| i == *x) {
                 write!(f, "*")?
             }
-            write!(f, "\n")?
+            writeln!(f)?
         }
         Ok(())
     }
@@ -0,0 +1,278 @@
+use macroquad::prelude::{
+    clear_background, draw_circle, draw_line, draw_rectangle, draw_rectangle_lines, draw_text, get_time,
+    is_key_pressed, next_frame, screen_height, screen_width, Color, KeyCode, BLACK, BLUE, DARKGRAY, GRAY,
+    ORANGE, RED, WHITE, YELLOW,
+};
+use macroquad::Window;
+use macroquad::window::Conf;
+use crate::circuit::Circuit;
+use crate::schematic::{BlockKind, Schematic};
+use crate::signal::Signal;
+
+//自动运行时每一步的间隔（秒）
+const INTERVAL: f64 = 0.3;
+const INPUT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+    KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+//逐个节点执行 pipeline，一轮执行完所有节点，每轮开始时写入输入
+pub struct Propagation {
+    circuit: Circuit,
+    inputs: Vec<usize>,
+    //下一个要执行的节点、已完成的轮数
+    next: usize,
+    pass: usize,
+    //本轮中输出改变过的节点
+    changed: Vec<bool>,
+    //上一轮是否有节点的输出改变
+    settled: bool,
+}
+
+impl Propagation {
+    pub fn new(circuit: Circuit) -> Propagation {
+        let inputs = circuit.get_signals()[..circuit.get_input().len()].iter()
+            .map(|s| (s.is_valid() && s.get_unchecked()) as usize)
+            .collect();
+        let changed = vec![false; circuit.get_pipeline().len()];
+        Self { circuit, inputs, next: 0, pass: 0, changed, settled: false }
+    }
+
+    pub fn get_circuit(&self) -> &Circuit { &self.circuit }
+    pub fn get_inputs(&self) -> &Vec<usize> { &self.inputs }
+    pub fn get_pass(&self) -> usize { self.pass }
+    pub fn get_next(&self) -> usize { self.next }
+    pub fn is_changed(&self, node: usize) -> bool { self.changed[node] }
+    //完整的一轮中没有任何输出改变
+    pub fn is_settled(&self) -> bool { self.settled }
+
+    //输入在下一轮开始时生效
+    pub fn toggle(&mut self, input: usize) {
+        if let Some(value) = self.inputs.get_mut(input) {
+            *value = 1 - *value;
+        }
+    }
+
+    //执行一个节点，返回它的下标
+    pub fn step(&mut self) -> Option<usize> {
+        let size = self.circuit.get_pipeline().len();
+        if size == 0 { return None; }
+        if self.next == 0 {
+            self.circuit.init_input(&self.inputs);
+            self.changed.iter_mut().for_each(|c| *c = false);
+        }
+        let k = self.next;
+        let node = self.circuit.get_node(k);
+        let before = node.get_output().iter().map(|o| self.circuit.get_signal(*o)).collect::<Vec<Signal>>();
+        let mut signals = self.circuit.get_signals().clone();
+        node.execute_mut(&self.circuit, &mut signals);
+        self.changed[k] = node.get_output().iter().zip(&before).any(|(o, b)| signals[*o] != *b);
+        *self.circuit.get_signals_mut() = signals;
+
+        self.next += 1;
+        if self.next == size {
+            self.next = 0;
+            self.pass += 1;
+            //一轮结束时寄存器在时钟沿更新，新状态在下一轮输出
+            let clocked = self.circuit.clock_mut();
+            self.settled = !clocked && !self.changed.iter().any(|c| *c);
+        }
+        Some(k)
+    }
+
+    //执行完当前这一轮
+    pub fn finish_pass(&mut self) {
+        let pass = self.pass;
+        while self.pass == pass && self.step().is_some() {}
+    }
+}
+
+fn value_color(signal: Signal) -> Color {
+    if !signal.is_valid() {
+        GRAY
+    } else if signal.get_unchecked() {
+        RED
+    } else {
+        BLUE
+    }
+}
+
+struct Visualizer {
+    propagation: Propagation,
+    schematic: Schematic,
+    //schematic 中每个元件输出引脚对应的信号，常量没有信号
+    signals: Vec<Vec<Option<usize>>>,
+    //刚执行的节点
+    current: Option<usize>,
+}
+
+impl Visualizer {
+    fn new(circuit: Circuit, input_name: &[&str], outputs: &[(&str, usize)]) -> Visualizer {
+        let schematic = circuit.schematic(input_name, outputs);
+        let input_size = circuit.get_input().len();
+        let pipeline = circuit.get_pipeline();
+        let signals = (0..schematic.get_blocks().len())
+            .map(|b| match b {
+                b if b < input_size => vec![Some(b)],
+                b if b < input_size + pipeline.len() => pipeline[b - input_size].get_output().iter().map(|o| Some(*o)).collect(),
+                _ => vec![None],
+            })
+            .collect();
+        Self { propagation: Propagation::new(circuit), schematic, signals, current: None }
+    }
+
+    fn signal(&self, (block, pin): (usize, usize)) -> Signal {
+        match self.signals[block][pin] {
+            Some(s) => self.propagation.get_circuit().get_signal(s),
+            None => match self.schematic.get_blocks()[block].get_kind() {
+                BlockKind::Input(label) if label != "?" => Signal::from_usize((label == "1") as usize),
+                _ => Signal::default(),
+            },
+        }
+    }
+
+    fn draw(&self) {
+        clear_background(WHITE);
+        let (width, height) = self.schematic.size();
+        let top = 40.0;
+        let scale = (screen_width() / width).min((screen_height() - top) / height).min(2.0);
+        let at = |(x, y): (f32, f32)| (x * scale, y * scale + top);
+
+        for wire in self.schematic.get_wires() {
+            let color = value_color(self.signal(wire.get_source()));
+            for pair in wire.get_points().windows(2) {
+                let (a, b) = (at(pair[0]), at(pair[1]));
+                draw_line(a.0, a.1, b.0, b.1, 2.0, color);
+            }
+        }
+        for (x, y) in self.schematic.get_junctions() {
+            let (x, y) = at((*x, *y));
+            draw_circle(x, y, 3.0, BLACK);
+        }
+
+        let input_size = self.propagation.get_circuit().get_input().len();
+        let font = 14.0 * scale.max(0.75);
+        for (b, block) in self.schematic.get_blocks().iter().enumerate() {
+            let (x, y, w, h) = block.get_rect();
+            let (x, y) = at((x, y));
+            let (w, h) = (w * scale, h * scale);
+            let node = b.checked_sub(input_size).filter(|k| *k < self.propagation.get_circuit().get_pipeline().len());
+            match block.get_kind() {
+                BlockKind::Input(name) => {
+                    let label = match b < input_size && b < INPUT_KEYS.len() {
+                        true => format!("[{}] {}", b + 1, name),
+                        false => name.clone(),
+                    };
+                    draw_text(&label, x, y + h * 0.75, font, value_color(self.signal((b, 0))));
+                }
+                BlockKind::Output(name) => { draw_text(name, x + 4.0, y + h * 0.75, font, BLACK); }
+                kind => {
+                    //刚执行的节点为黄色，本轮输出改变过的节点为橙色
+                    let fill = match node {
+                        Some(k) if Some(k) == self.current => YELLOW,
+                        Some(k) if self.propagation.is_changed(k) => ORANGE,
+                        _ => WHITE,
+                    };
+                    draw_rectangle(x, y, w, h, fill);
+                    draw_rectangle_lines(x, y, w, h, 2.0, BLACK);
+                    let label = match kind {
+                        BlockKind::Gate(gate) => format!("{:?}", gate).to_uppercase(),
+                        BlockKind::Pattern { name, .. } => name.clone(),
+                        _ => unreachable!(),
+                    };
+                    draw_text(&label, x + 3.0, y + h / 2.0 + font / 4.0, font * 0.8, BLACK);
+                    for k in 0..block.output_size() {
+                        let (px, py) = at(block.output_pin(k));
+                        draw_circle(px, py, 3.0, value_color(self.signal((b, k))));
+                    }
+                }
+            }
+        }
+
+        let status = format!(
+            "pass {}  node {}/{}  {}   [space] step  [enter] pass  [r] run  [1-9] toggle input  [backspace] reset",
+            self.propagation.get_pass(),
+            self.propagation.get_next(),
+            self.propagation.get_circuit().get_pipeline().len(),
+            if self.propagation.is_settled() { "settled" } else { "" },
+        );
+        draw_text(&status, 10.0, 24.0, 20.0, DARKGRAY);
+    }
+}
+
+//打开窗口动画显示信号在 pipeline 中的传播，参数与 Circuit::to_svg 相同
+pub fn run(circuit: Circuit, input_name: &[&str], outputs: &[(&str, usize)]) {
+    let initial = serde_json::to_string(&circuit).unwrap();
+    let mut visualizer = Visualizer::new(circuit, input_name, outputs);
+    let conf = Conf { window_title: "digicir".to_string(), window_width: 1024, window_height: 640, ..Default::default() };
+    Window::from_config(conf, async move {
+        let mut running = false;
+        let mut last = get_time();
+        loop {
+            if is_key_pressed(KeyCode::Space) {
+                visualizer.current = visualizer.propagation.step();
+            }
+            if is_key_pressed(KeyCode::Enter) {
+                visualizer.propagation.finish_pass();
+                visualizer.current = None;
+            }
+            if is_key_pressed(KeyCode::R) {
+                running = !running;
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                let circuit = serde_json::from_str::<Circuit>(&initial).unwrap();
+                visualizer.propagation = Propagation::new(circuit);
+                visualizer.current = None;
+            }
+            for (i, key) in INPUT_KEYS.iter().enumerate() {
+                if is_key_pressed(*key) {
+                    visualizer.propagation.toggle(i);
+                }
+            }
+            if running && get_time() - last > INTERVAL {
+                visualizer.current = visualizer.propagation.step();
+                last = get_time();
+            }
+            visualizer.draw();
+            next_frame().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeType;
+
+    //与非门 RS 锁存器：置位后需要两轮才稳定，撤去置位后保持
+    #[test]
+    fn test_latch() {
+        let mut circuit = Circuit::new(2);
+        let input = circuit.get_input();
+        let q = circuit.advance_output_with_value(&[0, 1]);
+        circuit.add_node(NodeType::NAnd, vec![input[0], q[1]], vec![q[0]]);
+        circuit.add_node(NodeType::NAnd, vec![input[1], q[0]], vec![q[1]]);
+        circuit.init_input(&[1, 1]);
+
+        let mut propagation = Propagation::new(circuit);
+        assert_eq!(propagation.get_inputs(), &vec![1, 1]);
+        propagation.toggle(0);
+        assert_eq!(propagation.step(), Some(0));
+        assert!(propagation.is_changed(0));
+        assert!(propagation.get_circuit().get_signal(q[0]).get_unchecked());
+        assert_eq!(propagation.step(), Some(1));
+        assert!(propagation.is_changed(1));
+        assert_eq!(propagation.get_pass(), 1);
+        assert!(!propagation.is_settled());
+
+        propagation.finish_pass();
+        assert!(propagation.is_settled());
+        propagation.toggle(0);
+        propagation.finish_pass();
+        propagation.finish_pass();
+        assert!(propagation.is_settled());
+        assert_eq!(propagation.get_pass(), 4);
+        let signals = propagation.get_circuit().get_signals();
+        assert!(signals[q[0]].get_unchecked() && !signals[q[1]].get_unchecked());
+    }
+}
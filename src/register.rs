@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::node::{Node, NodeType};
+use crate::signal::Signal;
+
+//保存的数据是一个 u64
+const MAX_WIDTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum RegisterError {
+    #[error("register needs at least 1 bit")]
+    NoData,
+    #[error("register width {0} is wider than {MAX_WIDTH} bits")]
+    TooWide(usize),
+}
+
+//行为级节点的状态：上一次看到的时钟电平和保存的数据
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    clock: bool,
+    data: Vec<u64>,
+}
+
+impl State {
+    pub fn new(data: Vec<u64>) -> State {
+        Self { clock: false, data }
+    }
+
+    pub fn get_clock(&self) -> bool { self.clock }
+    pub fn get_data(&self) -> &Vec<u64> { &self.data }
+    pub fn get_data_mut(&mut self) -> &mut Vec<u64> { &mut self.data }
+}
+
+pub(crate) fn read_bits(signals: &[Signal], bits: &[usize]) -> u64 {
+    bits.iter().enumerate()
+        .map(|(i, s)| (signals[*s].get_unchecked() as u64) << i)
+        .sum()
+}
+
+//无效的信号按 0 处理
+pub(crate) fn is_high(signal: Signal) -> bool {
+    signal.is_valid() && signal.get_unchecked()
+}
+
+pub(crate) fn write_bits(signals: &mut [Signal], bits: &[usize], value: u64) {
+    for (i, s) in bits.iter().enumerate() {
+        signals[*s].set_value(Signal::from_usize((value >> i & 1) as usize));
+    }
+}
+
+impl Node {
+    //寄存器在 pipeline 中只输出保存的值，与执行顺序无关
+    pub(crate) fn execute_register_mut(&self, circuit: &Circuit, signals: &mut [Signal]) {
+        let value = circuit.register_value(self.get_output()[0]);
+        write_bits(signals, self.get_output(), value);
+    }
+
+    //由时钟驱动的行为级节点，第一个输入为时钟
+    pub(crate) fn is_clocked(&self) -> bool {
+        matches!(self.get_type(), NodeType::Register(_) | NodeType::Ram(..))
+    }
+
+    //时钟上升沿时要写入的 (数据下标, 值)，clock 为上一次看到的时钟电平，没有写入时为 None
+    fn next_state(&self, clock: bool, signals: &[Signal]) -> Option<(usize, u64)> {
+        let inputs = self.get_input();
+        if clock || !is_high(signals[inputs[0]]) {
+            return None;
+        }
+        match self.get_type() {
+            NodeType::Register(_) => {
+                if is_high(signals[inputs[2]]) {
+                    Some((0, 0))
+                } else if is_high(signals[inputs[1]]) {
+                    Some((0, read_bits(signals, &inputs[3..])))
+                } else {
+                    None
+                }
+            }
+            NodeType::Ram(..) => self.ram_write(signals),
+            _ => None,
+        }
+    }
+}
+
+impl Circuit {
+    //寄存器 reg：输入 clock, enable, reset, d[]，输出 q[]，初始值为 0。
+    //clock 上升沿时 reset 为 1 则清零，否则 enable 为 1 时保存 d；无效的 enable、reset 视为 0，d 为 1 到 64 位
+    pub fn add_register(&mut self, clock: usize, enable: usize, reset: usize, d: &[usize]) -> Result<Vec<usize>, RegisterError> {
+        match d.len() {
+            0 => return Err(RegisterError::NoData),
+            n if n > MAX_WIDTH => return Err(RegisterError::TooWide(n)),
+            _ => {}
+        }
+        let q = self.advance_output_with_value(&vec![0; d.len()]);
+        let inputs = [&[clock, enable, reset], d].concat();
+        self.add_node(NodeType::Register(d.len()), inputs, q.clone());
+        self.get_states_mut().insert(q[0], State::new(vec![0]));
+        Ok(q)
+    }
+
+    //以寄存器第一个输出信号表示的寄存器当前保存的值
+    pub fn register_value(&self, q: usize) -> u64 {
+        self.get_states().get(&q).map_or(0, |s| s.data[0])
+    }
+
+    //所有行为级节点先用本轮结束时的信号算出新状态再一起更新，返回是否有状态改变
+    pub fn clock_mut(&mut self) -> bool {
+        let signals = self.get_signals();
+        let updates = self.get_pipeline().iter()
+            .filter(|node| node.is_clocked())
+            .map(|node| {
+                let key = node.get_output()[0];
+                let clock = self.get_states().get(&key).is_some_and(|s| s.clock);
+                (key, is_high(signals[node.get_input()[0]]), node.next_state(clock, signals))
+            })
+            .collect::<Vec<(usize, bool, Option<(usize, u64)>)>>();
+        let mut changed = false;
+        for (key, clock, write) in updates {
+            let state = self.get_states_mut().entry(key).or_default();
+            state.clock = clock;
+            if let Some((index, value)) = write {
+                if state.data.len() <= index {
+                    state.data.resize(index + 1, 0);
+                }
+                changed |= state.data[index] != value;
+                state.data[index] = value;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //三个一位寄存器组成的扭环计数器，order 为寄存器加入 pipeline 的顺序，返回各寄存器的输出
+    fn johnson(order: &[usize]) -> (Circuit, Vec<usize>) {
+        let mut circuit = Circuit::new(3);
+        let d = circuit.advance_output_with_value(&[0, 0, 0]);
+        let mut q = vec![0; 3];
+        for i in order {
+            q[*i] = circuit.add_register(0, 1, 2, &[d[*i]]).unwrap()[0];
+        }
+        let nq = circuit.add_gate("not", &[q[2]]);
+        circuit.add_node(NodeType::And, vec![nq], vec![d[0]]);
+        circuit.add_node(NodeType::And, vec![q[0]], vec![d[1]]);
+        circuit.add_node(NodeType::And, vec![q[1]], vec![d[2]]);
+        (circuit, q)
+    }
+
+    #[test]
+    fn test_register() {
+        for order in [[0, 1, 2], [2, 1, 0], [1, 2, 0]] {
+            let (mut circuit, q) = johnson(&order);
+            let read = |circuit: &Circuit| read_bits(circuit.get_signals(), &q);
+            for expected in [1, 3, 7, 6, 4, 0, 1] {
+                circuit.execute_sequential_mut(&[0, 1, 0]);
+                circuit.execute_sequential_mut(&[1, 1, 0]);
+                assert_eq!(read(&circuit), expected, "order {:?}", order);
+            }
+            //时钟保持高电平或 enable 为 0 时不变，reset 在上升沿清零
+            circuit.execute_sequential_mut(&[1, 1, 0]);
+            assert_eq!(read(&circuit), 1);
+            circuit.execute_sequential_mut(&[0, 0, 0]);
+            circuit.execute_sequential_mut(&[1, 0, 0]);
+            assert_eq!(read(&circuit), 1);
+            circuit.execute_sequential_mut(&[0, 1, 1]);
+            assert_eq!(read(&circuit), 1);
+            circuit.execute_sequential_mut(&[1, 1, 1]);
+            assert_eq!(read(&circuit), 0);
+            assert_eq!(circuit.register_value(q[0]), 0);
+        }
+
+        //多位寄存器在一个时钟沿保存整个输入
+        let mut circuit = Circuit::new(7);
+        let input = circuit.get_input();
+        let q = circuit.add_register(input[0], input[1], input[2], &input[3..]).unwrap();
+        circuit.execute_sequential_mut(&[0, 1, 0, 1, 0, 1, 1]);
+        assert_eq!(read_bits(circuit.get_signals(), &q), 0);
+        circuit.execute_sequential_mut(&[1, 1, 0, 1, 0, 1, 1]);
+        assert_eq!(read_bits(circuit.get_signals(), &q), 0b1101);
+        assert_eq!(circuit.register_value(q[0]), 0b1101);
+        let v = circuit.to_verilog("top", &["clk", "en", "rst"], &[("q", q[3])]);
+        assert!(v.contains("always @(posedge clk) if (rst) r0 <= 0; else if (en) r0 <= {i6, i5, i4, i3};"));
+    }
+
+    #[test]
+    fn test_undetermined() {
+        //enable、reset 还没有被驱动时上升沿不写入
+        let mut circuit = Circuit::new(2);
+        let floating = circuit.advance_output(2);
+        let q = circuit.add_register(0, floating[0], floating[1], &[1]).unwrap();
+        circuit.execute_sequential_mut(&[0, 1]);
+        circuit.execute_sequential_mut(&[1, 1]);
+        assert_eq!(circuit.register_value(q[0]), 0);
+    }
+
+    #[test]
+    fn test_width() {
+        let mut circuit = Circuit::new(3);
+        assert!(matches!(circuit.add_register(0, 1, 2, &[]), Err(RegisterError::NoData)));
+        let d = circuit.advance_output_with_value(&[0; 65]);
+        assert!(matches!(circuit.add_register(0, 1, 2, &d), Err(RegisterError::TooWide(65))));
+        let q = circuit.add_register(0, 1, 2, &d[..64]).unwrap();
+        circuit.execute_sequential_mut(&[0, 1, 0]);
+        circuit.execute_sequential_mut(&[1, 1, 0]);
+        assert_eq!(circuit.register_value(q[0]), 0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt::Write;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::logic::Logic;
+use crate::node::NodeType;
+
+//文字采用 DIMACS 的写法：变量 v 的原文字为 v + 1，反文字为 -(v + 1)
+pub type Lit = i32;
+
+#[derive(Debug, Error)]
+pub enum SatError {
+    #[error("signal {0} depends on an uninitialized signal")]
+    Undetermined(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Cnf {
+    var_count: usize,
+    clauses: Vec<Vec<Lit>>,
+    //恒为真的变量，需要常量时才创建
+    truth: Option<Lit>,
+}
+
+impl Cnf {
+    pub fn new() -> Cnf { Self::default() }
+
+    pub fn var_count(&self) -> usize { self.var_count }
+    pub fn get_clauses(&self) -> &Vec<Vec<Lit>> { &self.clauses }
+
+    pub fn new_var(&mut self) -> Lit {
+        self.var_count += 1;
+        self.var_count as Lit
+    }
+
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        self.clauses.push(clause.to_vec());
+    }
+
+    //Tseitin 编码：v <-> and(inputs)
+    pub fn and(&mut self, inputs: &[Lit]) -> Lit {
+        let v = self.new_var();
+        let mut clause = vec![v];
+        for x in inputs {
+            self.add_clause(&[-v, *x]);
+            clause.push(-x);
+        }
+        self.add_clause(&clause);
+        v
+    }
+
+    pub fn or(&mut self, inputs: &[Lit]) -> Lit {
+        let inputs = inputs.iter().map(|x| -x).collect::<Vec<Lit>>();
+        -self.and(&inputs)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let v = self.new_var();
+        self.add_clause(&[-v, a, b]);
+        self.add_clause(&[-v, -a, -b]);
+        self.add_clause(&[v, -a, b]);
+        self.add_clause(&[v, a, -b]);
+        v
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let mut dimacs = format!("p cnf {} {}\n", self.var_count, self.clauses.len());
+        for clause in &self.clauses {
+            for lit in clause {
+                write!(dimacs, "{} ", lit).unwrap();
+            }
+            dimacs.push_str("0\n");
+        }
+        dimacs
+    }
+
+    //在子句集上附加 assumptions 后求解，返回按变量下标排列的一组解
+    pub fn solve(&self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        let mut solver = Solver::new(self.var_count);
+        for clause in &self.clauses {
+            solver.add_clause(clause);
+        }
+        for lit in assumptions {
+            solver.add_clause(&[*lit]);
+        }
+        solver.solve()
+    }
+}
+
+impl Logic for Cnf {
+    type Value = Lit;
+
+    fn constant(&mut self, value: bool) -> Lit {
+        let truth = match self.truth {
+            Some(t) => t,
+            None => {
+                let t = self.new_var();
+                self.add_clause(&[t]);
+                self.truth = Some(t);
+                t
+            }
+        };
+        if value { truth } else { -truth }
+    }
+
+    fn gate(&mut self, gate: &NodeType, inputs: &[Lit]) -> Lit {
+        match gate {
+            NodeType::Not => -inputs[0],
+            NodeType::And => self.and(inputs),
+            NodeType::Or => self.or(inputs),
+            //与 Node::execute_gate 一致，只取前两个输入
+            NodeType::Xor => self.xor(inputs[0], inputs[1]),
+            NodeType::NAnd => -self.and(inputs),
+            NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+        }
+    }
+}
+
+//带两个监视文字的 DPLL，冲突时按时间顺序回溯到最近一个还没有翻转过的决策
+struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    watches: Vec<Vec<usize>>,
+    assign: Vec<Option<bool>>,
+    trail: Vec<Lit>,
+    //(决策在 trail 中的位置, 是否已经翻转)
+    decisions: Vec<(usize, bool)>,
+    units: Vec<Lit>,
+    empty: bool,
+}
+
+impl Solver {
+    fn new(var_count: usize) -> Solver {
+        Self {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); var_count * 2],
+            assign: vec![None; var_count],
+            trail: Vec::new(),
+            decisions: Vec::new(),
+            units: Vec::new(),
+            empty: false,
+        }
+    }
+
+    fn index(lit: Lit) -> usize { (lit.unsigned_abs() as usize - 1) * 2 + (lit < 0) as usize }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.assign[lit.unsigned_abs() as usize - 1].map(|v| v == (lit > 0))
+    }
+
+    fn add_clause(&mut self, clause: &[Lit]) {
+        let mut clause = clause.to_vec();
+        clause.sort();
+        clause.dedup();
+        if clause.iter().any(|l| clause.contains(&-l)) { return; }
+        match clause.len() {
+            0 => self.empty = true,
+            1 => self.units.push(clause[0]),
+            _ => {
+                let i = self.clauses.len();
+                self.watches[Self::index(clause[0])].push(i);
+                self.watches[Self::index(clause[1])].push(i);
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    fn enqueue(&mut self, lit: Lit) -> bool {
+        match self.value(lit) {
+            Some(v) => v,
+            None => {
+                self.assign[lit.unsigned_abs() as usize - 1] = Some(lit > 0);
+                self.trail.push(lit);
+                true
+            }
+        }
+    }
+
+    //从 trail 的 head 处开始传播，出现冲突时返回 false
+    fn propagate(&mut self, mut head: usize) -> bool {
+        while head < self.trail.len() {
+            let false_lit = -self.trail[head];
+            head += 1;
+            let watching = std::mem::take(&mut self.watches[Self::index(false_lit)]);
+            let mut keep = Vec::with_capacity(watching.len());
+            let mut conflict = false;
+            for (k, &c) in watching.iter().enumerate() {
+                if conflict {
+                    keep.extend_from_slice(&watching[k..]);
+                    break;
+                }
+                let clause = &mut self.clauses[c];
+                if clause[0] == false_lit { clause.swap(0, 1); }
+                let other = clause[0];
+                if self.assign[other.unsigned_abs() as usize - 1] == Some(other > 0) {
+                    keep.push(c);
+                    continue;
+                }
+                let replacement = (2..clause.len()).find(|&j| {
+                    let l = clause[j];
+                    self.assign[l.unsigned_abs() as usize - 1] != Some(l < 0)
+                });
+                match replacement {
+                    Some(j) => {
+                        clause.swap(1, j);
+                        let new_watch = Self::index(clause[1]);
+                        self.watches[new_watch].push(c);
+                    }
+                    None => {
+                        keep.push(c);
+                        if !self.enqueue(other) { conflict = true; }
+                    }
+                }
+            }
+            self.watches[Self::index(false_lit)] = keep;
+            if conflict { return false; }
+        }
+        true
+    }
+
+    fn backtrack(&mut self, position: usize) {
+        for lit in self.trail.drain(position..) {
+            self.assign[lit.unsigned_abs() as usize - 1] = None;
+        }
+    }
+
+    fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.empty { return None; }
+        for lit in std::mem::take(&mut self.units) {
+            if !self.enqueue(lit) { return None; }
+        }
+        let mut head = 0;
+        loop {
+            if !self.propagate(head) {
+                //找到最近一个未翻转的决策，改取相反的值
+                loop {
+                    let (position, flipped) = self.decisions.pop()?;
+                    if flipped { continue; }
+                    let lit = self.trail[position];
+                    self.backtrack(position);
+                    self.decisions.push((position, true));
+                    self.enqueue(-lit);
+                    head = position;
+                    break;
+                }
+                continue;
+            }
+            head = self.trail.len();
+            match self.assign.iter().position(|v| v.is_none()) {
+                Some(var) => {
+                    self.decisions.push((self.trail.len(), false));
+                    self.enqueue(-(var as Lit + 1));
+                }
+                None => return Some(self.assign.iter().map(|v| v.unwrap()).collect()),
+            }
+        }
+    }
+}
+
+impl Circuit {
+    //对整个电路做 Tseitin 编码，返回每个信号对应的文字
+    pub fn to_cnf(&self) -> (Cnf, Vec<Option<Lit>>) {
+        let mut cnf = Cnf::new();
+        let inputs = self.get_input().iter().map(|_| cnf.new_var()).collect::<Vec<Lit>>();
+        let literals = self.evaluate(&mut cnf, &inputs);
+        (cnf, literals)
+    }
+
+    //寻找一组输入，使 targets 中的每个信号取给定的值
+    //未被驱动的信号（例如触发器的状态）取当前值，返回的输入可以直接交给 execute_sequential
+    pub fn find_input(&self, targets: &[(usize, bool)]) -> Result<Option<Vec<usize>>, SatError> {
+        let (cnf, literals) = self.to_cnf();
+        let assumptions = targets.iter()
+            .map(|(signal, value)| {
+                let lit = literals[*signal].ok_or(SatError::Undetermined(*signal))?;
+                Ok(if *value { lit } else { -lit })
+            })
+            .collect::<Result<Vec<Lit>, SatError>>()?;
+        Ok(self.solve_input(&cnf, &assumptions))
+    }
+
+    //两个信号能否取不同的值，可以时返回一组输入
+    pub fn can_differ(&self, a: usize, b: usize) -> Result<Option<Vec<usize>>, SatError> {
+        let (mut cnf, literals) = self.to_cnf();
+        let la = literals[a].ok_or(SatError::Undetermined(a))?;
+        let lb = literals[b].ok_or(SatError::Undetermined(b))?;
+        let diff = cnf.xor(la, lb);
+        Ok(self.solve_input(&cnf, &[diff]))
+    }
+
+    //to_cnf 中输入依次是前几个变量
+    fn solve_input(&self, cnf: &Cnf, assumptions: &[Lit]) -> Option<Vec<usize>> {
+        cnf.solve(assumptions).map(|model| {
+            model[..self.get_input().len()].iter().map(|v| *v as usize).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+
+    #[test]
+    fn test_solver() {
+        let mut cnf = Cnf::new();
+        let (a, b, c) = (cnf.new_var(), cnf.new_var(), cnf.new_var());
+        cnf.add_clause(&[a, b]);
+        cnf.add_clause(&[-a, c]);
+        cnf.add_clause(&[-b, c]);
+        let model = cnf.solve(&[]).unwrap();
+        assert!(model[2]);
+        assert!(cnf.solve(&[-c]).is_none());
+        assert_eq!(cnf.to_dimacs().lines().next(), Some("p cnf 3 3"));
+
+        //鸽巢问题：3 只鸽子放进 2 个笼子
+        let mut cnf = Cnf::new();
+        let p = (0..3).map(|_| (cnf.new_var(), cnf.new_var())).collect::<Vec<(Lit, Lit)>>();
+        for (x, y) in &p { cnf.add_clause(&[*x, *y]); }
+        for i in 0..3 {
+            for j in i + 1..3 {
+                cnf.add_clause(&[-p[i].0, -p[j].0]);
+                cnf.add_clause(&[-p[i].1, -p[j].1]);
+            }
+        }
+        assert!(cnf.solve(&[]).is_none());
+    }
+
+    #[test]
+    fn test_queries() {
+        let expression = Expression::parse("s = a ^ b ^ c0; c1 = (a & b) | (c0 & (a ^ b)); z = a & !a").unwrap();
+        let (circuit, outputs) = expression.build_circuit();
+        let input = circuit.find_input(&[(outputs[0], false), (outputs[1], true)]).unwrap().unwrap();
+        assert_eq!(input.iter().sum::<usize>(), 2);
+        let res = circuit.execute_sequential(&input);
+        assert!(!res[outputs[0]].get_unchecked() && res[outputs[1]].get_unchecked());
+
+        assert_eq!(circuit.find_input(&[(outputs[2], true)]).unwrap(), None);
+        let input = circuit.can_differ(outputs[0], outputs[1]).unwrap().unwrap();
+        let res = circuit.execute_sequential(&input);
+        assert_ne!(res[outputs[0]], res[outputs[1]]);
+    }
+
+    #[test]
+    fn test_state() {
+        let mut circuit = Circuit::new_with_basic_pattern(3);
+        let input = circuit.get_input();
+        let output = circuit.apply_flipflop("rsff", &input).get_output().to_vec();
+        //初始状态为 nq = 0, q = 1，只有 r = 0, cp = 1, s = 1 能让 q 翻转
+        let input = circuit.find_input(&[(output[1], false)]).unwrap().unwrap();
+        assert_eq!(input, vec![0, 1, 1]);
+        circuit.execute_sequential_mut(&input);
+        assert!(!circuit.get_signal(output[1]).get_unchecked());
+    }
+}
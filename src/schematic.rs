@@ -0,0 +1,626 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PniType};
+
+//引脚间距、元件内边距、同一列元件间距、走线通道中竖线的间距、页边距
+const PIN: f32 = 16.0;
+const PAD: f32 = 4.0;
+const GAP: f32 = 16.0;
+const TRACK: f32 = 8.0;
+const MARGIN: f32 = 20.0;
+//pattern 方框顶部留给名字的高度、取反小圆圈的直径、字符宽度
+const TITLE: f32 = 16.0;
+const BUBBLE: f32 = 6.0;
+const CHAR: f32 = 7.0;
+//交叉最小化来回扫描的次数
+const SWEEPS: usize = 4;
+
+//(源元件, 输出引脚) -> (目标元件, 输入引脚)
+pub type Edge = ((usize, usize), (usize, usize));
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    Input(String),
+    Output(String),
+    Gate(NodeType),
+    Pattern { name: String, inputs: Vec<String>, outputs: Vec<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    kind: BlockKind,
+    rect: (f32, f32, f32, f32),
+    inputs: Vec<(f32, f32)>,
+    outputs: Vec<(f32, f32)>,
+}
+
+impl Block {
+    pub(crate) fn new(kind: BlockKind, input_size: usize, output_size: usize) -> Block {
+        let rows = input_size.max(output_size).max(1) as f32;
+        let (width, height) = match &kind {
+            BlockKind::Input(name) | BlockKind::Output(name) => (name.len() as f32 * CHAR + 12.0, PIN),
+            BlockKind::Gate(gate) => {
+                let height = rows * PIN + 2.0 * PAD;
+                let bubble = if matches!(gate, NodeType::Not | NodeType::NAnd) { BUBBLE } else { 0.0 };
+                (20.0 + height / 2.0 + bubble, height)
+            }
+            BlockKind::Pattern { name, inputs, outputs } => {
+                let longest = |names: &[String]| names.iter().map(|n| n.len()).max().unwrap_or(0) as f32;
+                let ports = (longest(inputs) + longest(outputs)) * CHAR + 24.0;
+                (ports.max(name.len() as f32 * CHAR + 16.0).max(60.0), rows * PIN + 2.0 * PAD + TITLE)
+            }
+        };
+        let mut block = Self { kind, rect: (0.0, 0.0, width, height), inputs: Vec::new(), outputs: Vec::new() };
+        block.place(0.0, 0.0, input_size, output_size);
+        block
+    }
+
+    pub(crate) fn place(&mut self, x: f32, y: f32, input_size: usize, output_size: usize) {
+        let (_, _, w, h) = self.rect;
+        self.rect = (x, y, w, h);
+        let top = if matches!(self.kind, BlockKind::Pattern { .. }) { TITLE } else { 0.0 };
+        let pin = |i: usize, n: usize| match n {
+            1 if top == 0.0 => y + h / 2.0,
+            _ => y + top + PAD + PIN * (i as f32 + 0.5) + (h - top - 2.0 * PAD - PIN * n as f32) / 2.0,
+        };
+        self.inputs = (0..input_size).map(|i| (x, pin(i, input_size))).collect();
+        self.outputs = (0..output_size).map(|i| (x + w, pin(i, output_size))).collect();
+    }
+
+    pub fn get_kind(&self) -> &BlockKind { &self.kind }
+    //(x, y, 宽, 高)
+    pub fn get_rect(&self) -> (f32, f32, f32, f32) { self.rect }
+    pub fn input_pin(&self, index: usize) -> (f32, f32) { self.inputs[index] }
+    pub fn output_pin(&self, index: usize) -> (f32, f32) { self.outputs[index] }
+    pub fn input_size(&self) -> usize { self.inputs.len() }
+    pub fn output_size(&self) -> usize { self.outputs.len() }
+}
+
+#[derive(Debug, Clone)]
+pub struct Wire {
+    //驱动这条线的元件及其输出引脚
+    source: (usize, usize),
+    points: Vec<(f32, f32)>,
+}
+
+impl Wire {
+    pub fn get_source(&self) -> (usize, usize) { self.source }
+    pub fn get_points(&self) -> &Vec<(f32, f32)> { &self.points }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Schematic {
+    blocks: Vec<Block>,
+    wires: Vec<Wire>,
+    junctions: Vec<(f32, f32)>,
+    edges: Vec<Edge>,
+    size: (f32, f32),
+}
+
+//列中的一项：元件，或者长连线经过中间列时占位的虚拟点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Item {
+    Block(usize),
+    Dummy(usize, usize),
+}
+
+//布局前的电路图：元件及其间的连线
+#[derive(Default)]
+struct Graph {
+    blocks: Vec<Block>,
+    pins: Vec<(usize, usize)>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn add(&mut self, kind: BlockKind, input_size: usize, output_size: usize) -> usize {
+        self.blocks.push(Block::new(kind, input_size, output_size));
+        self.pins.push((input_size, output_size));
+        self.blocks.len() - 1
+    }
+
+    //分层：输入在第 0 列，其它元件比已经分好层的前驱多一层，输出放在最后一列
+    fn levels(&self) -> Vec<usize> {
+        let mut level = self.blocks.iter()
+            .map(|b| matches!(b.kind, BlockKind::Input(_)).then_some(0))
+            .collect::<Vec<Option<usize>>>();
+        let mut preds = vec![Vec::new(); self.blocks.len()];
+        for ((from, _), (to, _)) in &self.edges {
+            preds[*to].push(*from);
+        }
+        for b in 0..self.blocks.len() {
+            if level[b].is_none() && !matches!(self.blocks[b].kind, BlockKind::Output(_)) {
+                level[b] = Some(preds[b].iter().filter_map(|p| level[*p]).max().map_or(1, |l| l + 1));
+            }
+        }
+        let last = level.iter().flatten().max().copied().unwrap_or(0) + 1;
+        level.into_iter().map(|l| l.unwrap_or(last)).collect()
+    }
+
+    fn layout(mut self) -> Schematic {
+        let level = self.levels();
+        let columns = level.iter().max().map_or(1, |l| l + 1);
+
+        //源引脚相同的连线属于同一个网络，长连线按网络共享虚拟点
+        let mut nets = Vec::new();
+        let mut net_index = HashMap::new();
+        let mut items = Vec::new();
+        let mut column = Vec::new();
+        let mut dummies = HashMap::new();
+        for (b, l) in level.iter().enumerate() {
+            items.push(Item::Block(b));
+            column.push(*l);
+        }
+        //(源项, 源引脚, 目标项, 目标引脚, 网络)
+        let mut segments = Vec::new();
+        let mut feedback = Vec::new();
+        for (from, to) in &self.edges {
+            let net = *net_index.entry(*from).or_insert_with(|| {
+                nets.push(*from);
+                nets.len() - 1
+            });
+            let (source, target) = (level[from.0], level[to.0]);
+            if target <= source {
+                feedback.push((*from, *to, net));
+                continue;
+            }
+            let mut previous = (from.0, from.1);
+            for c in source + 1..target {
+                let id = *dummies.entry((net, c)).or_insert_with(|| {
+                    items.push(Item::Dummy(net, c));
+                    column.push(c);
+                    items.len() - 1
+                });
+                segments.push((previous.0, previous.1, id, 0, net));
+                previous = (id, 0);
+            }
+            segments.push((previous.0, previous.1, to.0, to.1, net));
+        }
+        let segments = segments.into_iter().collect::<HashSet<_>>().into_iter().collect::<Vec<_>>();
+
+        //重心法减少交叉：按相邻列中相连项的平均位置排序，向右、向左交替扫描
+        let mut order = vec![Vec::new(); columns];
+        for (i, c) in column.iter().enumerate() {
+            order[*c].push(i);
+        }
+        let mut pos = vec![0.0; items.len()];
+        let renumber = |order: &[usize], pos: &mut Vec<f32>| order.iter().enumerate().for_each(|(p, i)| pos[*i] = p as f32);
+        order.iter().for_each(|o| renumber(o, &mut pos));
+        let mut preds = vec![Vec::new(); items.len()];
+        let mut succs = vec![Vec::new(); items.len()];
+        for (from, _, to, _, _) in &segments {
+            preds[*to].push(*from);
+            succs[*from].push(*to);
+        }
+        let reorder = |order: &mut Vec<usize>, pos: &mut Vec<f32>, neighbours: &[Vec<usize>]| {
+            let key = order.iter()
+                .map(|i| match neighbours[*i].len() {
+                    0 => pos[*i],
+                    n => neighbours[*i].iter().map(|j| pos[*j]).sum::<f32>() / n as f32,
+                })
+                .collect::<Vec<f32>>();
+            let mut sorted = (0..order.len()).collect::<Vec<usize>>();
+            sorted.sort_by(|a, b| key[*a].total_cmp(&key[*b]));
+            *order = sorted.into_iter().map(|k| order[k]).collect();
+            renumber(order, pos);
+        };
+        for _ in 0..SWEEPS {
+            for o in order.iter_mut().skip(1) {
+                reorder(o, &mut pos, &preds);
+            }
+            for o in order.iter_mut().rev().skip(1) {
+                reorder(o, &mut pos, &succs);
+            }
+        }
+
+        //每个走线通道中，一个网络占一条竖线
+        let mut tracks = vec![Vec::new(); columns.saturating_sub(1)];
+        for (from, _, _, _, net) in &segments {
+            tracks[column[*from]].push(*net);
+        }
+        let mut lanes = Vec::new();
+        for ((from, _), (to, _), net) in &feedback {
+            tracks[level[*from]].push(*net);
+            tracks[level[*to] - 1].push(*net);
+            if !lanes.contains(net) { lanes.push(*net); }
+        }
+        for t in tracks.iter_mut() {
+            t.sort();
+            t.dedup();
+        }
+
+        //纵向：反馈线在最上方，每一列的项依次排列并居中
+        let height_of = |i: usize| match items[i] {
+            Item::Block(b) => self.blocks[b].rect.3,
+            Item::Dummy(..) => PIN,
+        };
+        let top = MARGIN + lanes.len() as f32 * TRACK + if lanes.is_empty() { 0.0 } else { TRACK };
+        let column_height = order.iter()
+            .map(|o| o.iter().map(|i| height_of(*i) + GAP).sum::<f32>() - GAP)
+            .collect::<Vec<f32>>();
+        let content = column_height.iter().copied().fold(0.0, f32::max);
+        let mut y = vec![0.0; items.len()];
+        for (c, o) in order.iter().enumerate() {
+            let mut cursor = top + (content - column_height[c]) / 2.0;
+            for i in o {
+                y[*i] = cursor;
+                cursor += height_of(*i) + GAP;
+            }
+        }
+
+        //横向：列宽取最宽的元件，通道宽度由竖线数量决定
+        let column_width = order.iter()
+            .map(|o| o.iter().filter_map(|i| match items[*i] {
+                Item::Block(b) => Some(self.blocks[b].rect.2),
+                Item::Dummy(..) => None,
+            }).fold(20.0, f32::max))
+            .collect::<Vec<f32>>();
+        let mut column_x = Vec::new();
+        let mut track_x = HashMap::new();
+        let mut cursor = MARGIN;
+        for c in 0..columns {
+            column_x.push(cursor);
+            cursor += column_width[c];
+            if c + 1 < columns {
+                for (k, net) in tracks[c].iter().enumerate() {
+                    track_x.insert((c, *net), cursor + TRACK * (k + 1) as f32);
+                }
+                cursor += TRACK * (tracks[c].len() + 1) as f32 + TRACK;
+            }
+        }
+        let size = (cursor + MARGIN, top + content + MARGIN);
+
+        for (i, item) in items.iter().enumerate() {
+            if let Item::Block(b) = item {
+                let c = column[i];
+                let (_, _, w, _) = self.blocks[*b].rect;
+                let x = match self.blocks[*b].kind {
+                    BlockKind::Input(_) => column_x[c] + column_width[c] - w,
+                    BlockKind::Output(_) => column_x[c],
+                    _ => column_x[c] + (column_width[c] - w) / 2.0,
+                };
+                let (inputs, outputs) = self.pins[*b];
+                self.blocks[*b].place(x, y[i], inputs, outputs);
+            }
+        }
+
+        //连线：源引脚 -> 通道中的竖线 -> 目标引脚，虚拟点处水平穿过整列
+        let mut wires = Vec::new();
+        //每个 (通道, 网络) 的竖线上各端点的 y 及其方向（true 为向左连接）
+        let mut ends: HashMap<(usize, usize), Vec<(f32, bool)>> = HashMap::new();
+        let out_point = |i: usize, pin: usize| match items[i] {
+            Item::Block(b) => self.blocks[b].outputs[pin],
+            Item::Dummy(_, c) => (column_x[c] + column_width[c], y[i] + PIN / 2.0),
+        };
+        let in_point = |i: usize, pin: usize| match items[i] {
+            Item::Block(b) => self.blocks[b].inputs[pin],
+            Item::Dummy(_, c) => (column_x[c], y[i] + PIN / 2.0),
+        };
+        for (i, item) in items.iter().enumerate() {
+            if let Item::Dummy(net, _) = item {
+                let (a, b) = (in_point(i, 0), out_point(i, 0));
+                wires.push(Wire { source: nets[*net], points: vec![a, b] });
+            }
+        }
+        for (from, pin, to, to_pin, net) in &segments {
+            let c = column[*from];
+            let (a, b) = (out_point(*from, *pin), in_point(*to, *to_pin));
+            let x = track_x[&(c, *net)];
+            wires.push(Wire { source: nets[*net], points: simplify(vec![a, (x, a.1), (x, b.1), b]) });
+            ends.entry((c, *net)).or_default().extend([(a.1, true), (b.1, false)]);
+        }
+        for (from, to, net) in &feedback {
+            let lane = MARGIN + lanes.iter().position(|l| l == net).unwrap() as f32 * TRACK;
+            let (a, b) = (self.blocks[from.0].outputs[from.1], self.blocks[to.0].inputs[to.1]);
+            let (c0, c1) = (level[from.0], level[to.0] - 1);
+            let (x0, x1) = (track_x[&(c0, *net)], track_x[&(c1, *net)]);
+            wires.push(Wire {
+                source: nets[*net],
+                points: simplify(vec![a, (x0, a.1), (x0, lane), (x1, lane), (x1, b.1), b]),
+            });
+            ends.entry((c0, *net)).or_default().extend([(a.1, true), (lane, false)]);
+            ends.entry((c1, *net)).or_default().extend([(lane, true), (b.1, false)]);
+        }
+
+        //同一条竖线上向三个以上方向延伸的点画出连接点
+        let mut junctions = Vec::new();
+        for ((c, net), ends) in &ends {
+            let x = track_x[&(*c, *net)];
+            let low = ends.iter().map(|e| e.0).fold(f32::INFINITY, f32::min);
+            let high = ends.iter().map(|e| e.0).fold(f32::NEG_INFINITY, f32::max);
+            let mut seen = Vec::new();
+            for (y, _) in ends {
+                if seen.contains(y) { continue; }
+                seen.push(*y);
+                let left = ends.iter().any(|(e, l)| e == y && *l);
+                let right = ends.iter().any(|(e, l)| e == y && !*l);
+                let count = [left, right, *y > low, *y < high].iter().filter(|d| **d).count();
+                if count >= 3 { junctions.push((x, *y)); }
+            }
+        }
+        junctions.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+        Schematic { blocks: self.blocks, wires, junctions, edges: self.edges, size }
+    }
+}
+
+//去掉重复的点和直线中间的点
+fn simplify(points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    let mut result: Vec<(f32, f32)> = Vec::new();
+    for p in points {
+        if result.last() == Some(&p) { continue; }
+        if let [.., a, b] = result[..] && ((a.0 == b.0 && b.0 == p.0) || (a.1 == b.1 && b.1 == p.1)) {
+            result.pop();
+        }
+        result.push(p);
+    }
+    result
+}
+
+fn points(points: &[(f32, f32)]) -> String {
+    points.iter().map(|(x, y)| format!("{},{}", x, y)).collect::<Vec<String>>().join(" ")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+//ANSI 门符号，body 为不含取反圆圈的部分
+fn write_gate(svg: &mut String, gate: &NodeType, (x, y, w, h): (f32, f32, f32, f32)) {
+    let bubble = matches!(gate, NodeType::Not | NodeType::NAnd);
+    let body = if bubble { w - BUBBLE } else { w };
+    let (mid, bottom) = (y + h / 2.0, y + h);
+    let path = match gate {
+        NodeType::And | NodeType::NAnd => {
+            let r = h / 2.0;
+            format!("M{x},{y} H{} A{r},{r} 0 0 1 {},{bottom} H{x} Z", x + body - r, x + body - r)
+        }
+        NodeType::Or => format!(
+            "M{x},{y} Q{},{y} {},{mid} Q{},{bottom} {x},{bottom} Q{},{mid} {x},{y} Z",
+            x + body * 0.6, x + body, x + body * 0.6, x + body * 0.25),
+        NodeType::Xor => {
+            let s = x + 5.0;
+            format!(
+                "M{s},{y} Q{},{y} {},{mid} Q{},{bottom} {s},{bottom} Q{},{mid} {s},{y} Z M{x},{bottom} Q{},{mid} {x},{y}",
+                s + (body - 5.0) * 0.6, x + body, s + (body - 5.0) * 0.6, s + (body - 5.0) * 0.25, x + body * 0.25)
+        }
+        NodeType::Not => format!("M{x},{y} L{},{mid} L{x},{bottom} Z", x + body),
+        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+    };
+    writeln!(svg, "  <path class=\"gate\" d=\"{}\"/>", path).unwrap();
+    if bubble {
+        writeln!(svg, "  <circle class=\"gate\" cx=\"{}\" cy=\"{}\" r=\"{}\"/>", x + body + BUBBLE / 2.0, mid, BUBBLE / 2.0).unwrap();
+    }
+}
+
+impl Schematic {
+    pub fn get_blocks(&self) -> &Vec<Block> { &self.blocks }
+    pub fn get_wires(&self) -> &Vec<Wire> { &self.wires }
+    pub fn get_junctions(&self) -> &Vec<(f32, f32)> { &self.junctions }
+    pub fn get_edges(&self) -> &Vec<Edge> { &self.edges }
+    pub fn size(&self) -> (f32, f32) { self.size }
+
+    pub fn to_svg(&self) -> String {
+        let (width, height) = self.size;
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">").unwrap();
+        svg.push_str("  <style>.gate{fill:white;stroke:black;stroke-width:1.5} .wire{fill:none;stroke:black} \
+            text{font-family:monospace;font-size:12px}</style>\n");
+        for wire in &self.wires {
+            writeln!(svg, "  <polyline class=\"wire\" points=\"{}\"/>", points(&wire.points)).unwrap();
+        }
+        for (x, y) in &self.junctions {
+            writeln!(svg, "  <circle cx=\"{}\" cy=\"{}\" r=\"2.5\"/>", x, y).unwrap();
+        }
+        for block in &self.blocks {
+            let (x, y, w, h) = block.rect;
+            match &block.kind {
+                BlockKind::Input(name) => {
+                    writeln!(svg, "  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>", x + w - 4.0, y + h / 2.0 + 4.0, escape(name)).unwrap();
+                }
+                BlockKind::Output(name) => {
+                    writeln!(svg, "  <text x=\"{}\" y=\"{}\">{}</text>", x + 4.0, y + h / 2.0 + 4.0, escape(name)).unwrap();
+                }
+                BlockKind::Gate(gate) => write_gate(&mut svg, gate, block.rect),
+                BlockKind::Pattern { name, inputs, outputs } => {
+                    writeln!(svg, "  <rect class=\"gate\" x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\"/>").unwrap();
+                    writeln!(svg, "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x + w / 2.0, y + 12.0, escape(name)).unwrap();
+                    for (port, (px, py)) in inputs.iter().zip(&block.inputs) {
+                        writeln!(svg, "  <text x=\"{}\" y=\"{}\">{}</text>", px + 3.0, py + 4.0, escape(port)).unwrap();
+                    }
+                    for (port, (px, py)) in outputs.iter().zip(&block.outputs) {
+                        writeln!(svg, "  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>", px - 3.0, py + 4.0, escape(port)).unwrap();
+                    }
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn pattern_block(circuit: &Circuit, name: &str) -> (BlockKind, usize, usize) {
+    let pattern = circuit.get_pattern(name);
+    let inputs = (0..pattern.input_size()).map(|i| pattern.input_port(i)).collect();
+    let outputs = (0..pattern.output_size()).map(|i| pattern.output_port(i)).collect();
+    let kind = BlockKind::Pattern { name: name.to_string(), inputs, outputs };
+    (kind, pattern.input_size(), pattern.output_size())
+}
+
+//寄存器、存储器和 pattern 一样画成带端口名的方框
+fn behaviour_block(node_type: &NodeType) -> (BlockKind, usize, usize) {
+    let ports = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let (name, inputs, width) = match node_type {
+        NodeType::Register(width) => ("register", [ports(&["clock", "enable", "reset"]), Pattern::port_names("d", *width)].concat(), *width),
+        NodeType::Rom(address, width) => ("rom", Pattern::port_names("a", *address), *width),
+        NodeType::Ram(address, width) => {
+            let inputs = [ports(&["clock", "we"]), Pattern::port_names("a", *address), Pattern::port_names("d", *width)].concat();
+            ("ram", inputs, *width)
+        }
+        _ => unreachable!(),
+    };
+    let input_size = inputs.len();
+    let kind = BlockKind::Pattern { name: name.to_string(), inputs, outputs: Pattern::port_names("q", width) };
+    (kind, input_size, width)
+}
+
+impl Pattern {
+    //内部用到的其它 pattern 画成方框
+    pub fn schematic(&self, circuit: &Circuit) -> Schematic {
+        let mut graph = Graph::default();
+        for i in 0..self.input_size() {
+            graph.add(BlockKind::Input(self.input_port(i)), 0, 1);
+        }
+        let first = self.input_size();
+        for node in self.get_pattern() {
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let (kind, input_size, output_size) = pattern_block(circuit, name);
+                    graph.add(kind, input_size, output_size);
+                }
+                gate => { graph.add(BlockKind::Gate(gate.clone()), node.get_input().len(), 1); }
+            }
+        }
+        let source = |input: &PniType| match input {
+            PniType::Input(j) => (*j, 0),
+            PniType::NodeOutput((n, k)) => (first + n, *k),
+        };
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            for (j, input) in node.get_input().iter().enumerate() {
+                graph.edges.push((source(input), (first + i, j)));
+            }
+        }
+        for (k, output) in self.get_output().iter().enumerate() {
+            let b = graph.add(BlockKind::Output(self.output_port(k)), 1, 0);
+            graph.edges.push((source(&PniType::NodeOutput(*output)), (b, 0)));
+        }
+        graph.layout()
+    }
+
+    pub fn to_svg(&self, circuit: &Circuit) -> String {
+        self.schematic(circuit).to_svg()
+    }
+}
+
+impl Circuit {
+    //参数与 to_verilog 相同，pattern 节点画成带端口名的方框，没有驱动的信号画成常量
+    pub fn schematic(&self, input_name: &[&str], outputs: &[(&str, usize)]) -> Schematic {
+        let input_size = self.get_input().len();
+        let mut graph = Graph::default();
+        for i in 0..input_size {
+            let name = input_name.get(i).map(|s| s.to_string()).unwrap_or_else(|| format!("i{}", i));
+            graph.add(BlockKind::Input(name), 0, 1);
+        }
+        for node in self.get_pipeline() {
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let (kind, input_size, output_size) = pattern_block(self, name);
+                    graph.add(kind, input_size, output_size);
+                }
+                NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => {
+                    let (kind, input_size, output_size) = behaviour_block(node.get_type());
+                    graph.add(kind, input_size, output_size);
+                }
+                gate => { graph.add(BlockKind::Gate(gate.clone()), node.get_input().len(), 1); }
+            }
+        }
+
+        let mut constants = HashMap::new();
+        let mut source = |graph: &mut Graph, s: usize| match self.get_parent_node_index(s) {
+            _ if s < input_size => (s, 0),
+            Some(p) => (input_size + p, self.get_node(p).get_output().iter().position(|o| *o == s).unwrap()),
+            None => {
+                let signal = self.get_signal(s);
+                let label = if signal.is_valid() { (signal.get_unchecked() as usize).to_string() } else { "?".to_string() };
+                (*constants.entry(s).or_insert_with(|| graph.add(BlockKind::Input(label), 0, 1)), 0)
+            }
+        };
+        for (k, node) in self.get_pipeline().iter().enumerate() {
+            for (j, s) in node.get_input().iter().enumerate() {
+                let from = source(&mut graph, *s);
+                graph.edges.push((from, (input_size + k, j)));
+            }
+        }
+        for (name, s) in outputs {
+            let from = source(&mut graph, *s);
+            let b = graph.add(BlockKind::Output(name.to_string()), 1, 0);
+            graph.edges.push((from, (b, 0)));
+        }
+        graph.layout()
+    }
+
+    pub fn to_svg(&self, input_name: &[&str], outputs: &[(&str, usize)]) -> String {
+        self.schematic(input_name, outputs).to_svg()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //同一列中的元件互不重叠，每条线都是水平或竖直的线段，并且连到引脚上
+    fn check(schematic: &Schematic) {
+        let blocks = schematic.get_blocks();
+        for (i, a) in blocks.iter().enumerate() {
+            let (ax, ay, aw, ah) = a.get_rect();
+            for b in &blocks[i + 1..] {
+                let (bx, by, bw, bh) = b.get_rect();
+                assert!(ax + aw <= bx || bx + bw <= ax || ay + ah <= by || by + bh <= ay, "{:?} overlaps {:?}", a, b);
+            }
+        }
+        let (width, height) = schematic.size();
+        for wire in schematic.get_wires() {
+            let points = wire.get_points();
+            for pair in points.windows(2) {
+                assert!(pair[0].0 == pair[1].0 || pair[0].1 == pair[1].1);
+            }
+            assert!(points.iter().all(|(x, y)| *x >= 0.0 && *x <= width && *y >= 0.0 && *y <= height));
+        }
+        for (b, block) in blocks.iter().enumerate() {
+            for pin in &block.inputs {
+                assert!(schematic.get_wires().iter().any(|w| w.get_points().last() == Some(pin)), "block {} not driven", b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_adder() {
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        let schematic = circuit.get_pattern("full_adder").schematic(&circuit);
+        check(&schematic);
+        //输入、两层门、输出
+        let columns = schematic.get_blocks().iter()
+            .map(|b| b.get_rect().0 as i32)
+            .collect::<HashSet<i32>>();
+        assert!(columns.len() >= 4);
+        let svg = schematic.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("<path class=\"gate\"").count(), 5);
+        assert!(svg.contains(">c0</text>"));
+        assert!(!schematic.get_junctions().is_empty());
+    }
+
+    #[test]
+    fn test_feedback() {
+        let mut circuit = Circuit::new_with_basic_pattern(3);
+        Pattern::build_jkff(&mut circuit);
+        let schematic = circuit.get_pattern("jkff").schematic(&circuit);
+        check(&schematic);
+        //反馈线走在所有元件上方
+        let top = schematic.get_blocks().iter().map(|b| b.get_rect().1).fold(f32::INFINITY, f32::min);
+        assert!(schematic.get_wires().iter().any(|w| w.get_points().iter().any(|p| p.1 < top)));
+        assert_eq!(schematic.to_svg().matches("<circle class=\"gate\"").count(), 4);
+
+        let input = circuit.get_input();
+        let q = circuit.apply_flipflop("jkff", &input).get_output().to_vec();
+        let n = circuit.add_gate("not", &[q[1]]);
+        let schematic = circuit.schematic(&["k", "cp", "j"], &[("q", q[1]), ("n", n)]);
+        check(&schematic);
+        let svg = schematic.to_svg();
+        assert!(svg.contains(">jkff</text>"));
+        assert!(svg.contains("<rect class=\"gate\""));
+    }
+}
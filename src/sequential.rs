@@ -1,7 +1,63 @@
 use crate::circuit::Circuit;
+use crate::generator::build;
+use crate::node::NodeType;
 use crate::pattern::{add_node, Pattern, PniType};
 use crate::pattern::PniType::NodeOutput;
 
+//width 位主从结构的上升沿触发器：cp 为 0 时主锁存器跟随 next 由 q 算出的输入，cp 为 1 时从锁存器跟随主锁存器。
+//按顺序执行时主锁存器先算，上升沿那一轮主锁存器已经保持，从锁存器拿到的是沿之前的输入。
+//force 为高电平有效的异步置位、清零，同时有效时清零优先。
+//主锁存器只被自身读取，转换成 pattern 后是内部状态（Pattern::get_state），不作为输出，返回 q
+pub(crate) fn edge_bits(
+    circuit: &mut Circuit,
+    cp: usize,
+    force: Option<(usize, usize)>,
+    width: usize,
+    next: impl FnOnce(&mut Circuit, &[usize]) -> Vec<usize>,
+) -> Vec<usize> {
+    let m = circuit.advance_output_with_value(&vec![0; width]);
+    let q = circuit.advance_output_with_value(&vec![0; width]);
+    let ncp = circuit.add_gate("not", &[cp]);
+    let force = force.map(|(pr, clr)| (pr, circuit.add_gate("not", &[clr])));
+    let d = next(circuit, &q);
+    let mut latch = |circuit: &mut Circuit, load: usize, hold: usize, output: usize| {
+        match force {
+            Some((pr, nclr)) => {
+                let value = circuit.add_gate("or", &[load, hold, pr]);
+                circuit.add_node(NodeType::And, vec![value, nclr], vec![output]);
+            }
+            None => { circuit.add_node(NodeType::Or, vec![load, hold], vec![output]); }
+        }
+    };
+    for i in 0..width {
+        let load = circuit.add_gate("and", &[d[i], ncp]);
+        let hold = circuit.add_gate("and", &[m[i], cp]);
+        latch(circuit, load, hold, m[i]);
+    }
+    for i in 0..width {
+        let load = circuit.add_gate("and", &[m[i], cp]);
+        let hold = circuit.add_gate("and", &[q[i], ncp]);
+        latch(circuit, load, hold, q[i]);
+    }
+    q
+}
+
+//一位带异步置位、清零的触发器，输入依次为 pr, clr, cp，返回 nq, q
+fn master_slave(
+    circuit: &mut Circuit,
+    inputs: &[usize],
+    next: impl FnOnce(&mut Circuit, usize) -> usize,
+) -> Vec<(String, usize)> {
+    let (pr, clr, cp) = (inputs[0], inputs[1], inputs[2]);
+    let q = edge_bits(circuit, cp, Some((pr, clr)), 1, |circuit, q| vec![next(circuit, q[0])]);
+    let nq = circuit.add_gate("not", &[q[0]]);
+    vec![("nq".to_string(), nq), ("q".to_string(), q[0])]
+}
+
+fn edge_input_name(names: &[&str]) -> Vec<String> {
+    ["pr", "clr", "cp"].iter().chain(names).map(|s| s.to_string()).collect()
+}
+
 impl Pattern {
     pub fn build_jkff(circuit: &mut Circuit) {
         let mut pattern = Vec::new();
@@ -10,26 +66,26 @@ impl Pattern {
         let n0 = add_node(
             &mut pattern,
             "nand",
-            vec![k, cp, NodeOutput(q)]
+            vec![k, cp, NodeOutput((q, 0))]
         );
         let n1 = add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(nq), cp, j]
+            vec![NodeOutput((nq, 0)), cp, j]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n0), NodeOutput(q)]
+            vec![NodeOutput((n0, 0)), NodeOutput((q, 0))]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n1), NodeOutput(nq)]
+            vec![NodeOutput((n1, 0)), NodeOutput((nq, 0))]
         );
         let mut pattern = Pattern::new(
             3,
-            vec![nq, q],
+            vec![(nq, 0), (q, 0)],
             pattern
         );
         pattern.set_name(&["k", "cp", "j"], &["nq", "q"]);
@@ -44,26 +100,26 @@ impl Pattern {
         let n0 = add_node(
             &mut pattern,
             "nand",
-            vec![t, cp, NodeOutput(q)]
+            vec![t, cp, NodeOutput((q, 0))]
         );
         let n1 = add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(nq), cp, t]
+            vec![NodeOutput((nq, 0)), cp, t]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n0), NodeOutput(q)]
+            vec![NodeOutput((n0, 0)), NodeOutput((q, 0))]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n1), NodeOutput(nq)]
+            vec![NodeOutput((n1, 0)), NodeOutput((nq, 0))]
         );
         let mut pattern = Pattern::new(
             2,
-            vec![nq, q],
+            vec![(nq, 0), (q, 0)],
             pattern
         );
         pattern.set_name(&["cp", "t"], &["nq", "q"]);
@@ -83,21 +139,21 @@ impl Pattern {
         let n1 = add_node(
             &mut pattern,
             "nand",
-            vec![cp, NodeOutput(n0)]
+            vec![cp, NodeOutput((n0, 0))]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n0), NodeOutput(q)]
+            vec![NodeOutput((n0, 0)), NodeOutput((q, 0))]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![NodeOutput(n1), NodeOutput(nq)]
+            vec![NodeOutput((n1, 0)), NodeOutput((nq, 0))]
         );
         let mut pattern = Pattern::new( 
             2,
-            vec![nq, q],
+            vec![(nq, 0), (q, 0)],
             pattern
         );
         pattern.set_name(&["cp", "d"], &["nq", "q"]);
@@ -123,16 +179,16 @@ impl Pattern {
         add_node(
             &mut pattern,
             "nand",
-            vec![PniType::NodeOutput(n1), PniType::NodeOutput(q)]
+            vec![PniType::NodeOutput((n1, 0)), PniType::NodeOutput((q, 0))]
         );
         add_node(
             &mut pattern,
             "nand",
-            vec![PniType::NodeOutput(nq), PniType::NodeOutput(n0)]
+            vec![PniType::NodeOutput((nq, 0)), PniType::NodeOutput((n0, 0))]
         );
         let mut pattern = Pattern::new(
             3,
-            vec![nq, q],
+            vec![(nq, 0), (q, 0)],
             pattern
         );
         pattern.set_name(
@@ -147,17 +203,160 @@ impl Pattern {
         let nq = add_node(
             &mut pattern,
             "nand",
-            vec![PniType::Input(0), PniType::NodeOutput(1)]);
+            vec![PniType::Input(0), PniType::NodeOutput((1, 0))]);
         let q = add_node(
             &mut pattern,
             "nand",
-            vec![PniType::Input(1), PniType::NodeOutput(0)]);
+            vec![PniType::Input(1), PniType::NodeOutput((0, 0))]);
         let mut pattern = Pattern::new(
             2,
-            vec![nq, q],
+            vec![(nq, 0), (q, 0)],
             pattern,
         );
         pattern.set_name(&["rd", "sd"], &["nq", "q"]);
         circuit.get_patterns_mut().insert("brsff".to_string(), pattern);
     }
+
+    //数据选择器形式的 D 锁存器，只有 q 一条反馈，按顺序执行一次即可得到正确结果
+    pub fn build_dlatch(circuit: &mut Circuit) {
+        let mut pattern = Vec::new();
+        let (cp, d) = (PniType::Input(0), PniType::Input(1));
+        let q = 3;
+        let n0 = add_node(
+            &mut pattern,
+            "not",
+            vec![cp]
+        );
+        let n1 = add_node(
+            &mut pattern,
+            "and",
+            vec![d, cp]
+        );
+        let n2 = add_node(
+            &mut pattern,
+            "and",
+            vec![NodeOutput((q, 0)), NodeOutput((n0, 0))]
+        );
+        add_node(
+            &mut pattern,
+            "or",
+            vec![NodeOutput((n1, 0)), NodeOutput((n2, 0))]
+        );
+        let mut pattern = Pattern::new(
+            2,
+            vec![(q, 0)],
+            pattern
+        );
+        pattern.set_name(&["cp", "d"], &["q"]);
+        pattern.set_description("Qn+1 = D when CP else Qn");
+        circuit.get_patterns_mut().insert("dlatch".to_string(), pattern);
+    }
+
+    //上升沿触发的 D 触发器 edff，实例的输出 nq, q 要有初始值
+    pub fn build_edge_dff(circuit: &mut Circuit) -> String {
+        let name = build(circuit, "edff", &edge_input_name(&["d"]), |circuit, inputs| {
+            master_slave(circuit, inputs, |_, _| inputs[3])
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("Qn+1 = D on rising edge of CP");
+        name
+    }
+
+    //上升沿触发的 JK 触发器 ejkff
+    pub fn build_edge_jkff(circuit: &mut Circuit) -> String {
+        let name = build(circuit, "ejkff", &edge_input_name(&["j", "k"]), |circuit, inputs| {
+            master_slave(circuit, inputs, |circuit, q| {
+                let (j, k) = (inputs[3], inputs[4]);
+                let nq = circuit.add_gate("not", &[q]);
+                let nk = circuit.add_gate("not", &[k]);
+                let set = circuit.add_gate("and", &[j, nq]);
+                let keep = circuit.add_gate("and", &[nk, q]);
+                circuit.add_gate("or", &[set, keep])
+            })
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap()
+            .set_description("Qn+1 = (J and not(Qn)) or (not(K) and Qn) on rising edge of CP");
+        name
+    }
+
+    //上升沿触发的 T 触发器 etff
+    pub fn build_edge_tff(circuit: &mut Circuit) -> String {
+        let name = build(circuit, "etff", &edge_input_name(&["t"]), |circuit, inputs| {
+            master_slave(circuit, inputs, |circuit, q| circuit.add_gate("xor", &[inputs[3], q]))
+        });
+        circuit.get_patterns_mut().get_mut(&name).unwrap().set_description("Qn+1 = Qn xor T on rising edge of CP");
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::instance_with;
+
+    //每一步给出 (pr, clr, cp, 数据输入..)，检查执行一轮后的 q
+    fn check(name: &str, build: fn(&mut Circuit) -> String, steps: &[(&[usize], bool)]) {
+        let mut circuit = Circuit::new(steps[0].0.len());
+        assert_eq!(build(&mut circuit), name);
+        assert!(circuit.get_pattern(name).is_sequential());
+        assert_eq!(circuit.get_pattern(name).get_output_name(), &["nq", "q"]);
+        //输出为 nq, q，主锁存器是实例的内部状态
+        let q = instance_with(&mut circuit, name, &[1, 0])[1];
+        for (i, (inputs, expected)) in steps.iter().enumerate() {
+            circuit.execute_sequential_mut(inputs);
+            assert_eq!(circuit.get_signal(q).get_unchecked(), *expected, "{} step {}", name, i);
+        }
+    }
+
+    #[test]
+    fn test_edge_triggered() {
+        check("edff", Pattern::build_edge_dff, &[
+            (&[0, 0, 0, 1], false),
+            //上升沿取沿之前的 d
+            (&[0, 0, 1, 1], true),
+            //cp 保持高电平或下降沿时 d 的变化不影响 q
+            (&[0, 0, 1, 0], true),
+            (&[0, 0, 0, 0], true),
+            (&[0, 0, 0, 1], true),
+            (&[0, 0, 0, 0], true),
+            (&[0, 0, 1, 0], false),
+            //同一轮里 d 和 cp 一起变化，取的是旧的 d
+            (&[0, 0, 0, 0], false),
+            (&[0, 0, 1, 1], false),
+            //异步置位、清零立即生效，并且松开后保持
+            (&[1, 0, 1, 0], true),
+            (&[0, 0, 1, 0], true),
+            (&[0, 1, 0, 1], false),
+            (&[0, 0, 0, 1], false),
+            (&[1, 1, 0, 1], false),
+            (&[0, 0, 0, 1], false),
+            (&[0, 0, 1, 1], true),
+        ]);
+        check("ejkff", Pattern::build_edge_jkff, &[
+            (&[0, 0, 0, 1, 0], false),
+            (&[0, 0, 1, 1, 0], true),
+            (&[0, 0, 0, 1, 1], true),
+            (&[0, 0, 1, 1, 1], false),
+            (&[0, 0, 0, 1, 1], false),
+            (&[0, 0, 1, 1, 1], true),
+            (&[0, 0, 0, 0, 0], true),
+            (&[0, 0, 1, 0, 0], true),
+            (&[0, 0, 0, 0, 1], true),
+            (&[0, 0, 1, 0, 1], false),
+            (&[1, 0, 0, 0, 1], true),
+        ]);
+        check("etff", Pattern::build_edge_tff, &[
+            (&[0, 0, 0, 1], false),
+            (&[0, 0, 1, 1], true),
+            (&[0, 0, 1, 1], true),
+            (&[0, 0, 0, 1], true),
+            (&[0, 0, 1, 1], false),
+            (&[0, 0, 0, 0], false),
+            (&[0, 0, 1, 0], false),
+            (&[0, 0, 0, 1], false),
+            (&[0, 1, 1, 1], false),
+            (&[0, 0, 0, 1], false),
+            (&[0, 0, 1, 1], true),
+        ]);
+    }
 }
@@ -1,9 +1,9 @@
 use std::fmt;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use pest::pratt_parser::Op;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Hash, Deserialize, Serialize)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
 pub struct Signal {
     parent: Option<usize>,
     value: Option<bool>,
@@ -16,6 +16,13 @@ impl PartialEq for Signal {
 }
 impl Eq for Signal {}
 
+//与 PartialEq 保持一致，只对取值做哈希
+impl Hash for Signal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state)
+    }
+}
+
 impl Signal {
     pub fn get_unchecked(&self) -> bool { self.value.unwrap() }
 
@@ -34,6 +41,9 @@ impl Signal {
 
     pub fn set_parent(&mut self, p: usize) { self.parent = Some(p) }
 
+    //只更新取值，保留信号来源
+    pub fn set_value(&mut self, other: Signal) { self.value = other.value }
+
     pub fn not(&self) -> Self {
         Self::from_bool(Some(!self.get_unchecked()))
     }
@@ -51,10 +61,6 @@ impl Signal {
     }
 }
 
-impl Default for Signal {
-    fn default() -> Self { Signal { parent: None, value: None } }
-}
-
 impl fmt::Debug for Signal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.value {
@@ -1,7 +1,30 @@
 
-#[derive(Debug)]
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+use crate::circuit::Circuit;
+
+#[derive(Debug, Error)]
+pub enum TableError {
+    #[error("missing header row")]
+    MissingHeader,
+    #[error("header has {0} columns, expected more than {1} inputs")]
+    HeaderSize(usize, usize),
+    #[error("line {0}: expected {1} values, found {2}")]
+    RowSize(usize, usize, usize),
+    #[error("line {0}: invalid value `{1}`")]
+    InvalidValue(usize, String),
+    #[error("expected {0} input and {1} output names, found {2} and {3}")]
+    NameSize(usize, usize, usize, usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct TruthTable {
-    data: Vec<Vec<usize>>,
+    data: Vec<Vec<Option<usize>>>,
+    names: Vec<String>,
     input_size: usize,
     output_size: usize,
 }
@@ -11,16 +34,66 @@ impl TruthTable {
         data: Vec<Vec<usize>>,
         input_size: usize,
         output_size: usize) -> TruthTable {
-        Self { data, input_size, output_size }
+        let data = data.into_iter()
+            .map(|line| line.into_iter().map(Some).collect())
+            .collect();
+        Self::with_dont_care(data, input_size, output_size)
+    }
+
+    //None 表示无关项（don't care）
+    pub fn with_dont_care(
+        data: Vec<Vec<Option<usize>>>,
+        input_size: usize,
+        output_size: usize) -> TruthTable {
+        let names = (0..input_size).map(|i| format!("i{}", i))
+            .chain((0..output_size).map(|i| format!("o{}", i)))
+            .collect();
+        Self { data, names, input_size, output_size }
+    }
+
+    pub fn from_circuit(
+        circuit: &Circuit,
+        input_size: usize,
+        choose_outputs: &[usize]) -> TruthTable {
+        let data = Circuit::truth_table(circuit, input_size, choose_outputs)
+            .iter()
+            .map(|(input, output)| input.iter()
+                .chain(output.iter())
+                .map(|s| s.is_valid().then(|| s.get_unchecked() as usize))
+                .collect())
+            .collect();
+        Self::with_dont_care(data, input_size, choose_outputs.len())
     }
 
     pub fn input_size(&self) -> usize { self.input_size }
     pub fn output_size(&self) -> usize { self.output_size }
-    pub fn prepare_print(input_size: usize, output_size: usize){
+    pub fn get_data(&self) -> &Vec<Vec<Option<usize>>> { &self.data }
+    pub fn get_names(&self) -> &Vec<String> { &self.names }
+    pub fn get_input_name(&self) -> &[String] { &self.names[..self.input_size] }
+    pub fn get_output_name(&self) -> &[String] { &self.names[self.input_size..] }
+
+    pub fn set_name(
+        &mut self,
+        input_name: &[&str],
+        output_name: &[&str],
+    ) -> Result<&mut TruthTable, TableError> {
+        if input_name.len() != self.input_size || output_name.len() != self.output_size {
+            return Err(TableError::NameSize(
+                self.input_size, self.output_size, input_name.len(), output_name.len()));
+        }
+        self.names = input_name.iter()
+            .chain(output_name.iter())
+            .map(|s| s.to_string())
+            .collect();
+        Ok(self)
+    }
+
+    //输出全部为无关项的空表，便于手动填写
+    pub fn prepare(input_size: usize, output_size: usize) -> TruthTable {
         let n = 2u32.pow(input_size as u32) as usize;
         let mut data = vec![vec![]; n];
 
-        for i in 0..n {
+        for (i, line) in data.iter_mut().enumerate() {
             let mut temp = i;
             let mut input_line = vec![None; input_size + output_size];
             for j in 0..input_size {
@@ -28,34 +101,138 @@ impl TruthTable {
                 temp /= 2;
                 input_line[input_size - j - 1] = Some(value);
             }
-            data[i] = input_line;
+            *line = input_line;
         }
 
-        Self::print(&data);
+        Self::with_dont_care(data, input_size, output_size)
+    }
+
+    pub fn prepare_print(input_size: usize, output_size: usize) {
+        print!("{}", Self::prepare(input_size, output_size));
     }
 
-    pub fn print(data: &Vec<Vec<Option<usize>>>) {
-        println!("vec![");
-        for line in data {
-            print!("vec![");
-            for value in line {
-                Self::print_value(value);
-                print!(", ")
+    //第一行为信号名，其余每行为一组取值，`-`或`x`表示无关项
+    pub fn from_csv(csv: &str, input_size: usize) -> Result<TruthTable, TableError> {
+        let mut lines = csv.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or(TableError::MissingHeader)?;
+        let names = header.split(',')
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<String>>();
+        if names.len() <= input_size {
+            return Err(TableError::HeaderSize(names.len(), input_size));
+        }
+
+        let mut data = Vec::new();
+        for (i, line) in lines {
+            let values = line.split(',')
+                .map(|s| Self::parse_value(s.trim()).ok_or_else(||
+                    TableError::InvalidValue(i + 1, s.trim().to_string())))
+                .collect::<Result<Vec<Option<usize>>, TableError>>()?;
+            if values.len() != names.len() {
+                return Err(TableError::RowSize(i + 1, names.len(), values.len()));
             }
-            println!("],");
+            data.push(values);
+        }
+
+        let output_size = names.len() - input_size;
+        Ok(Self { data, names, input_size, output_size })
+    }
+
+    pub fn load_csv<P: AsRef<Path>>(path: P, input_size: usize) -> Result<TruthTable, TableError> {
+        Self::from_csv(&fs::read_to_string(path)?, input_size)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.names.join(",");
+        csv.push('\n');
+        for line in &self.data {
+            let values = line.iter()
+                .map(|v| Self::format_value(v))
+                .collect::<Vec<&str>>();
+            csv.push_str(&values.join(","));
+            csv.push('\n');
         }
-        println!("]");
+        csv
     }
 
-    pub fn print_value(value: &Option<usize>) {
-        if let Some(v) = value {
-            print!("{}", v);
-        } else {
-            print!("_");
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), TableError> {
+        fs::write(path, self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let widths = self.column_widths();
+        let row = |cells: Vec<&str>| {
+            let cells = cells.iter().zip(&widths)
+                .map(|(c, w)| format!(" {:^w$} ", c, w = w))
+                .collect::<Vec<String>>();
+            format!("|{}|\n", cells.join("|"))
+        };
+
+        let mut md = row(self.names.iter().map(|s| s.as_str()).collect());
+        let rule = widths.iter()
+            .map(|w| format!(":{}:", "-".repeat(*w)))
+            .collect::<Vec<String>>();
+        md.push_str(&format!("|{}|\n", rule.join("|")));
+        for line in &self.data {
+            md.push_str(&row(line.iter().map(Self::format_value).collect()));
+        }
+        md
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        self.names.iter().map(|s| s.chars().count().max(1)).collect()
+    }
+
+    fn parse_value(s: &str) -> Option<Option<usize>> {
+        match s {
+            "0" => Some(Some(0)),
+            "1" => Some(Some(1)),
+            "-" | "x" | "X" => Some(None),
+            _ => None,
+        }
+    }
+
+    fn format_value(value: &Option<usize>) -> &'static str {
+        match value {
+            Some(0) => "0",
+            Some(_) => "1",
+            None => "-",
         }
     }
 }
 
+//对齐的ASCII表格，输入与输出之间用`||`分隔
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths = self.column_widths();
+        let write_row = |f: &mut fmt::Formatter, cells: Vec<&str>| -> fmt::Result {
+            for (i, (cell, w)) in cells.iter().zip(&widths).enumerate() {
+                if i == self.input_size && i != 0 { write!(f, "|")?; }
+                write!(f, "| {:^w$} ", cell, w = w)?;
+            }
+            writeln!(f, "|")
+        };
+        let write_rule = |f: &mut fmt::Formatter| -> fmt::Result {
+            for (i, w) in widths.iter().enumerate() {
+                if i == self.input_size && i != 0 { write!(f, "+")?; }
+                write!(f, "+{}", "-".repeat(w + 2))?;
+            }
+            writeln!(f, "+")
+        };
+
+        write_rule(f)?;
+        write_row(f, self.names.iter().map(|s| s.as_str()).collect())?;
+        write_rule(f)?;
+        for line in &self.data {
+            write_row(f, line.iter().map(Self::format_value).collect())?;
+        }
+        write_rule(f)
+    }
+}
+
 pub struct KarnaughMap {
     data: Vec<Vec<usize>>,
 }
@@ -110,4 +287,32 @@ mod tests {
             2
         );
     }
+
+    #[test]
+    fn test_csv() {
+        let csv = "a,b,s,c\n0,0,0,0\n0,1,1,-\n1,0,1,x\n1,1,0,1\n";
+        let tt = TruthTable::from_csv(csv, 2).unwrap();
+        assert_eq!(tt.output_size(), 2);
+        assert_eq!(tt.get_data()[1], vec![Some(0), Some(1), Some(1), None]);
+        assert_eq!(tt.to_csv(), csv.replace('x', "-"));
+        assert!(TruthTable::from_csv("a,b\n0,2\n", 1).is_err());
+        assert!(TruthTable::from_csv("a,b\n0\n", 1).is_err());
+    }
+
+    #[test]
+    fn test_markdown() {
+        let mut circuit = Circuit::new(2);
+        let input = circuit.get_input();
+        let s = circuit.add_gate("xor", &input);
+        let c = circuit.add_gate("and", &input);
+        let mut tt = TruthTable::from_circuit(&circuit, 2, &[s, c]);
+        tt.set_name(&["a", "b"], &["s", "c"]).unwrap();
+        assert!(tt.clone().set_name(&["a"], &["s", "c"]).is_err());
+        assert!(tt.clone().set_name(&["a", "b"], &["s", "c", "d"]).is_err());
+
+        let md = tt.to_markdown();
+        assert_eq!(md.lines().next(), Some("| a | b | s | c |"));
+        assert_eq!(md.lines().nth(5), Some("| 1 | 1 | 0 | 1 |"));
+        assert_eq!(format!("{}", tt).lines().nth(1), Some("| a | b || s | c |"));
+    }
 }
@@ -0,0 +1,28 @@
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+
+//各 pattern 测试共用的工具
+
+//实例化 pattern，输入依次接电路输入，输出预置为 initial，返回各输出信号
+pub(crate) fn instance_with(circuit: &mut Circuit, name: &str, initial: &[usize]) -> Vec<usize> {
+    let inputs = circuit.get_input();
+    let outputs = circuit.advance_output_with_value(initial);
+    circuit.add_node(NodeType::Pattern(name.to_string()), inputs, outputs.clone());
+    outputs
+}
+
+//输出全部预置为 0，带反馈的触发器也可以直接使用
+pub(crate) fn instance(circuit: &mut Circuit, name: &str) -> Vec<usize> {
+    let size = circuit.get_pattern(name).output_size();
+    instance_with(circuit, name, &vec![0; size])
+}
+
+//value 从低位到高位的 width 位，可以直接交给 execute_sequential
+pub(crate) fn bits(value: usize, width: usize) -> Vec<usize> {
+    (0..width).map(|i| value >> i & 1).collect()
+}
+
+//signals 从低位到高位组成的整数
+pub(crate) fn read(circuit: &Circuit, signals: &[usize]) -> usize {
+    signals.iter().enumerate().map(|(i, s)| (circuit.get_signal(*s).get_unchecked() as usize) << i).sum()
+}
@@ -0,0 +1,441 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use pest::Parser;
+use pest::iterators::Pair;
+use pest_derive::Parser;
+use thiserror::Error;
+use crate::circuit::Circuit;
+use crate::netlist::{Cell, CellKind, Connection, Module, Netlist, NetlistError};
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PniType};
+
+#[derive(Parser)]
+#[grammar = "verilog.pest"]
+struct VerilogParser;
+
+#[derive(Debug, Error)]
+pub enum VerilogError {
+    #[error(transparent)]
+    Syntax(#[from] Box<pest::error::Error<Rule>>),
+    #[error(transparent)]
+    Netlist(#[from] NetlistError),
+}
+
+const KEYWORDS: &[&str] = &[
+    "always", "and", "assign", "begin", "buf", "case", "default", "else", "end", "endcase",
+    "endmodule", "for", "if", "initial", "inout", "input", "module", "nand", "nor", "not",
+    "or", "output", "parameter", "reg", "wire", "xnor", "xor",
+];
+
+//不合法的名字（例如 a[0]）写成转义标识符
+pub fn verilog_ident(name: &str) -> String {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !KEYWORDS.contains(&name);
+    if valid { name.to_string() } else { format!("\\{} ", name) }
+}
+
+fn primitive(node_type: &NodeType) -> Option<&'static str> {
+    match node_type {
+        NodeType::And => Some("and"),
+        NodeType::Or => Some("or"),
+        NodeType::Not => Some("not"),
+        NodeType::Xor => Some("xor"),
+        NodeType::NAnd => Some("nand"),
+        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => None,
+    }
+}
+
+//门级原语，与 execute_gate 一致，异或门只取前两个输入，Verilog 的多输入 xor 是奇偶校验
+fn write_gate(v: &mut String, gate: &NodeType, index: usize, output: &str, operands: &[String]) {
+    let operands = match gate {
+        NodeType::Xor => &operands[..operands.len().min(2)],
+        _ => operands,
+    };
+    writeln!(v, "    {} g{} ({}, {});", primitive(gate).unwrap(), index, output, operands.join(", ")).unwrap();
+}
+
+//实例化 pattern，端口按名字连接
+fn write_instance(
+    v: &mut String,
+    pattern_name: &str,
+    pattern: &Pattern,
+    instance: &str,
+    inputs: &[String],
+    outputs: &[String],
+) {
+    let ports = inputs.iter().enumerate()
+        .map(|(i, s)| format!(".{}({})", verilog_ident(&pattern.input_port(i)), s))
+        .chain(outputs.iter().enumerate()
+            .map(|(i, s)| format!(".{}({})", verilog_ident(&pattern.output_port(i)), s)))
+        .collect::<Vec<String>>();
+    writeln!(v, "    {} {} ({});", verilog_ident(pattern_name), instance, ports.join(", ")).unwrap();
+}
+
+impl Pattern {
+    pub fn to_verilog(&self, module_name: &str, circuit: &Circuit) -> String {
+        let inputs = (0..self.input_size()).map(|i| verilog_ident(&self.input_port(i))).collect::<Vec<String>>();
+        let outputs = (0..self.output_size()).map(|i| verilog_ident(&self.output_port(i))).collect::<Vec<String>>();
+        //每个 node 的第 k 个输出
+        let wire = |node: usize, k: usize| match self.get_pattern()[node].get_type() {
+            NodeType::Pattern(_) => format!("n{}_{}", node, k),
+            _ => format!("n{}", node),
+        };
+
+        let mut v = String::new();
+        if !self.get_description().is_empty() {
+            writeln!(v, "// {}", self.get_description()).unwrap();
+        }
+        if self.is_sequential() {
+            writeln!(v, "// sequential: contains feedback loops, outputs hold state between evaluations").unwrap();
+        }
+        let ports = inputs.iter().chain(&outputs).cloned().collect::<Vec<String>>();
+        writeln!(v, "module {}({});", verilog_ident(module_name), ports.join(", ")).unwrap();
+        for input in &inputs {
+            writeln!(v, "    input {};", input).unwrap();
+        }
+        for output in &outputs {
+            writeln!(v, "    output {};", output).unwrap();
+        }
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    for k in 0..circuit.get_pattern(name).output_size() {
+                        writeln!(v, "    wire {};", wire(i, k)).unwrap();
+                    }
+                }
+                _ => writeln!(v, "    wire {};", wire(i, 0)).unwrap(),
+            }
+        }
+
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            let operands = node.get_input().iter()
+                .map(|input| match input {
+                    PniType::Input(i) => inputs[*i].clone(),
+                    PniType::NodeOutput((n, k)) => wire(*n, *k),
+                })
+                .collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let pattern = circuit.get_pattern(name);
+                    let node_outputs = (0..pattern.output_size()).map(|k| wire(i, k)).collect::<Vec<String>>();
+                    write_instance(&mut v, name, pattern, &format!("u{}", i), &operands, &node_outputs);
+                }
+                gate => write_gate(&mut v, gate, i, &wire(i, 0), &operands),
+            }
+        }
+        for (output, (node, k)) in outputs.iter().zip(self.get_output()) {
+            writeln!(v, "    assign {} = {};", output, wire(*node, *k)).unwrap();
+        }
+        v.push_str("endmodule\n");
+        v
+    }
+}
+
+impl Circuit {
+    //用到的 pattern 各自生成一个 module，之后是顶层 module
+    //outputs 给出顶层输出端口的名字和对应的信号，输入依次以 input_name 命名，不足时记为 i0, i1...
+    pub fn to_verilog(&self, module_name: &str, input_name: &[&str], outputs: &[(&str, usize)]) -> String {
+        let mut v = String::new();
+        for name in self.used_patterns() {
+            v.push_str(&self.get_pattern(&name).to_verilog(&name, self));
+            v.push('\n');
+        }
+
+        let input_size = self.get_input().len();
+        let signal = |i: usize| {
+            if i < input_size {
+                verilog_ident(&input_name.get(i).map(|s| s.to_string()).unwrap_or_else(|| format!("i{}", i)))
+            } else {
+                format!("s{}", i)
+            }
+        };
+        let input_ports = (0..input_size).map(signal).collect::<Vec<String>>();
+        let output_ports = outputs.iter().map(|(name, _)| verilog_ident(name)).collect::<Vec<String>>();
+
+        let ports = input_ports.iter().chain(&output_ports).cloned().collect::<Vec<String>>();
+        writeln!(v, "module {}({});", verilog_ident(module_name), ports.join(", ")).unwrap();
+        for input in &input_ports {
+            writeln!(v, "    input {};", input).unwrap();
+        }
+        for output in &output_ports {
+            writeln!(v, "    output {};", output).unwrap();
+        }
+        for i in input_size..self.signals_size() {
+            writeln!(v, "    wire {};", signal(i)).unwrap();
+        }
+        for (i, node) in self.get_pipeline().iter().enumerate() {
+            let operands = node.get_input().iter().map(|i| signal(*i)).collect::<Vec<String>>();
+            let node_outputs = node.get_output().iter().map(|o| signal(*o)).collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    write_instance(&mut v, name, self.get_pattern(name), &format!("u{}", i), &operands, &node_outputs);
+                }
+                NodeType::Register(width) => {
+                    let d = operands[3..].iter().rev().cloned().collect::<Vec<String>>();
+                    writeln!(v, "    reg [{}:0] r{} = 0;", width - 1, i).unwrap();
+                    writeln!(v, "    always @(posedge {}) if ({}) r{} <= 0; else if ({}) r{} <= {{{}}};",
+                             operands[0], operands[2], i, operands[1], i, d.join(", ")).unwrap();
+                    for (k, q) in node_outputs.iter().enumerate() {
+                        writeln!(v, "    assign {} = r{}[{}];", q, i, k).unwrap();
+                    }
+                }
+                //存储器写成数组，初始内容为当前保存的数据
+                NodeType::Rom(address_width, width) | NodeType::Ram(address_width, width) => {
+                    let address = match node.get_type() {
+                        NodeType::Rom(..) => &operands[..*address_width],
+                        _ => &operands[2..2 + address_width],
+                    };
+                    let address = address.iter().rev().cloned().collect::<Vec<String>>().join(", ");
+                    writeln!(v, "    reg [{}:0] m{} [0:{}];", width - 1, i, (1usize << address_width) - 1).unwrap();
+                    writeln!(v, "    integer k{};", i).unwrap();
+                    writeln!(v, "    initial begin").unwrap();
+                    writeln!(v, "        for (k{} = 0; k{} < {}; k{} = k{} + 1) m{}[k{}] = 0;", i, i, 1usize << address_width, i, i, i, i).unwrap();
+                    let words = self.get_states().get(&node.get_output()[0]).map(|s| s.get_data().clone()).unwrap_or_default();
+                    for (a, word) in words.iter().enumerate().filter(|(_, w)| **w != 0) {
+                        writeln!(v, "        m{}[{}] = {}'h{:x};", i, a, width, word).unwrap();
+                    }
+                    writeln!(v, "    end").unwrap();
+                    if let NodeType::Ram(..) = node.get_type() {
+                        let d = operands[2 + address_width..].iter().rev().cloned().collect::<Vec<String>>();
+                        writeln!(v, "    always @(posedge {}) if ({}) m{}[{{{}}}] <= {{{}}};",
+                                 operands[0], operands[1], i, address, d.join(", ")).unwrap();
+                    }
+                    for (k, q) in node_outputs.iter().enumerate() {
+                        writeln!(v, "    assign {} = m{}[{{{}}}][{}];", q, i, address, k).unwrap();
+                    }
+                }
+                gate => write_gate(&mut v, gate, i, &node_outputs[0], &operands),
+            }
+        }
+        for (port, (_, s)) in output_ports.iter().zip(outputs) {
+            writeln!(v, "    assign {} = {};", port, signal(*s)).unwrap();
+        }
+        v.push_str("endmodule\n");
+        v
+    }
+
+    //pipeline 中直接或间接用到的 pattern，被依赖的排在前面
+    pub fn used_patterns(&self) -> Vec<String> {
+        fn visit(circuit: &Circuit, name: &str, seen: &mut BTreeSet<String>, order: &mut Vec<String>) {
+            if !seen.insert(name.to_string()) { return; }
+            for node in circuit.get_pattern(name).get_pattern() {
+                if let NodeType::Pattern(inner) = node.get_type() {
+                    visit(circuit, inner, seen, order);
+                }
+            }
+            order.push(name.to_string());
+        }
+        let mut seen = BTreeSet::new();
+        let mut order = Vec::new();
+        for node in self.get_pipeline() {
+            if let NodeType::Pattern(name) = node.get_type() {
+                visit(self, name, &mut seen, &mut order);
+            }
+        }
+        order
+    }
+}
+
+fn ident(pair: Pair<Rule>) -> String {
+    let s = pair.as_str();
+    s.strip_prefix('\\').unwrap_or(s).to_string()
+}
+
+fn ident_list(pair: Pair<Rule>) -> Vec<String> {
+    pair.into_inner().map(ident).collect()
+}
+
+fn parse_module(pair: Pair<Rule>) -> Module {
+    let mut module = Module::default();
+    //端口表中的顺序优先于声明的顺序
+    let mut header = Vec::new();
+    let mut declared = Vec::new();
+    for item in pair.into_inner() {
+        match item.as_rule() {
+            Rule::simple_ident | Rule::escaped_ident => module.name = ident(item),
+            Rule::port_list => for port in item.into_inner() {
+                let mut dir = None;
+                let mut name = String::new();
+                for p in port.into_inner() {
+                    match p.as_rule() {
+                        Rule::input | Rule::output => dir = Some(p.as_rule()),
+                        Rule::simple_ident | Rule::escaped_ident => name = ident(p),
+                        _ => {}
+                    }
+                }
+                if let Some(dir) = dir { declared.push((dir, name.clone())); }
+                header.push(name);
+            },
+            Rule::port_decl => {
+                let mut inner = item.into_inner();
+                let dir = inner.next().unwrap().as_rule();
+                let names = inner.find(|p| p.as_rule() == Rule::ident_list).unwrap();
+                declared.extend(ident_list(names).into_iter().map(|n| (dir, n)));
+            }
+            Rule::assign => {
+                let mut names = item.into_inner().skip(1).map(ident);
+                let (lhs, rhs) = (names.next().unwrap(), names.next().unwrap());
+                module.cells.push(Cell::new(CellKind::Assign, vec![rhs], vec![lhs]));
+            }
+            Rule::gate_inst => {
+                let mut inner = item.into_inner();
+                let kind = inner.next().unwrap().as_str();
+                let terminals = ident_list(inner.last().unwrap());
+                module.cells.push(Cell::new(
+                    CellKind::Gate(crate::msic::gain_gate_type(kind)),
+                    terminals[1..].to_vec(),
+                    vec![terminals[0].clone()],
+                ));
+            }
+            Rule::module_inst => {
+                let mut inner = item.into_inner();
+                let name = ident(inner.next().unwrap());
+                inner.next();
+                let connection = match inner.next() {
+                    Some(list) if list.as_rule() == Rule::named_list => Connection::Named(
+                        list.into_inner()
+                            .map(|named| {
+                                let mut n = named.into_inner().map(ident);
+                                (n.next().unwrap(), n.next())
+                            })
+                            .collect()),
+                    Some(list) => Connection::Positional(ident_list(list)),
+                    None => Connection::Positional(Vec::new()),
+                };
+                module.add_instance(name, connection);
+            }
+            _ => {}
+        }
+    }
+
+    for name in header.iter().chain(declared.iter().map(|(_, n)| n)) {
+        match declared.iter().find(|(_, n)| n == name) {
+            Some((Rule::input, _)) if !module.inputs.contains(name) => module.inputs.push(name.clone()),
+            Some((Rule::output, _)) if !module.outputs.contains(name) => module.outputs.push(name.clone()),
+            _ => {}
+        }
+    }
+    module
+}
+
+impl Circuit {
+    //top 为 None 时取没有被实例化的最后一个模块，其余模块各自成为一个 pattern
+    pub fn from_verilog(src: &str, top: Option<&str>) -> Result<(Circuit, Vec<(String, usize)>), VerilogError> {
+        let file = VerilogParser::parse(Rule::file, src)
+            .map_err(Box::new)?
+            .next()
+            .unwrap();
+        let modules = file.into_inner()
+            .filter(|p| p.as_rule() == Rule::module)
+            .map(parse_module)
+            .collect::<Vec<Module>>();
+        let netlist = Netlist::new(modules)?;
+        let top = match top {
+            Some(name) => netlist.module(name)?,
+            None => netlist.top()?,
+        };
+        Ok(netlist.build(top)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let mut circuit = Circuit::new_with_basic_pattern(4);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input[..3]).get_output().to_vec();
+        let q = circuit.apply_flipflop("rsff", &[input[3], input[1], sum[0]]).get_output()[1];
+        let n = circuit.add_gate("not", &[q]);
+
+        let v = circuit.to_verilog("top", &["c0", "a", "b", "r"], &[("c1", sum[1]), ("q", n)]);
+        assert!(v.contains("module full_adder(c0, a, b, s, c1);"));
+        assert!(v.contains("    xor g0 (n0, a, b);"));
+        assert!(v.contains("    assign c1 = n4;"));
+        assert!(v.contains("// sequential"));
+        assert!(v.contains("module top(c0, a, b, r, c1, q);"));
+        assert!(v.contains("    full_adder u0 (.c0(c0), .a(a), .b(b), .s(s4), .c1(s5));"));
+        assert!(v.contains("    rsff u1 (.r(r), .cp(a), .s(s4), .nq(s6), .q(s7));"));
+        assert!(v.contains("    not g2 (s8, s7);"));
+        assert_eq!(v.matches("endmodule").count(), 3);
+        assert_eq!(verilog_ident("a[0]"), "\\a[0] ");
+        assert_eq!(verilog_ident("wire"), "\\wire ");
+
+        //三输入的异或门只写出前两个输入
+        let mut circuit = Circuit::new(3);
+        let input = circuit.get_input();
+        let x = circuit.add_gate("xor", &input);
+        let v = circuit.to_verilog("top", &["a", "b", "c"], &[("x", x)]);
+        assert!(v.contains("    xor g0 (s3, a, b);"));
+    }
+
+    #[test]
+    fn test_import() {
+        let src = "
+            // 半加器
+            module half_adder(a, b, s, c);
+                input a, b;
+                output s, c;
+                xor (s, a, b);
+                and g1 (c, a, b);
+            endmodule
+
+            module full_adder(input a, input b, input c0, output s, output c1);
+                wire s0, c2, c3;
+                half_adder h0 (.a(a), .b(b), .s(s0), .c(c2));
+                half_adder h1 (s0, c0, s, c3);
+                or (c1, c2, c3);
+            endmodule
+
+            module top(x, y, z, sum, carry, parity);
+                input x, y, z;
+                output sum, carry, parity;
+                wire p;
+                full_adder fa (.a(x), .b(y), .c0(z), .s(sum), .c1(carry));
+                assign parity = p;
+                xor (p, x, y, z);
+            endmodule
+        ";
+        let (circuit, outputs) = Circuit::from_verilog(src, None).unwrap();
+        assert_eq!(outputs.iter().map(|(n, _)| n.as_str()).collect::<Vec<&str>>(), ["sum", "carry", "parity"]);
+        assert_eq!(circuit.get_pattern("full_adder").get_input_name(), &["a", "b", "c0"]);
+        assert_eq!(circuit.get_pattern("full_adder").get_pattern().len(), 5);
+
+        for (input, output) in Circuit::truth_table(&circuit, 3, &[outputs[0].1, outputs[1].1, outputs[2].1]) {
+            let n = input.iter().filter(|s| s.get_unchecked()).count();
+            assert_eq!(output[0].get_unchecked(), n % 2 == 1);
+            assert_eq!(output[1].get_unchecked(), n >= 2);
+            assert_eq!(output[2], output[0]);
+        }
+        assert!(matches!(Circuit::from_verilog("module m(a, y); input a; output y; endmodule", None),
+                         Err(VerilogError::Netlist(NetlistError::Undriven(_, _)))));
+        assert!(matches!(Circuit::from_verilog("module m(a, y); input a; output y; foo u(a, y); endmodule", None),
+                         Err(VerilogError::Netlist(NetlistError::UnknownModule(_)))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut circuit = Circuit::new_with_basic_pattern(4);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input[..3]).get_output().to_vec();
+        let n = circuit.add_gate("nand", &[sum[0], sum[1], input[3]]);
+        let v = circuit.to_verilog("top", &["c0", "a", "b", "r"], &[("s", sum[0]), ("c1", sum[1]), ("n", n)]);
+
+        let (imported, outputs) = Circuit::from_verilog(&v, Some("top")).unwrap();
+        let outputs = outputs.iter().map(|(_, s)| *s).collect::<Vec<usize>>();
+        assert_eq!(imported.get_pattern("full_adder").get_pattern().len(), 5);
+        assert_eq!(imported.equivalent(&outputs, &circuit, &[sum[0], sum[1], n]).unwrap(),
+                   crate::equivalence::Equivalence::Equal);
+
+        //rsff 中的回路保持原来的顺序
+        let original = circuit.get_pattern("rsff").to_verilog("rsff", &circuit);
+        let (imported, _) = Circuit::from_verilog(&original, None).unwrap();
+        assert_eq!(imported.get_pipeline().len(), 4);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::pattern::{Pattern, PniType};
+
+const KEYWORDS: &[&str] = &[
+    "abs", "and", "architecture", "begin", "block", "buffer", "bus", "case", "component",
+    "constant", "else", "elsif", "end", "entity", "for", "function", "generate", "if", "in",
+    "inout", "is", "library", "loop", "map", "mod", "nand", "nor", "not", "of", "or", "others",
+    "out", "port", "process", "range", "rem", "select", "signal", "then", "to", "type", "use",
+    "variable", "wait", "when", "while", "with", "xnor", "xor",
+];
+
+const HEADER: &str = "library ieee;\nuse ieee.std_logic_1164.all;\nuse ieee.numeric_std.all;\n";
+
+//不合法的名字（例如 a[0]、连续下划线）写成扩展标识符
+pub fn vhdl_ident(name: &str) -> String {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.ends_with('_')
+        && !name.contains("__")
+        && !KEYWORDS.contains(&name.to_ascii_lowercase().as_str());
+    if valid { name.to_string() } else { format!("\\{}\\", name.replace('\\', "\\\\")) }
+}
+
+fn expression(node_type: &NodeType, operands: &[String]) -> String {
+    match node_type {
+        NodeType::And if operands.len() == 1 => operands[0].clone(),
+        NodeType::And => operands.join(" and "),
+        NodeType::Or => operands.join(" or "),
+        NodeType::Not => format!("not {}", operands[0]),
+        //与 execute_gate 一致，只取前两个输入
+        NodeType::Xor => format!("{} xor {}", operands[0], operands[1]),
+        NodeType::NAnd => format!("not ({})", operands.join(" and ")),
+        NodeType::Pattern(_) | NodeType::Register(_) | NodeType::Rom(..) | NodeType::Ram(..) => unreachable!(),
+    }
+}
+
+//从高位到低位拼接，例如 unsigned'(a1 & a0)，最左边为最高位；只有一位时写成 unsigned'(0 => a0)
+//不能按下标写成聚合：无约束类型的聚合取升序范围，最左边会变成 a0
+fn vector(kind: &str, bits: &[String]) -> String {
+    match bits {
+        [bit] => format!("{}'(0 => {})", kind, bit),
+        _ => format!("{}'({})", kind, bits.iter().rev().cloned().collect::<Vec<String>>().join(" & ")),
+    }
+}
+
+fn write_entity(v: &mut String, entity_name: &str, inputs: &[String], outputs: &[String]) {
+    v.push_str(HEADER);
+    v.push('\n');
+    writeln!(v, "entity {} is", vhdl_ident(entity_name)).unwrap();
+    let ports = inputs.iter().map(|p| format!("{} : in std_logic", p))
+        .chain(outputs.iter().map(|p| format!("{} : out std_logic", p)))
+        .collect::<Vec<String>>();
+    if !ports.is_empty() {
+        writeln!(v, "    port (\n        {}\n    );", ports.join(";\n        ")).unwrap();
+    }
+    writeln!(v, "end entity;").unwrap();
+    v.push('\n');
+}
+
+//实例化 pattern 对应的 entity，端口按名字连接
+fn write_instance(
+    v: &mut String,
+    pattern_name: &str,
+    pattern: &Pattern,
+    instance: &str,
+    inputs: &[String],
+    outputs: &[String],
+) {
+    let ports = inputs.iter().enumerate()
+        .map(|(i, s)| format!("{} => {}", vhdl_ident(&pattern.input_port(i)), s))
+        .chain(outputs.iter().enumerate()
+            .map(|(i, s)| format!("{} => {}", vhdl_ident(&pattern.output_port(i)), s)))
+        .collect::<Vec<String>>();
+    writeln!(v, "    {}: entity work.{} port map ({});", instance, vhdl_ident(pattern_name), ports.join(", ")).unwrap();
+}
+
+impl Pattern {
+    //带反馈的 node 在被驱动之前就会被读到，它们的信号初始为 '0'
+    pub fn to_vhdl(&self, entity_name: &str, circuit: &Circuit) -> String {
+        let inputs = (0..self.input_size()).map(|i| vhdl_ident(&self.input_port(i))).collect::<Vec<String>>();
+        let outputs = (0..self.output_size()).map(|i| vhdl_ident(&self.output_port(i))).collect::<Vec<String>>();
+        //每个 node 的第 k 个输出
+        let wire = |node: usize, k: usize| match self.get_pattern()[node].get_type() {
+            NodeType::Pattern(_) => format!("n{}_{}", node, k),
+            _ => format!("n{}", node),
+        };
+        let feedback = self.get_pattern().iter()
+            .enumerate()
+            .flat_map(|(i, node)| node.get_input().iter().filter_map(move |input| match input {
+                PniType::NodeOutput((n, _)) if *n >= i => Some(*n),
+                _ => None,
+            }))
+            .collect::<HashSet<usize>>();
+
+        let mut v = String::new();
+        if !self.get_description().is_empty() {
+            writeln!(v, "-- {}", self.get_description()).unwrap();
+        }
+        if self.is_sequential() {
+            writeln!(v, "-- sequential: contains feedback loops, outputs hold state between evaluations").unwrap();
+        }
+        write_entity(&mut v, entity_name, &inputs, &outputs);
+
+        writeln!(v, "architecture structural of {} is", vhdl_ident(entity_name)).unwrap();
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            let init = if feedback.contains(&i) { " := '0'" } else { "" };
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    for k in 0..circuit.get_pattern(name).output_size() {
+                        writeln!(v, "    signal {} : std_logic{};", wire(i, k), init).unwrap();
+                    }
+                }
+                _ => writeln!(v, "    signal {} : std_logic{};", wire(i, 0), init).unwrap(),
+            }
+        }
+        writeln!(v, "begin").unwrap();
+
+        for (i, node) in self.get_pattern().iter().enumerate() {
+            let operands = node.get_input().iter()
+                .map(|input| match input {
+                    PniType::Input(i) => inputs[*i].clone(),
+                    PniType::NodeOutput((n, k)) => wire(*n, *k),
+                })
+                .collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    let pattern = circuit.get_pattern(name);
+                    let node_outputs = (0..pattern.output_size()).map(|k| wire(i, k)).collect::<Vec<String>>();
+                    write_instance(&mut v, name, pattern, &format!("u{}", i), &operands, &node_outputs);
+                }
+                gate => writeln!(v, "    {} <= {};", wire(i, 0), expression(gate, &operands)).unwrap(),
+            }
+        }
+        for (output, (node, k)) in outputs.iter().zip(self.get_output()) {
+            writeln!(v, "    {} <= {};", output, wire(*node, *k)).unwrap();
+        }
+        v.push_str("end architecture;\n");
+        v
+    }
+}
+
+impl Circuit {
+    //用到的 pattern 各自生成一对 entity/architecture，之后是顶层
+    //参数与 to_verilog 相同；已有值的内部信号（例如触发器的预置状态）作为初始值写出
+    pub fn to_vhdl(&self, entity_name: &str, input_name: &[&str], outputs: &[(&str, usize)]) -> String {
+        let mut v = String::new();
+        for name in self.used_patterns() {
+            v.push_str(&self.get_pattern(&name).to_vhdl(&name, self));
+            v.push('\n');
+        }
+
+        let input_size = self.get_input().len();
+        let signal = |i: usize| {
+            if i < input_size {
+                vhdl_ident(&input_name.get(i).map(|s| s.to_string()).unwrap_or_else(|| format!("i{}", i)))
+            } else {
+                format!("s{}", i)
+            }
+        };
+        let input_ports = (0..input_size).map(signal).collect::<Vec<String>>();
+        let output_ports = outputs.iter().map(|(name, _)| vhdl_ident(name)).collect::<Vec<String>>();
+        write_entity(&mut v, entity_name, &input_ports, &output_ports);
+
+        writeln!(v, "architecture structural of {} is", vhdl_ident(entity_name)).unwrap();
+        for (i, s) in self.get_signals().iter().enumerate().skip(input_size) {
+            match s.is_valid() {
+                true => writeln!(v, "    signal {} : std_logic := '{}';", signal(i), s.get_unchecked() as usize).unwrap(),
+                false => writeln!(v, "    signal {} : std_logic;", signal(i)).unwrap(),
+            }
+        }
+        //存储器写成数组，初始内容为当前保存的数据
+        for (i, node) in self.get_pipeline().iter().enumerate() {
+            if let NodeType::Rom(address_width, width) | NodeType::Ram(address_width, width) = node.get_type() {
+                writeln!(v, "    type m{}_t is array (0 to {}) of std_logic_vector({} downto 0);", i, (1usize << address_width) - 1, width - 1).unwrap();
+                let words = self.get_states().get(&node.get_output()[0]).map(|s| s.get_data().clone()).unwrap_or_default();
+                let init = words.iter().enumerate()
+                    .filter(|(_, w)| **w != 0)
+                    .map(|(a, w)| format!("{} => \"{:0width$b}\"", a, w, width = width))
+                    .chain(["others => (others => '0')".to_string()])
+                    .collect::<Vec<String>>();
+                writeln!(v, "    signal m{} : m{}_t := ({});", i, i, init.join(", ")).unwrap();
+            }
+        }
+        writeln!(v, "begin").unwrap();
+        for (i, node) in self.get_pipeline().iter().enumerate() {
+            let operands = node.get_input().iter().map(|i| signal(*i)).collect::<Vec<String>>();
+            let node_outputs = node.get_output().iter().map(|o| signal(*o)).collect::<Vec<String>>();
+            match node.get_type() {
+                NodeType::Pattern(name) => {
+                    write_instance(&mut v, name, self.get_pattern(name), &format!("u{}", i), &operands, &node_outputs);
+                }
+                NodeType::Register(_) => {
+                    writeln!(v, "    process({}) begin", operands[0]).unwrap();
+                    writeln!(v, "        if rising_edge({}) then", operands[0]).unwrap();
+                    writeln!(v, "            if {} = '1' then", operands[2]).unwrap();
+                    for q in &node_outputs {
+                        writeln!(v, "                {} <= '0';", q).unwrap();
+                    }
+                    writeln!(v, "            elsif {} = '1' then", operands[1]).unwrap();
+                    for (q, d) in node_outputs.iter().zip(&operands[3..]) {
+                        writeln!(v, "                {} <= {};", q, d).unwrap();
+                    }
+                    writeln!(v, "            end if;\n        end if;\n    end process;").unwrap();
+                }
+                NodeType::Rom(address_width, _) => {
+                    let address = vector("unsigned", &operands[..*address_width]);
+                    for (k, q) in node_outputs.iter().enumerate() {
+                        writeln!(v, "    {} <= m{}(to_integer({}))({});", q, i, address, k).unwrap();
+                    }
+                }
+                NodeType::Ram(address_width, _) => {
+                    let address = vector("unsigned", &operands[2..2 + address_width]);
+                    let data = vector("std_logic_vector", &operands[2 + address_width..]);
+                    writeln!(v, "    process({}) begin", operands[0]).unwrap();
+                    writeln!(v, "        if rising_edge({}) and {} = '1' then", operands[0], operands[1]).unwrap();
+                    writeln!(v, "            m{}(to_integer({})) <= {};", i, address, data).unwrap();
+                    writeln!(v, "        end if;\n    end process;").unwrap();
+                    for (k, q) in node_outputs.iter().enumerate() {
+                        writeln!(v, "    {} <= m{}(to_integer({}))({});", q, i, address, k).unwrap();
+                    }
+                }
+                gate => writeln!(v, "    {} <= {};", node_outputs[0], expression(gate, &operands)).unwrap(),
+            }
+        }
+        for (port, (_, s)) in output_ports.iter().zip(outputs) {
+            writeln!(v, "    {} <= {};", port, signal(*s)).unwrap();
+        }
+        v.push_str("end architecture;\n");
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let mut circuit = Circuit::new_with_basic_pattern(4);
+        Pattern::build_full_adder(&mut circuit);
+        circuit.get_patterns_mut().get_mut("full_adder").unwrap().set_description("one bit full adder");
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input[..3]).get_output().to_vec();
+        let q = circuit.apply_flipflop("rsff", &[input[3], input[1], sum[0]]).get_output()[1];
+        let n = circuit.add_gate("nand", &[q, input[0], input[2]]);
+
+        let v = circuit.to_vhdl("top", &["c0", "a", "b", "r"], &[("c1", sum[1]), ("out", n)]);
+        assert!(v.contains("-- one bit full adder\n"));
+        assert!(v.contains("entity full_adder is\n    port (\n        c0 : in std_logic;\n"));
+        assert!(v.contains("        c1 : out std_logic\n    );\nend entity;"));
+        assert!(v.contains("    n0 <= a xor b;"));
+        assert!(v.contains("    c1 <= n4;"));
+        assert!(v.contains("-- sequential"));
+        assert!(v.contains("entity top is"));
+        assert!(v.contains("    \\out\\ : out std_logic"));
+        assert!(v.contains("    signal s7 : std_logic := '1';"));
+        assert!(v.contains("    u0: entity work.full_adder port map (c0 => c0, a => a, b => b, s => s4, c1 => s5);"));
+        assert!(v.contains("    s8 <= not (s7 and c0 and b);"));
+        assert!(v.contains("    \\out\\ <= s8;"));
+        assert_eq!(v.matches("end architecture;").count(), 3);
+        assert_eq!(v.matches("library ieee;").count(), 3);
+        assert_eq!(vhdl_ident("a[0]"), "\\a[0]\\");
+        assert_eq!(vhdl_ident("a__b"), "\\a__b\\");
+        assert_eq!(vhdl_ident("Signal"), "\\Signal\\");
+    }
+
+    #[test]
+    fn test_memory() {
+        //地址和数据都从高位写起，内容不对称，位序反了就读出别的字
+        let mut circuit = Circuit::new(7);
+        let input = circuit.get_input();
+        let rom = circuit.add_rom(&input[..2], 4, vec![1, 2, 4, 8]).unwrap();
+        let ram = circuit.add_ram(input[2], input[3], &input[..2], &input[4..]).unwrap();
+        let single = circuit.add_rom(&input[..1], 1, vec![0, 1]).unwrap();
+        let v = circuit.to_vhdl("top", &["a0", "a1", "clk", "we", "d0", "d1", "d2"],
+            &[("rom", rom[0]), ("ram", ram[0]), ("single", single[0])]);
+        assert!(v.contains("    signal m0 : m0_t := (0 => \"0001\", 1 => \"0010\", 2 => \"0100\", 3 => \"1000\", others => (others => '0'));"));
+        assert!(v.contains("    s7 <= m0(to_integer(unsigned'(a1 & a0)))(0);"));
+        assert!(v.contains("            m1(to_integer(unsigned'(a1 & a0))) <= std_logic_vector'(d2 & d1 & d0);"));
+        assert!(v.contains("m2(to_integer(unsigned'(0 => a0)))(0);"));
+    }
+}
@@ -0,0 +1,319 @@
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2};
+use crate::circuit::Circuit;
+use crate::node::NodeType;
+use crate::signal::Signal;
+
+//名字列的宽度、每条波形的高度
+const NAME_WIDTH: f32 = 140.0;
+const LANE_HEIGHT: f32 = 24.0;
+
+//仿真历史：每个周期结束时所有信号的取值
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    samples: Vec<Vec<Signal>>,
+}
+
+impl History {
+    pub fn new() -> History { Self::default() }
+
+    pub fn push(&mut self, signals: &[Signal]) {
+        self.samples.push(signals.to_vec());
+    }
+
+    pub fn clear(&mut self) { self.samples.clear(); }
+
+    pub fn len(&self) -> usize { self.samples.len() }
+    pub fn is_empty(&self) -> bool { self.samples.is_empty() }
+
+    //信号在该周期之前还不存在时视为无效
+    pub fn get(&self, time: usize, signal: usize) -> Signal {
+        self.samples[time].get(signal).copied().unwrap_or_default()
+    }
+
+    //signals 从低位到高位，有无效位时返回 None
+    pub fn value(&self, time: usize, signals: &[usize]) -> Option<u64> {
+        signals.iter().enumerate().try_fold(0, |acc, (i, s)| {
+            let signal = self.get(time, *s);
+            signal.is_valid().then(|| acc | ((signal.get_unchecked() as u64) << i))
+        })
+    }
+
+    //按十六进制显示，宽度不限，无效的半字节写成 x
+    pub fn hex(&self, time: usize, signals: &[usize]) -> String {
+        signals.chunks(4).rev()
+            .map(|nibble| match self.value(time, nibble) {
+                Some(v) => char::from_digit(v as u32, 16).unwrap(),
+                None => 'x',
+            })
+            .collect()
+    }
+}
+
+impl Circuit {
+    //stimulus 的每一项为一个周期的输入，每个周期执行一次并记录
+    pub fn simulate_history(&mut self, stimulus: &[Vec<usize>]) -> History {
+        let mut history = History::new();
+        for inputs in stimulus {
+            self.execute_sequential_mut(inputs);
+            history.push(self.get_signals());
+        }
+        history
+    }
+
+    //输入用 input_name，门的输出为 "{门}{节点}"，pattern 的输出为 "{pattern}{节点}.{端口}"
+    pub fn signal_names(&self, input_name: &[&str]) -> Vec<String> {
+        let input_size = self.get_input().len();
+        (0..self.signals_size())
+            .map(|s| match self.get_parent_node_index(s) {
+                _ if s < input_size => input_name.get(s).map(|n| n.to_string()).unwrap_or_else(|| format!("i{}", s)),
+                Some(k) => {
+                    let node = self.get_node(k);
+                    match node.get_type() {
+                        NodeType::Pattern(name) => {
+                            let port = node.get_output().iter().position(|o| *o == s).unwrap();
+                            format!("{}{}.{}", name, k, self.get_pattern(name).output_port(port))
+                        }
+                        gate => format!("{:?}{}", gate, k).to_lowercase(),
+                    }
+                }
+                None => format!("s{}", s),
+            })
+            .collect()
+    }
+}
+
+//一条波形：一个信号，或者从低位到高位分组显示的总线
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lane {
+    name: String,
+    signals: Vec<usize>,
+}
+
+impl Lane {
+    pub fn bit(name: &str, signal: usize) -> Lane {
+        Self { name: name.to_string(), signals: vec![signal] }
+    }
+    pub fn bus(name: &str, signals: &[usize]) -> Lane {
+        Self { name: name.to_string(), signals: signals.to_vec() }
+    }
+    pub fn get_name(&self) -> &str { &self.name }
+    pub fn get_signals(&self) -> &Vec<usize> { &self.signals }
+    pub fn width(&self) -> usize { self.signals.len() }
+}
+
+//逻辑分析仪式的波形窗口：缩放、滚动时间轴，两个光标显示时间差
+#[derive(Debug, Clone)]
+pub struct WaveformViewer {
+    lanes: Vec<Lane>,
+    //每个周期的像素宽度和最左侧的周期
+    zoom: f32,
+    offset: f32,
+    //左键放置光标 A，右键放置光标 B
+    cursors: [Option<usize>; 2],
+    //勾选后可以合并成总线
+    selected: Vec<bool>,
+}
+
+impl Default for WaveformViewer {
+    fn default() -> Self {
+        Self { lanes: Vec::new(), zoom: 24.0, offset: 0.0, cursors: [None, None], selected: Vec::new() }
+    }
+}
+
+impl WaveformViewer {
+    pub fn new(lanes: Vec<Lane>) -> WaveformViewer {
+        let selected = vec![false; lanes.len()];
+        Self { lanes, selected, ..Self::default() }
+    }
+
+    pub fn get_lanes(&self) -> &Vec<Lane> { &self.lanes }
+
+    pub fn add_lane(&mut self, lane: Lane) {
+        self.lanes.push(lane);
+        self.selected.push(false);
+    }
+
+    pub fn remove_lane(&mut self, index: usize) {
+        self.lanes.remove(index);
+        self.selected.remove(index);
+    }
+
+    //把勾选的波形按顺序合并成一条总线，第一条为最低位
+    pub fn group(&mut self, name: &str) {
+        let signals = self.lanes.iter().zip(&self.selected)
+            .filter(|(_, s)| **s)
+            .flat_map(|(l, _)| l.signals.iter().copied())
+            .collect::<Vec<usize>>();
+        if signals.is_empty() { return; }
+        let first = self.selected.iter().position(|s| *s).unwrap();
+        let lanes = std::mem::take(&mut self.lanes);
+        self.lanes = lanes.into_iter().zip(&self.selected).filter(|(_, s)| !**s).map(|(l, _)| l).collect();
+        self.lanes.insert(first.min(self.lanes.len()), Lane::bus(name, &signals));
+        self.selected = vec![false; self.lanes.len()];
+    }
+
+    //names 为可以加入的信号名，下标即信号编号
+    pub fn show(&mut self, ui: &mut egui::Ui, history: &History, names: &[String]) {
+        let length = history.len();
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut self.zoom, 4.0..=100.0).logarithmic(true).text("zoom"));
+            ui.add(egui::Slider::new(&mut self.offset, 0.0..=length.saturating_sub(1) as f32).text("cycle"));
+            egui::ComboBox::from_id_salt("add_lane")
+                .selected_text("add signal")
+                .show_ui(ui, |ui| {
+                    for (s, name) in names.iter().enumerate() {
+                        if ui.selectable_label(false, name).clicked() {
+                            self.add_lane(Lane::bit(name, s));
+                        }
+                    }
+                });
+            if ui.button("group").clicked() {
+                let name = format!("bus{}", self.lanes.len());
+                self.group(&name);
+            }
+        });
+        ui.horizontal(|ui| {
+            let show = |c: Option<usize>| c.map_or("-".to_string(), |t| t.to_string());
+            ui.label(format!("A: {}  B: {}", show(self.cursors[0]), show(self.cursors[1])));
+            if let [Some(a), Some(b)] = self.cursors {
+                ui.label(format!("Δ = {} cycles", a.abs_diff(b)));
+            }
+        });
+
+        let mut remove = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let width = ui.available_width();
+            let height = LANE_HEIGHT * (self.lanes.len() + 1) as f32;
+            let (response, painter) = ui.allocate_painter(Vec2::new(width, height), Sense::click_and_drag());
+            let rect = response.rect;
+            let plot = Rect::from_min_max(Pos2::new(rect.min.x + NAME_WIDTH, rect.min.y), rect.max);
+            let font = FontId::monospace(11.0);
+
+            //滚轮滚动时间轴，按住 ctrl 缩放；拖动平移
+            if response.hovered() {
+                let (scroll, zoom) = ui.input(|i| (i.smooth_scroll_delta, i.zoom_delta()));
+                self.zoom = (self.zoom * zoom).clamp(4.0, 100.0);
+                self.offset -= scroll.x / self.zoom;
+            }
+            if response.dragged() {
+                self.offset -= response.drag_delta().x / self.zoom;
+            }
+            self.offset = self.offset.clamp(0.0, length.saturating_sub(1) as f32);
+            let x = |t: f32| plot.min.x + (t - self.offset) * self.zoom;
+            let time_at = |pos: Pos2| ((pos.x - plot.min.x) / self.zoom + self.offset).max(0.0) as usize;
+            for (button, cursor) in [(response.clicked(), 0), (response.secondary_clicked(), 1)] {
+                if let (true, Some(pos)) = (button, response.interact_pointer_pos()) && plot.contains(pos) {
+                    self.cursors[cursor] = Some(time_at(pos).min(length.saturating_sub(1)));
+                }
+            }
+
+            let visible = (self.offset as usize)..((self.offset + plot.width() / self.zoom).ceil() as usize + 1).min(length);
+            let clip = painter.with_clip_rect(plot);
+            //时间刻度
+            let step = ((40.0 / self.zoom).ceil() as usize).max(1);
+            for t in visible.clone().filter(|t| t % step == 0) {
+                clip.line_segment([Pos2::new(x(t as f32), plot.min.y), Pos2::new(x(t as f32), plot.max.y)], Stroke::new(0.5, Color32::from_gray(60)));
+                clip.text(Pos2::new(x(t as f32) + 2.0, plot.min.y + 2.0), Align2::LEFT_TOP, t, font.clone(), Color32::GRAY);
+            }
+
+            for (l, lane) in self.lanes.iter().enumerate() {
+                let top = rect.min.y + LANE_HEIGHT * (l + 1) as f32;
+                let (high, low, mid) = (top + 4.0, top + LANE_HEIGHT - 4.0, top + LANE_HEIGHT / 2.0);
+                let label = match self.cursors[0] {
+                    Some(t) if lane.width() == 1 => format!("{} = {:?}", lane.name, history.get(t, lane.signals[0])),
+                    Some(t) => format!("{} = {}", lane.name, history.hex(t, &lane.signals)),
+                    None => lane.name.clone(),
+                };
+                painter.text(Pos2::new(rect.min.x + 20.0, mid), Align2::LEFT_CENTER, label, font.clone(), Color32::LIGHT_GRAY);
+                let checkbox = Rect::from_center_size(Pos2::new(rect.min.x + 8.0, mid), Vec2::splat(12.0));
+                if ui.put(checkbox, egui::Checkbox::without_text(&mut self.selected[l])).secondary_clicked() {
+                    remove = Some(l);
+                }
+
+                for t in visible.clone() {
+                    let (x0, x1) = (x(t as f32), x(t as f32 + 1.0));
+                    if lane.width() == 1 {
+                        let signal = history.get(t, lane.signals[0]);
+                        if !signal.is_valid() {
+                            clip.rect_filled(Rect::from_min_max(Pos2::new(x0, high), Pos2::new(x1, low)), 0.0, Color32::from_rgba_unmultiplied(200, 0, 0, 60));
+                            continue;
+                        }
+                        let y = if signal.get_unchecked() { high } else { low };
+                        let color = Color32::GREEN;
+                        clip.line_segment([Pos2::new(x0, y), Pos2::new(x1, y)], Stroke::new(1.5, color));
+                        if t > 0 && history.get(t - 1, lane.signals[0]) != signal {
+                            clip.line_segment([Pos2::new(x0, high), Pos2::new(x0, low)], Stroke::new(1.5, color));
+                        }
+                    } else {
+                        //总线：取值改变处画交叉，新值写在段的开头
+                        let value = history.hex(t, &lane.signals);
+                        let changed = t == 0 || t == visible.start || history.hex(t - 1, &lane.signals) != value;
+                        let color = if value.contains('x') { Color32::RED } else { Color32::GREEN };
+                        let slope = if changed { 3.0_f32.min(self.zoom / 4.0) } else { 0.0 };
+                        clip.line_segment([Pos2::new(x0 + slope, high), Pos2::new(x1, high)], Stroke::new(1.0, color));
+                        clip.line_segment([Pos2::new(x0 + slope, low), Pos2::new(x1, low)], Stroke::new(1.0, color));
+                        if changed {
+                            clip.line_segment([Pos2::new(x0, mid), Pos2::new(x0 + slope, high)], Stroke::new(1.0, color));
+                            clip.line_segment([Pos2::new(x0, mid), Pos2::new(x0 + slope, low)], Stroke::new(1.0, color));
+                            clip.text(Pos2::new(x0 + slope + 2.0, mid), Align2::LEFT_CENTER, &value, font.clone(), Color32::WHITE);
+                        }
+                    }
+                }
+            }
+
+            for (cursor, color) in self.cursors.iter().zip([Color32::YELLOW, Color32::LIGHT_BLUE]) {
+                if let Some(t) = cursor {
+                    let cx = x(*t as f32 + 0.5);
+                    clip.line_segment([Pos2::new(cx, plot.min.y), Pos2::new(cx, plot.max.y)], Stroke::new(1.0, color));
+                }
+            }
+        });
+        if let Some(l) = remove {
+            self.remove_lane(l);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn test_history() {
+        let mut circuit = Circuit::new(3);
+        Pattern::build_full_adder(&mut circuit);
+        let input = circuit.get_input();
+        let sum = circuit.apply_pattern("full_adder", &input).get_output().to_vec();
+        let n = circuit.add_gate("not", &[sum[1]]);
+
+        let stimulus = (0..8).map(|v| vec![v & 1, (v >> 1) & 1, v >> 2]).collect::<Vec<Vec<usize>>>();
+        let history = circuit.simulate_history(&stimulus);
+        assert_eq!(history.len(), 8);
+        for t in 0..8 {
+            let ones = (t & 1) + ((t >> 1) & 1) + (t >> 2);
+            assert_eq!(history.value(t, &input), Some(t as u64));
+            assert_eq!(history.value(t, &sum), Some(ones as u64));
+            assert_eq!(history.get(t, n).get_unchecked(), ones < 2);
+        }
+
+        let names = circuit.signal_names(&["c0", "a"]);
+        assert_eq!(names[..3], ["c0", "a", "i2"]);
+        assert_eq!(names[sum[0]], "full_adder0.s");
+        assert_eq!(names[sum[1]], "full_adder0.c1");
+        assert_eq!(names[n], "not1");
+
+        //超过 4 位的总线按半字节分组，含无效位的半字节为 x
+        let mut history = History::new();
+        let bits = [1, 0, 1, 0, 0, 1, 0, 1].map(Signal::from_usize);
+        history.push(&bits);
+        assert_eq!(history.hex(0, &[0, 1, 2, 3, 4, 5, 6, 7]), "a5");
+        assert_eq!(history.hex(0, &[0, 1, 2, 3, 4, 5, 6, 7, 8]), "xa5");
+        assert_eq!(history.value(0, &[0, 9]), None);
+
+        let mut viewer = WaveformViewer::new(vec![Lane::bit("c0", 0), Lane::bit("s", sum[0]), Lane::bit("c1", sum[1])]);
+        viewer.selected = vec![false, true, true];
+        viewer.group("sum");
+        assert_eq!(viewer.get_lanes(), &vec![Lane::bit("c0", 0), Lane::bus("sum", &sum)]);
+    }
+}